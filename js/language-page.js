@@ -4,8 +4,17 @@ const NUMERIC_HEADERS = new Set([
   "Forks",
   "Watchers",
   "Open Issues",
+  "Closed Issues",
+  "Issue Close Rate",
+  "Releases",
+  "Open PRs",
+  "Merged PRs",
   "Size (KB)",
+  "Age (Years)",
+  "Days Since Last Commit",
+  "Health Score",
 ]);
+const ISO_SUFFIX = " (ISO)";
 const HEADER_TO_CLASS_MAP = {
   Ranking: "td-ranking",
   Stars: "td-stars",
@@ -45,8 +54,34 @@ function createTable(data) {
     headers.indexOf("Repository") !== -1
       ? headers.indexOf("Repository")
       : headers.indexOf("Repo URL");
+  // README excerpts are not shown as a column; they stand in for empty descriptions.
+  const excerptIndex = headers.indexOf("README Excerpt");
+  const descriptionIndex = headers.indexOf("Description");
+  // Avatars are not shown as a column either; they go next to the owner.
+  const avatarIndex = headers.indexOf("Owner Avatar");
+  const ownerIndex = headers.indexOf("Owner");
+  // Projects accepting sponsorship get a badge next to their name instead.
+  const sponsorableIndex = headers.indexOf("Sponsorable");
+  const nameIndex = headers.indexOf("Project Name");
+  // Owner types are only used to filter the rows.
+  const ownerTypeIndex = headers.indexOf("Owner Type");
+  // Languages get a dot of their GitHub color instead of a color column.
+  const colorIndex = headers.indexOf("Language Color");
+  const languageIndex = headers.indexOf("Language");
+  // Watchers repeating the stars tell nothing.
+  const mirroredIndex = mirroredWatchersIndex(data);
+  // Dates are sorted by their " (ISO)" column, which is not shown either.
+  const isHidden = (colIndex) =>
+    colIndex === excerptIndex ||
+    colIndex === avatarIndex ||
+    colIndex === sponsorableIndex ||
+    colIndex === colorIndex ||
+    colIndex === ownerTypeIndex ||
+    colIndex === mirroredIndex ||
+    headers[colIndex].endsWith(ISO_SUFFIX);
 
-  headers.forEach((colText) => {
+  headers.forEach((colText, colIndex) => {
+    if (isHidden(colIndex)) return;
     const th = document.createElement("th");
     th.textContent = colText;
     if (NUMERIC_HEADERS.has(colText)) {
@@ -63,6 +98,7 @@ function createTable(data) {
     if (!rowData || rowData.length < headers.length) continue;
 
     const row = document.createElement("tr");
+    row.dataset.row = i;
 
     if (repoUrlIndex !== -1 && rowData[repoUrlIndex]) {
       row.style.cursor = "pointer";
@@ -72,12 +108,20 @@ function createTable(data) {
     }
 
     rowData.forEach((cellText, colIndex) => {
+      if (isHidden(colIndex)) return;
+      if (colIndex === descriptionIndex && !cellText && excerptIndex !== -1) {
+        cellText = rowData[excerptIndex];
+      }
       const td = document.createElement("td");
       const headerText = headers[colIndex];
 
       if (HEADER_TO_CLASS_MAP[headerText]) {
         td.classList.add(HEADER_TO_CLASS_MAP[headerText]);
       }
+      const isoIndex = headers.indexOf(headerText + ISO_SUFFIX);
+      if (isoIndex !== -1 && rowData[isoIndex]) {
+        td.setAttribute("data-value", rowData[isoIndex]);
+      }
 
       if (colIndex === repoUrlIndex && cellText) {
         const link = document.createElement("a");
@@ -86,6 +130,24 @@ function createTable(data) {
         link.textContent = cellText.replace("https://github.com/", "");
         link.addEventListener("click", (e) => e.stopPropagation());
         td.appendChild(link);
+      } else if (colIndex === ownerIndex && rowData[avatarIndex]) {
+        const avatar = document.createElement("img");
+        avatar.src = rowData[avatarIndex];
+        avatar.alt = "";
+        avatar.loading = "lazy";
+        avatar.classList.add("owner-avatar");
+        td.appendChild(avatar);
+        td.appendChild(document.createTextNode(cellText));
+      } else if (colIndex === nameIndex && rowData[sponsorableIndex] === "true") {
+        td.appendChild(document.createTextNode(cellText));
+        const badge = document.createElement("span");
+        badge.textContent = "♥";
+        badge.title = "Accepts sponsorship";
+        badge.classList.add("sponsor-badge");
+        td.appendChild(badge);
+      } else if (colIndex === languageIndex && rowData[colorIndex]) {
+        td.appendChild(languageDot(rowData[colorIndex]));
+        td.appendChild(document.createTextNode(cellText));
       } else {
         td.textContent = truncateStringAtWord(cellText, 150);
       }
@@ -97,6 +159,66 @@ function createTable(data) {
   return table;
 }
 
+// Project hygiene facets: each checked column has to be "true" for a row to show.
+const HYGIENE_FACETS = [
+  ["Security Policy", "Has a security policy"],
+  ["CI", "Runs CI"],
+];
+
+// Owner type facet: rows owned by another type of account are hidden.
+const OWNER_TYPE_OPTIONS = [
+  ["", "All owners"],
+  ["organization", "Organizations"],
+  ["user", "Individuals"],
+];
+
+function createFacets(data, table) {
+  const headers = data[0];
+  const facets = HYGIENE_FACETS.filter(([column]) => headers.includes(column));
+  const ownerTypeIndex = headers.indexOf("Owner Type");
+  if (!facets.length && ownerTypeIndex === -1) return null;
+
+  const facetDiv = document.createElement("div");
+  facetDiv.className = "facets";
+  const checked = new Set();
+  let ownerType = "";
+  const update = () => {
+    for (const row of table.tBodies[0].rows) {
+      const rowData = data[Number(row.dataset.row)];
+      row.hidden =
+        [...checked].some((index) => rowData[index] !== "true") ||
+        (ownerType !== "" && rowData[ownerTypeIndex] !== ownerType);
+    }
+  };
+  if (ownerTypeIndex !== -1) {
+    const select = document.createElement("select");
+    select.setAttribute("aria-label", "Owner type");
+    OWNER_TYPE_OPTIONS.forEach(([value, label]) => {
+      select.appendChild(new Option(label, value));
+    });
+    select.addEventListener("change", () => {
+      ownerType = select.value;
+      update();
+    });
+    facetDiv.appendChild(select);
+  }
+  facets.forEach(([column, label]) => {
+    const index = headers.indexOf(column);
+    const checkbox = document.createElement("input");
+    checkbox.type = "checkbox";
+    checkbox.addEventListener("change", () => {
+      if (checkbox.checked) checked.add(index);
+      else checked.delete(index);
+      update();
+    });
+    const labelElement = document.createElement("label");
+    labelElement.appendChild(checkbox);
+    labelElement.appendChild(document.createTextNode(label));
+    facetDiv.appendChild(labelElement);
+  });
+  return facetDiv;
+}
+
 document.addEventListener("DOMContentLoaded", () => {
   const languageContentDiv = document.getElementById("language-content");
   const loadingMessage = document.getElementById("loading-message");
@@ -119,11 +241,29 @@ document.addEventListener("DOMContentLoaded", () => {
     };
     return nameMap[lang] || lang;
   }
-  const displayName = getDisplayName(language);
 
-  const pageTitle = `kstars: Top 1000 GitHub Repos for ${displayName}`;
-  languageTitle.textContent = `kstars ${displayName}`;
-  document.title = pageTitle;
+  function setTitle(displayName) {
+    languageTitle.textContent = `kstars ${displayName}`;
+    document.title = `kstars: Top 1000 GitHub Repos for ${displayName}`;
+  }
+  setTitle(getDisplayName(language));
+
+  // The manifest has the display names of every language.
+  fetch("../data/processed/manifest.json")
+    .then((response) => (response.ok ? response.json() : Promise.reject()))
+    .then((manifest) => {
+      if (!isSupportedSchema(manifest)) return;
+      const entry = manifest.languages.find((lang) => lang.name === language);
+      if (entry) setTitle(entry.display_name);
+    })
+    .catch(() => {});
+
+  // The language comes from the URL, so messages naming it are set as text.
+  function showMessage(text) {
+    const message = document.createElement("p");
+    message.textContent = text;
+    languageContentDiv.replaceChildren(message);
+  }
 
   const csvPath = `../data/processed/${language}.csv`;
 
@@ -132,21 +272,27 @@ document.addEventListener("DOMContentLoaded", () => {
     skipEmptyLines: "greedy",
     complete: function (results) {
       loadingMessage.style.display = "none";
-      if (results.data && results.data.length > 1) {
+      const missing = results.data?.length ? missingColumns(results.data[0]) : [];
+      if (missing.length) {
+        console.error(`${language} ranking is missing: ${missing.join(", ")}`);
+        showMessage(`Could not read repository data for ${language}: missing ${missing.join(", ")}.`);
+      } else if (results.data && results.data.length > 1) {
         const tableContainer = document.createElement("div");
         tableContainer.className = "table-container";
         const table = createTable(results.data);
         tableContainer.appendChild(table);
+        const facet = createFacets(results.data, table);
+        if (facet) languageContentDiv.appendChild(facet);
         languageContentDiv.appendChild(tableContainer);
         Sortable.init();
       } else {
-        languageContentDiv.innerHTML = `<p>No repository data found for ${language}.</p>`;
+        showMessage(`No repository data found for ${language}.`);
       }
     },
     error: function (err) {
       loadingMessage.style.display = "none";
       console.error(`Error loading CSV for ${language} from ${csvPath}:`, err);
-      languageContentDiv.innerHTML = `<p>Could not load repository data for ${language}.</p>`;
+      showMessage(`Could not load repository data for ${language}.`);
     },
   });
 
@@ -4,8 +4,17 @@ const NUMERIC_HEADERS = new Set([
   "Forks",
   "Watchers",
   "Open Issues",
+  "Closed Issues",
+  "Issue Close Rate",
+  "Releases",
+  "Open PRs",
+  "Merged PRs",
   "Size (KB)",
+  "Age (Years)",
+  "Days Since Last Commit",
+  "Health Score",
 ]);
+const ISO_SUFFIX = " (ISO)";
 const HEADER_TO_CLASS_MAP = {
   Ranking: "td-ranking",
   Stars: "td-stars",
@@ -35,7 +44,8 @@ function loadCSV(language, folder, prefix) {
       const headerDiv = document.createElement("div");
       headerDiv.classList.add("language-header");
       const h2 = document.createElement("h2");
-      h2.textContent = language[1];
+      if (language[2]) h2.appendChild(languageDot(language[2]));
+      h2.appendChild(document.createTextNode(language[1]));
       headerDiv.appendChild(h2);
       const link = document.createElement("a");
       link.href = `pages/language.html?lang=${encodeURIComponent(language[0])}`;
@@ -44,7 +54,15 @@ function loadCSV(language, folder, prefix) {
       headerDiv.appendChild(link);
       sectionDiv.appendChild(headerDiv);
 
-      if (results.data && results.data.length > 1) {
+      const missing = results.data?.length ? missingColumns(results.data[0]) : [];
+      if (missing.length) {
+        console.error(`${language[0]} ranking is missing: ${missing.join(", ")}`);
+        sectionDiv.appendChild(
+          document.createTextNode(
+            `Could not read preview data: missing ${missing.join(", ")}.`,
+          ),
+        );
+      } else if (results.data && results.data.length > 1) {
         const tableContainer = document.createElement("div");
         tableContainer.className = "table-container";
         const table = createTable(results.data, 10); // Show top 10
@@ -87,8 +105,34 @@ function createTable(data, maxRows) {
     headers.indexOf("Repository") !== -1
       ? headers.indexOf("Repository")
       : headers.indexOf("Repo URL");
+  // README excerpts are not shown as a column; they stand in for empty descriptions.
+  const excerptIndex = headers.indexOf("README Excerpt");
+  const descriptionIndex = headers.indexOf("Description");
+  // Avatars are not shown as a column either; they go next to the owner.
+  const avatarIndex = headers.indexOf("Owner Avatar");
+  const ownerIndex = headers.indexOf("Owner");
+  // Projects accepting sponsorship get a badge next to their name instead.
+  const sponsorableIndex = headers.indexOf("Sponsorable");
+  const nameIndex = headers.indexOf("Project Name");
+  // Owner types are only used to filter the rows.
+  const ownerTypeIndex = headers.indexOf("Owner Type");
+  // Languages get a dot of their GitHub color instead of a color column.
+  const colorIndex = headers.indexOf("Language Color");
+  const languageIndex = headers.indexOf("Language");
+  // Watchers repeating the stars tell nothing.
+  const mirroredIndex = mirroredWatchersIndex(data);
+  // Dates are sorted by their " (ISO)" column, which is not shown either.
+  const isHidden = (colIndex) =>
+    colIndex === excerptIndex ||
+    colIndex === avatarIndex ||
+    colIndex === sponsorableIndex ||
+    colIndex === colorIndex ||
+    colIndex === ownerTypeIndex ||
+    colIndex === mirroredIndex ||
+    headers[colIndex].endsWith(ISO_SUFFIX);
 
-  headers.forEach((colText) => {
+  headers.forEach((colText, colIndex) => {
+    if (isHidden(colIndex)) return;
     const th = document.createElement("th");
     th.textContent = colText;
     if (NUMERIC_HEADERS.has(colText)) {
@@ -118,12 +162,20 @@ function createTable(data, maxRows) {
     }
 
     rowData.forEach((cellText, colIndex) => {
+      if (isHidden(colIndex)) return;
+      if (colIndex === descriptionIndex && !cellText && excerptIndex !== -1) {
+        cellText = rowData[excerptIndex];
+      }
       const td = document.createElement("td");
       const headerText = headers[colIndex];
 
       if (HEADER_TO_CLASS_MAP[headerText]) {
         td.classList.add(HEADER_TO_CLASS_MAP[headerText]);
       }
+      const isoIndex = headers.indexOf(headerText + ISO_SUFFIX);
+      if (isoIndex !== -1 && rowData[isoIndex]) {
+        td.setAttribute("data-value", rowData[isoIndex]);
+      }
 
       if (colIndex === repoUrlIndex && cellText) {
         const link = document.createElement("a");
@@ -132,6 +184,24 @@ function createTable(data, maxRows) {
         link.textContent = cellText.replace("https://github.com/", "");
         link.addEventListener("click", (e) => e.stopPropagation());
         td.appendChild(link);
+      } else if (colIndex === ownerIndex && rowData[avatarIndex]) {
+        const avatar = document.createElement("img");
+        avatar.src = rowData[avatarIndex];
+        avatar.alt = "";
+        avatar.loading = "lazy";
+        avatar.classList.add("owner-avatar");
+        td.appendChild(avatar);
+        td.appendChild(document.createTextNode(cellText));
+      } else if (colIndex === nameIndex && rowData[sponsorableIndex] === "true") {
+        td.appendChild(document.createTextNode(cellText));
+        const badge = document.createElement("span");
+        badge.textContent = "♥";
+        badge.title = "Accepts sponsorship";
+        badge.classList.add("sponsor-badge");
+        td.appendChild(badge);
+      } else if (colIndex === languageIndex && rowData[colorIndex]) {
+        td.appendChild(languageDot(rowData[colorIndex]));
+        td.appendChild(document.createTextNode(cellText));
       } else {
         td.textContent = truncateStringAtWord(cellText, 150);
       }
@@ -185,19 +255,35 @@ document.addEventListener("DOMContentLoaded", function () {
     });
   }
 
-  languages.forEach((lang) => {
-    const link = document.createElement("a");
-    link.href = `#${lang[0]}`;
-    link.textContent = lang[1];
-    navLinksDiv.appendChild(link);
-  });
+  loadLanguages().then((loaded) => {
+    languages = loaded;
+    languages.forEach((lang) => {
+      const link = document.createElement("a");
+      link.href = `#${lang[0]}`;
+      link.textContent = lang[1];
+      navLinksDiv.appendChild(link);
+    });
 
-  languages.forEach((language) =>
-    loadCSV(language, "data/processed", "top10_"),
-  );
+    languages.forEach((language) =>
+      loadCSV(language, "data/processed", "top10_"),
+    );
+  });
 });
 
-const languages = [
+// Languages of the dataset manifest, or the built-in list if it can't be read.
+function loadLanguages() {
+  return fetch("data/processed/manifest.json")
+    .then((response) => (response.ok ? response.json() : Promise.reject()))
+    .then((manifest) => (isSupportedSchema(manifest) ? manifest : Promise.reject()))
+    .then((manifest) =>
+      manifest.languages
+        .filter((lang) => !lang.trending)
+        .map((lang) => [lang.name, lang.display_name, lang.color]),
+    )
+    .catch(() => DEFAULT_LANGUAGES);
+}
+
+const DEFAULT_LANGUAGES = [
   ["ActionScript", "ActionScript"],
   ["C", "C"],
   ["CSharp", "C#"],
@@ -235,15 +321,6 @@ const languages = [
   ["Vim-script", "Vim script"],
 ];
 
+let languages = DEFAULT_LANGUAGES;
 const contentDiv = document.getElementById("content");
-const navLinksDiv = document.getElementById("language-nav-links");
 let loadedLanguagesCount = 0;
-
-languages.forEach((lang) => {
-  const link = document.createElement("a");
-  link.href = `#${lang[0]}`;
-  link.textContent = lang[1];
-  navLinksDiv.appendChild(link);
-});
-
-languages.forEach((language) => loadCSV(language, "data/processed", "top10_"));
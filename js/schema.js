@@ -0,0 +1,51 @@
+// Layout of the processed rankings, shared by the home and language pages.
+
+// Schema versions of data/processed/manifest.json this site can read. Version 1
+// rankings have no " (ISO)" date columns; rankings published without a manifest
+// predate both and are read like version 1. Before version 3, "Watchers" may only
+// repeat the stars.
+const SUPPORTED_SCHEMA_VERSIONS = [1, 2, 3];
+
+// Columns the tables need, each with every name it has been published under.
+const REQUIRED_COLUMNS = [
+  ["Ranking"],
+  ["Project Name"],
+  ["Stars"],
+  ["Repo URL", "Repository"],
+];
+
+// Whether the site knows how to read the rankings of `manifest`.
+function isSupportedSchema(manifest) {
+  if (SUPPORTED_SCHEMA_VERSIONS.includes(manifest.schema_version)) return true;
+  console.warn(
+    `Unsupported dataset schema version ${manifest.schema_version}; ` +
+      `this page reads versions ${SUPPORTED_SCHEMA_VERSIONS.join(", ")}.`,
+  );
+  return false;
+}
+
+// Required columns missing from `headers`, by their current name.
+function missingColumns(headers) {
+  return REQUIRED_COLUMNS.filter(
+    (names) => !names.some((name) => headers.includes(name)),
+  ).map((names) => names[0]);
+}
+
+// Index of a "Watchers" column that only repeats "Stars" in `data`, as older
+// rankings have them, or -1.
+function mirroredWatchersIndex(data) {
+  const headers = data[0];
+  const watchers = headers.indexOf("Watchers");
+  const stars = headers.indexOf("Stars");
+  if (watchers === -1 || stars === -1 || data.length < 2) return -1;
+  const rows = data.slice(1);
+  return rows.every((row) => row[watchers] === row[stars]) ? watchers : -1;
+}
+
+// Dot of a language's GitHub color, put before its name.
+function languageDot(color) {
+  const dot = document.createElement("span");
+  dot.style.backgroundColor = color;
+  dot.classList.add("language-dot");
+  return dot;
+}
@@ -0,0 +1,198 @@
+use crate::{diff::LanguageDiff, error::Result};
+use anyhow::Context;
+use serde::Serialize;
+use std::{fs, path::Path};
+use tracing::info;
+
+/// Changes of a diff worth an alert.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlertRules {
+    /// Sizes of the tops whose new entrants are alerted, e.g. `[10, 100]`.
+    pub top_n: Vec<usize>,
+    /// Repositories climbing more than this many positions are alerted.
+    pub min_rank_gain: Option<i64>,
+}
+
+impl AlertRules {
+    /// Whether no rule was given.
+    pub fn is_empty(&self) -> bool {
+        self.top_n.is_empty() && self.min_rank_gain.is_none()
+    }
+}
+
+/// What an alert is about.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum AlertKind {
+    /// The repository is in the top `n` of the language and was not before.
+    EnteredTop { n: usize },
+    /// The repository climbed `ranks` positions.
+    RankGain { ranks: i64 },
+}
+
+/// A change of the ranking of a language that matched a rule.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub language: String,
+    pub name: String,
+    pub url: String,
+    /// `None` when the repository was not ranked before.
+    pub old_rank: Option<usize>,
+    pub new_rank: usize,
+    #[serde(flatten)]
+    pub kind: AlertKind,
+}
+
+impl Alert {
+    /// One-line description, e.g. "Rust: serde entered the top 10 (#7, was #14)."
+    pub fn message(&self) -> String {
+        let was = match self.old_rank {
+            Some(rank) => format!("was #{}", rank),
+            None => "new".to_string(),
+        };
+        match self.kind {
+            AlertKind::EnteredTop { n } => format!(
+                "{}: {} entered the top {} (#{}, {}).",
+                self.language, self.name, n, self.new_rank, was
+            ),
+            AlertKind::RankGain { ranks } => format!(
+                "{}: {} climbed {} positions (#{}, {}).",
+                self.language, self.name, ranks, self.new_rank, was
+            ),
+        }
+    }
+}
+
+/// Smallest of the `top_n` that `new_rank` is in and `old_rank` was not.
+fn entered_top(top_n: &[usize], old_rank: Option<usize>, new_rank: usize) -> Option<usize> {
+    top_n
+        .iter()
+        .copied()
+        .filter(|&n| new_rank <= n && old_rank.is_none_or(|old| old > n))
+        .min()
+}
+
+/// Alerts of `diffs` under `rules`, language by language in ranking order.
+pub fn evaluate(diffs: &[LanguageDiff], rules: &AlertRules) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+    for LanguageDiff { language, diff } in diffs {
+        let alert = |name: &str, url: &str, old_rank, new_rank, kind| Alert {
+            language: language.clone(),
+            name: name.to_string(),
+            url: url.to_string(),
+            old_rank,
+            new_rank,
+            kind,
+        };
+        let mut changes: Vec<Alert> = Vec::new();
+        for repo in &diff.entered {
+            if let Some(n) = entered_top(&rules.top_n, None, repo.rank) {
+                changes.push(alert(
+                    &repo.name,
+                    &repo.url,
+                    None,
+                    repo.rank,
+                    AlertKind::EnteredTop { n },
+                ));
+            }
+        }
+        for change in &diff.moved {
+            let (old_rank, new_rank) = (Some(change.old_rank), change.new_rank);
+            if let Some(n) = entered_top(&rules.top_n, old_rank, new_rank) {
+                changes.push(alert(
+                    &change.name,
+                    &change.url,
+                    old_rank,
+                    new_rank,
+                    AlertKind::EnteredTop { n },
+                ));
+            }
+            if rules
+                .min_rank_gain
+                .is_some_and(|min| change.rank_delta > min)
+            {
+                changes.push(alert(
+                    &change.name,
+                    &change.url,
+                    old_rank,
+                    new_rank,
+                    AlertKind::RankGain {
+                        ranks: change.rank_delta,
+                    },
+                ));
+            }
+        }
+        changes.sort_by_key(|alert| alert.new_rank);
+        alerts.extend(changes);
+    }
+    alerts
+}
+
+/// Logs every alert and writes them to `output` as JSON, if given.
+pub fn report(alerts: &[Alert], output: Option<&Path>) -> Result<()> {
+    for alert in alerts {
+        info!("{}", alert.message());
+    }
+    if let Some(path) = output {
+        let content = serde_json::to_string_pretty(alerts)? + "\n";
+        fs::write(path, content).with_context(|| format!("Failed to write {:?}", path))?;
+        info!("{} alert(s) written to {:?}", alerts.len(), path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlertKind, AlertRules, evaluate};
+    use crate::diff::{LanguageDiff, RankedRepo, diff_rankings};
+
+    fn ranking(names: &[&str]) -> Vec<RankedRepo> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| RankedRepo {
+                name: name.to_string(),
+                url: format!("https://github.com/example/{}", name),
+                rank: i + 1,
+                stars: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_alerts_for_new_top_entries_and_big_climbs() {
+        let old = ranking(&["a", "b", "c", "d", "e"]);
+        let new = ranking(&["a", "e", "new", "b", "c"]);
+        let diffs = [LanguageDiff {
+            language: "Rust".to_string(),
+            diff: diff_rankings(&old, &new),
+        }];
+        let rules = AlertRules {
+            top_n: vec![2, 3],
+            min_rank_gain: Some(2),
+        };
+
+        let alerts = evaluate(&diffs, &rules);
+        let kinds: Vec<(&str, &AlertKind)> = alerts
+            .iter()
+            .map(|alert| (alert.name.as_str(), &alert.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("e", &AlertKind::EnteredTop { n: 2 }),
+                ("e", &AlertKind::RankGain { ranks: 3 }),
+                ("new", &AlertKind::EnteredTop { n: 3 }),
+            ]
+        );
+        assert_eq!(
+            alerts[0].message(),
+            "Rust: e entered the top 2 (#2, was #5)."
+        );
+        assert_eq!(
+            alerts[2].message(),
+            "Rust: new entered the top 3 (#3, new)."
+        );
+        assert!(evaluate(&diffs, &AlertRules::default()).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::error::Result;
+use anyhow::Context;
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Suffix of the file written until it replaces its destination.
+pub const TEMP_SUFFIX: &str = ".tmp";
+
+/// File written next to its destination as "<name>.tmp" and renamed over it on
+/// [`AtomicFile::commit`], so readers never see a truncated file when the run
+/// dies halfway. The temporary file is deleted if it is dropped uncommitted.
+pub struct AtomicFile {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    temp_path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> Result<Self> {
+        let mut temp_name = path.file_name().map(OsString::from).unwrap_or_default();
+        temp_name.push(TEMP_SUFFIX);
+        let temp_path = path.with_file_name(temp_name);
+        let file = File::create(&temp_path)
+            .with_context(|| format!("Failed to create file: {:?}", temp_path))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            path: path.to_path_buf(),
+            temp_path,
+            committed: false,
+        })
+    }
+
+    /// Syncs the file to disk and moves it to its destination.
+    pub fn commit(mut self) -> Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        fs::rename(&self.temp_path, &self.path)
+            .with_context(|| format!("Failed to move {:?} to {:?}", self.temp_path, self.path))?;
+        self.committed = true;
+        sync_parent(&self.path)
+            .with_context(|| format!("Failed to sync the folder of {:?}", self.path))?;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// Syncs the folder of `path`, so that a rename into it survives a crash.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    File::open(parent.unwrap_or(Path::new(".")))?.sync_all()
+}
+
+/// Folders can't be opened for syncing on other platforms.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Writes `contents` to `path` through an [`AtomicFile`].
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(contents.as_ref())
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    file.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AtomicFile;
+    use std::{fs, io::Write};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_atomic_file_replaces_the_destination_on_commit() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Rust.csv");
+        fs::write(&path, "old").unwrap();
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert!(dir.path().join("Rust.csv.tmp").exists());
+        file.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+
+        let mut abandoned = AtomicFile::create(&path).unwrap();
+        abandoned.write_all(b"partial").unwrap();
+        drop(abandoned);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!dir.path().join("Rust.csv.tmp").exists());
+    }
+}
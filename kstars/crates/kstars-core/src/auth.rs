@@ -0,0 +1,204 @@
+use crate::error::{KstarsError, Result, bail};
+use anyhow::Context;
+use reqwest::Client;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{debug, info};
+
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Response of `POST /login/device/code`.
+#[derive(Deserialize, Debug)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+/// Response of `POST /login/oauth/access_token` while polling.
+#[derive(Deserialize, Debug)]
+struct TokenPoll {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+    /// New minimum polling interval sent with `slow_down`.
+    interval: Option<u64>,
+}
+
+/// What to do after one poll of the token endpoint.
+#[derive(Debug, PartialEq, Eq)]
+enum PollStep {
+    Done(String),
+    Wait,
+    SlowDown(u64),
+}
+
+fn interpret_poll(poll: TokenPoll) -> Result<PollStep> {
+    if let Some(token) = poll.access_token {
+        return Ok(PollStep::Done(token));
+    }
+    match poll.error.as_deref() {
+        Some("authorization_pending") => Ok(PollStep::Wait),
+        Some("slow_down") => Ok(PollStep::SlowDown(poll.interval.unwrap_or(5))),
+        Some(error) => Err(KstarsError::AuthFailed(format!(
+            "device authorization failed: {} ({})",
+            error,
+            poll.error_description.unwrap_or_default()
+        ))),
+        None => bail!("Unexpected response from the token endpoint."),
+    }
+}
+
+/// Derives the web root of a GitHub instance from its REST API root.
+/// github.com uses `api.github.com`; GitHub Enterprise Server serves the API under `/api/v3`.
+pub fn web_base_url(api_base_url: &str) -> String {
+    let api_base_url = api_base_url.trim_end_matches('/');
+    match api_base_url.strip_suffix("/api/v3") {
+        Some(web_root) => web_root.to_string(),
+        None => api_base_url.replace("://api.", "://"),
+    }
+}
+
+/// Runs the OAuth device authorization flow and returns the granted token.
+///
+/// The user is asked to open the verification page and enter the printed code;
+/// the token endpoint is polled until they approve, deny or the code expires.
+pub async fn device_login(
+    client: &Client,
+    web_base_url: &str,
+    client_id: &str,
+    scope: &str,
+) -> Result<String> {
+    let device: DeviceCode = client
+        .post(format!("{}/login/device/code", web_base_url))
+        .header(reqwest::header::ACCEPT, "application/json")
+        .form(&[("client_id", client_id), ("scope", scope)])
+        .send()
+        .await
+        .context("HTTP request failed")?
+        .error_for_status()
+        .context("Failed to request a device code")?
+        .json()
+        .await
+        .context("Failed to deserialize device code response")?;
+
+    println!(
+        "Open {} and enter the code: {}",
+        device.verification_uri, device.user_code
+    );
+    info!(
+        "Waiting for authorization (code expires in {} seconds)...",
+        device.expires_in
+    );
+
+    let mut interval = device.interval.max(1);
+    loop {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        let poll: TokenPoll = client
+            .post(format!("{}/login/oauth/access_token", web_base_url))
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[
+                ("client_id", client_id),
+                ("device_code", device.device_code.as_str()),
+                ("grant_type", DEVICE_GRANT_TYPE),
+            ])
+            .send()
+            .await
+            .context("HTTP request failed")?
+            .json()
+            .await
+            .context("Failed to deserialize token response")?;
+
+        match interpret_poll(poll)? {
+            PollStep::Done(token) => return Ok(token),
+            PollStep::Wait => debug!("Authorization pending."),
+            PollStep::SlowDown(new_interval) => {
+                debug!(
+                    "Asked to slow down, polling every {} seconds.",
+                    new_interval
+                );
+                interval = new_interval;
+            }
+        }
+    }
+}
+
+/// Default location of the stored credential: `<config dir>/kstars/token`.
+pub fn credentials_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("kstars").join("token"))
+}
+
+/// Stores a token, readable only by the current user on Unix.
+pub fn save_token(path: &Path, token: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+    }
+    fs::write(path, token).with_context(|| format!("Failed to write token file: {:?}", path))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to restrict permissions of {:?}", path))?;
+    }
+    Ok(())
+}
+
+/// Loads a token saved by `kstars login`, if there is one.
+pub fn load_stored_token(path: &Path) -> Option<String> {
+    let token = fs::read_to_string(path).ok()?;
+    let token = token.trim();
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PollStep, TokenPoll, interpret_poll, load_stored_token, save_token, web_base_url};
+    use tempfile::tempdir;
+
+    fn poll(body: &str) -> TokenPoll {
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn test_interpret_poll_responses() {
+        assert_eq!(
+            interpret_poll(poll(r#"{"access_token":"gho_x","token_type":"bearer"}"#)).unwrap(),
+            PollStep::Done("gho_x".to_string())
+        );
+        assert_eq!(
+            interpret_poll(poll(r#"{"error":"authorization_pending"}"#)).unwrap(),
+            PollStep::Wait
+        );
+        assert_eq!(
+            interpret_poll(poll(r#"{"error":"slow_down","interval":10}"#)).unwrap(),
+            PollStep::SlowDown(10)
+        );
+        assert!(interpret_poll(poll(r#"{"error":"access_denied"}"#)).is_err());
+    }
+
+    #[test]
+    fn test_web_base_url() {
+        assert_eq!(web_base_url("https://api.github.com"), "https://github.com");
+        assert_eq!(
+            web_base_url("https://ghe.example.com/api/v3/"),
+            "https://ghe.example.com"
+        );
+    }
+
+    #[test]
+    fn test_save_and_load_token() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("kstars").join("token");
+        assert!(load_stored_token(&path).is_none());
+
+        save_token(&path, "gho_secret").unwrap();
+        assert_eq!(load_stored_token(&path).as_deref(), Some("gho_secret"));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    diff::{self, RankedRepo},
+    error::Result,
+    manifest::Manifest,
+    process::{self, OVERALL_TOP_STEM},
+};
+use anyhow::Context;
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+use tracing::info;
+
+/// Folder of the processed files the badges are written to when none is given.
+pub const BADGES_DIR_NAME: &str = "badges";
+
+/// Response of a shields.io endpoint badge, e.g. "kstars rank | #3 in Rust".
+/// See <https://shields.io/badges/endpoint-badge>.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Badge {
+    /// Version of the endpoint format; always 1.
+    pub schema_version: u32,
+    pub label: String,
+    pub message: String,
+    pub color: &'static str,
+}
+
+impl Badge {
+    fn new(label: String, message: String, color: &'static str) -> Self {
+        Self {
+            schema_version: 1,
+            label,
+            message,
+            color,
+        }
+    }
+}
+
+/// Color of the badge of a repository ranked `rank`.
+fn rank_color(rank: usize) -> &'static str {
+    match rank {
+        1..=10 => "brightgreen",
+        11..=100 => "green",
+        101..=500 => "yellowgreen",
+        _ => "blue",
+    }
+}
+
+/// Path of the badge of `repo` under `dir`, from the path of its URL, e.g.
+/// "<dir>/vercel/next.js.json"; `None` for URLs without a usable path.
+fn repo_badge_path(dir: &Path, repo: &RankedRepo) -> Option<PathBuf> {
+    let path = repo
+        .url
+        .split_once("://")?
+        .1
+        .split_once('/')?
+        .1
+        .trim_matches('/');
+    if path.is_empty()
+        || Path::new(path)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(dir.join(format!("{}.json", path)))
+}
+
+fn write_badge(path: &Path, badge: &Badge) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+    }
+    fs::write(path, serde_json::to_string(badge)?)
+        .with_context(|| format!("Failed to write badge: {:?}", path))?;
+    Ok(())
+}
+
+/// Writes shields.io endpoint badges for the processed rankings of `input_dir`
+/// into `output_dir`: "<language>.json" with the size of each ranking, and
+/// "<language>/<owner>/<name>.json" with the rank of each repository in it. The
+/// overall ranking is included as "overall_top". Languages are named as in the
+/// manifest, if there is one. Returns the number of badges written.
+pub fn write_badges(input_dir: &Path, output_dir: &Path, delimiter: u8) -> Result<usize> {
+    let display_names = Manifest::display_names(input_dir);
+
+    let mut written = 0;
+    for path in process::rankings(input_dir, delimiter)? {
+        let stem = path
+            .file_stem()
+            .expect("result files have a name")
+            .to_string_lossy()
+            .into_owned();
+        let ranking = diff::read_ranking(&path, delimiter)?;
+        let (scope, label) = if stem == OVERALL_TOP_STEM {
+            ("overall".to_string(), "kstars overall".to_string())
+        } else {
+            let name = display_names.get(&stem).unwrap_or(&stem);
+            (format!("in {}", name), format!("kstars {}", name))
+        };
+
+        write_badge(
+            &output_dir.join(format!("{}.json", stem)),
+            &Badge::new(label, format!("{} repos ranked", ranking.len()), "blue"),
+        )?;
+        written += 1;
+        for repo in &ranking {
+            let Some(badge_path) = repo_badge_path(&output_dir.join(&stem), repo) else {
+                continue;
+            };
+            let badge = Badge::new(
+                "kstars rank".to_string(),
+                format!("#{} {}", repo.rank, scope),
+                rank_color(repo.rank),
+            );
+            write_badge(&badge_path, &badge)?;
+            written += 1;
+        }
+    }
+    info!("Wrote {} badge(s) to {:?}", written, output_dir);
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_badges;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_badges_ranks_each_repository() {
+        let temp_dir = tempdir().unwrap();
+        let processed = temp_dir.path().join("processed");
+        let badges = processed.join("badges");
+        fs::create_dir(&processed).unwrap();
+        let ranking = "Ranking,Project Name,Stars,Repo URL\n\
+                       1,next.js,300,https://github.com/vercel/next.js\n\
+                       2,evil,200,https://github.com/../../etc\n";
+        fs::write(processed.join("CPP.csv"), ranking).unwrap();
+        fs::write(processed.join("top10_CPP.csv"), ranking).unwrap();
+        fs::write(
+            processed.join("manifest.json"),
+            r#"{"schema_version": 2, "snapshot_date": "2025-06-01", "preview_sizes": [10],
+                "languages": [{"name": "CPP", "display_name": "C++", "trending": false,
+                               "rows": 2, "columns": [], "file": "CPP.csv", "previews": {}}],
+                "overall_top": null}"#,
+        )
+        .unwrap();
+
+        assert_eq!(write_badges(&processed, &badges, b',').unwrap(), 2);
+
+        assert_eq!(
+            fs::read_to_string(badges.join("CPP/vercel/next.js.json")).unwrap(),
+            r##"{"schemaVersion":1,"label":"kstars rank","message":"#1 in C++","color":"brightgreen"}"##
+        );
+        assert_eq!(
+            fs::read_to_string(badges.join("CPP.json")).unwrap(),
+            r#"{"schemaVersion":1,"label":"kstars C++","message":"2 repos ranked","color":"blue"}"#
+        );
+        assert!(!badges.join("top10_CPP.json").exists());
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{
+    OwnerType, Repo,
+    error::{Result, bail},
+    google_auth::ServiceAccount,
+};
+use anyhow::Context;
+use chrono::{Local, NaiveDate};
+use reqwest::{Client, StatusCode};
+use serde_json::{Value, json};
+use std::{fmt, path::Path, str::FromStr};
+use tracing::info;
+
+const BIGQUERY_API_URL: &str = "https://bigquery.googleapis.com/bigquery/v2";
+
+const BIGQUERY_SCOPE: &str = "https://www.googleapis.com/auth/bigquery";
+
+/// Rows sent per streaming insert; BigQuery recommends at most 500.
+const INSERT_BATCH: usize = 500;
+
+/// Columns of the table, as name, type and mode. Mirrors the PostgreSQL `repos` table.
+const SCHEMA: &[(&str, &str, &str)] = &[
+    ("full_name", "STRING", "REQUIRED"),
+    ("snapshot_date", "DATE", "REQUIRED"),
+    ("language", "STRING", "REQUIRED"),
+    ("rank", "INTEGER", "REQUIRED"),
+    ("name", "STRING", "REQUIRED"),
+    ("url", "STRING", "REQUIRED"),
+    ("stars", "INTEGER", "REQUIRED"),
+    ("forks", "INTEGER", "REQUIRED"),
+    ("watchers", "INTEGER", "REQUIRED"),
+    ("open_issues", "INTEGER", "REQUIRED"),
+    ("created_at", "TIMESTAMP", "NULLABLE"),
+    ("last_commit", "TIMESTAMP", "NULLABLE"),
+    ("size_kb", "INTEGER", "REQUIRED"),
+    ("description", "STRING", "NULLABLE"),
+    ("license", "STRING", "NULLABLE"),
+    ("topics", "STRING", "REPEATED"),
+    ("owner", "STRING", "NULLABLE"),
+    ("owner_avatar", "STRING", "NULLABLE"),
+    ("owner_type", "STRING", "NULLABLE"),
+    ("archived", "BOOLEAN", "REQUIRED"),
+    ("fork", "BOOLEAN", "REQUIRED"),
+    ("default_branch", "STRING", "NULLABLE"),
+];
+
+/// BigQuery table named as "project.dataset.table", e.g. "my-project.kstars.repos".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigQueryTable {
+    pub project: String,
+    pub dataset: String,
+    pub table: String,
+}
+
+impl FromStr for BigQueryTable {
+    type Err = String;
+
+    /// Splits on the last two dots, so that domain-scoped projects such as
+    /// "example.com:project" keep theirs.
+    fn from_str(value: &str) -> Result<Self, String> {
+        let mut parts = value.rsplitn(3, '.');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(table), Some(dataset), Some(project))
+                if !table.is_empty() && !dataset.is_empty() && !project.is_empty() =>
+            {
+                Ok(Self {
+                    project: project.to_string(),
+                    dataset: dataset.to_string(),
+                    table: table.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "expected \"project.dataset.table\", got {:?}",
+                value
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BigQueryTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.project, self.dataset, self.table)
+    }
+}
+
+/// "owner/name" of a repository, taken from the path of its URL.
+fn full_name(html_url: &str) -> &str {
+    let path = html_url
+        .split_once("://")
+        .map_or(html_url, |(_, rest)| rest);
+    path.split_once('/')
+        .map_or(path, |(_, path)| path)
+        .trim_end_matches('/')
+}
+
+/// Streaming insert row of the repository ranked at `rank`. The insert ID lets
+/// BigQuery drop the duplicates of a retried insert.
+fn row(snapshot_date: NaiveDate, language: &str, rank: usize, repo: &Repo) -> Value {
+    let full_name = full_name(&repo.html_url);
+    let timestamp = |value: &str| (!value.is_empty()).then(|| value.to_string());
+    json!({
+        "insertId": format!("{}/{}/{}", snapshot_date, language, full_name),
+        "json": {
+            "full_name": full_name,
+            "snapshot_date": snapshot_date.to_string(),
+            "language": language,
+            "rank": rank,
+            "name": repo.name,
+            "url": repo.html_url,
+            "stars": repo.stargazers_count,
+            "forks": repo.forks_count,
+            "watchers": repo.watchers_count,
+            "open_issues": repo.open_issues_count,
+            "created_at": timestamp(&repo.created_at),
+            "last_commit": timestamp(&repo.pushed_at),
+            "size_kb": repo.size,
+            "description": repo.description,
+            "license": repo.license.as_ref().map(|l| l.label()),
+            "topics": repo.topics,
+            "owner": repo.owner_login(),
+            "owner_avatar": repo.owner_avatar_url(),
+            "owner_type": repo.owner_type().map(OwnerType::name),
+            "archived": repo.archived,
+            "fork": repo.fork,
+            "default_branch": repo.default_branch,
+        },
+    })
+}
+
+/// BigQuery table the rankings are streamed into, one row per repository and
+/// snapshot date, partitioned by day on `snapshot_date`.
+///
+/// The service account needs the BigQuery Data Editor role on the dataset.
+pub struct BigQuerySink {
+    client: Client,
+    account: ServiceAccount,
+    table: BigQueryTable,
+    snapshot_date: NaiveDate,
+}
+
+impl BigQuerySink {
+    /// Loads the service account key at `credentials_path` and creates `table` if
+    /// it does not exist.
+    pub async fn connect(
+        client: Client,
+        credentials_path: &Path,
+        table: BigQueryTable,
+    ) -> Result<Self> {
+        let sink = Self {
+            client,
+            account: ServiceAccount::load(credentials_path, BIGQUERY_SCOPE)?,
+            table,
+            snapshot_date: Local::now().date_naive(),
+        };
+        sink.ensure_table().await?;
+        info!("Connected to BigQuery table {}.", sink.table);
+        Ok(sink)
+    }
+
+    fn tables_url(&self) -> String {
+        format!(
+            "{}/projects/{}/datasets/{}/tables",
+            BIGQUERY_API_URL, self.table.project, self.table.dataset
+        )
+    }
+
+    async fn ensure_table(&self) -> Result<()> {
+        let token = self.account.access_token(&self.client).await?;
+        let response = self
+            .client
+            .get(format!("{}/{}", self.tables_url(), self.table.table))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .context("HTTP request failed")?;
+        if response.status() != StatusCode::NOT_FOUND {
+            response
+                .error_for_status()
+                .context("Failed to look up the BigQuery table")?;
+            return Ok(());
+        }
+        let fields: Vec<Value> = SCHEMA
+            .iter()
+            .map(|(name, kind, mode)| json!({ "name": name, "type": kind, "mode": mode }))
+            .collect();
+        let table = json!({
+            "tableReference": {
+                "projectId": self.table.project,
+                "datasetId": self.table.dataset,
+                "tableId": self.table.table,
+            },
+            "schema": { "fields": fields },
+            "timePartitioning": { "type": "DAY", "field": "snapshot_date" },
+        });
+        self.client
+            .post(self.tables_url())
+            .bearer_auth(&token)
+            .json(&table)
+            .send()
+            .await
+            .context("HTTP request failed")?
+            .error_for_status()
+            .context("Failed to create the BigQuery table")?;
+        info!("Created BigQuery table {}.", self.table);
+        Ok(())
+    }
+
+    /// Streams the ranking of one language into the table.
+    pub async fn insert_language(&self, language: &str, repos: &[Repo]) -> Result<()> {
+        let url = format!("{}/{}/insertAll", self.tables_url(), self.table.table);
+        let rows: Vec<Value> = repos
+            .iter()
+            .enumerate()
+            .map(|(i, repo)| row(self.snapshot_date, language, i + 1, repo))
+            .collect();
+        for batch in rows.chunks(INSERT_BATCH) {
+            let token = self.account.access_token(&self.client).await?;
+            let response: Value = self
+                .client
+                .post(&url)
+                .bearer_auth(token)
+                .json(&json!({ "rows": batch }))
+                .send()
+                .await
+                .context("HTTP request failed")?
+                .error_for_status()
+                .context("Failed to stream rows into BigQuery")?
+                .json()
+                .await
+                .context("Failed to deserialize BigQuery insert response")?;
+            // Rejected rows come back in a successful response.
+            if let Some(errors) = response["insertErrors"].as_array()
+                && !errors.is_empty()
+            {
+                bail!(
+                    "BigQuery rejected {} rows, e.g. {}",
+                    errors.len(),
+                    errors[0]["errors"]
+                );
+            }
+        }
+        info!(
+            "Streamed {} repositories for {} into BigQuery",
+            repos.len(),
+            language
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigQueryTable;
+
+    #[test]
+    fn test_parse_table_name() {
+        assert_eq!(
+            "my-project.kstars.repos".parse::<BigQueryTable>(),
+            Ok(BigQueryTable {
+                project: "my-project".to_string(),
+                dataset: "kstars".to_string(),
+                table: "repos".to_string(),
+            })
+        );
+        let scoped: BigQueryTable = "example.com:analytics.kstars.repos".parse().unwrap();
+        assert_eq!(scoped.project, "example.com:analytics");
+        assert_eq!(scoped.to_string(), "example.com:analytics.kstars.repos");
+        assert!("kstars.repos".parse::<BigQueryTable>().is_err());
+        assert!("project..repos".parse::<BigQueryTable>().is_err());
+    }
+}
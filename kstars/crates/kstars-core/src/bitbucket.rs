@@ -0,0 +1,320 @@
+use crate::{
+    Owner, Repo,
+    error::Result,
+    forge::{ForgeClient, SearchPage, send_with_retry},
+    rate_limit::RateLimiter,
+    token_pool::TokenPool,
+    transport::{HttpTransport, ReqwestTransport},
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use reqwest::Client;
+use serde::{Deserialize, de::DeserializeOwned};
+use std::{cmp::Reverse, sync::Arc};
+use tracing::debug;
+
+const BITBUCKET_API_URL: &str = "https://api.bitbucket.org/2.0";
+
+/// Metric used to rank Bitbucket repositories, which have no star count.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BitbucketRankBy {
+    Watchers,
+    Forks,
+}
+
+#[derive(Deserialize, Debug)]
+struct RepositoryPage {
+    values: Vec<Repository>,
+    next: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Link {
+    href: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RepositoryLinks {
+    html: Link,
+    watchers: Link,
+    forks: Link,
+}
+
+#[derive(Deserialize, Debug)]
+struct Repository {
+    name: String,
+    links: RepositoryLinks,
+    language: Option<String>,
+    description: Option<String>,
+    created_on: String,
+    updated_on: String,
+    /// Size in bytes.
+    size: Option<u64>,
+    workspace: Option<Workspace>,
+    /// Only present on forks.
+    parent: Option<serde_json::Value>,
+    mainbranch: Option<Branch>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Workspace {
+    slug: String,
+    links: Option<WorkspaceLinks>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WorkspaceLinks {
+    avatar: Option<Link>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Branch {
+    name: String,
+}
+
+/// Paginated collections only need their `size` to be counted.
+#[derive(Deserialize, Debug)]
+struct CollectionSize {
+    size: u64,
+}
+
+/// Bitbucket stores languages in lower case ("c++", "c#").
+fn bitbucket_language_name(api_name: &str) -> String {
+    match api_name {
+        "CPP" => "c++".to_string(),
+        "CSharp" => "c#".to_string(),
+        other => other.to_lowercase(),
+    }
+}
+
+/// Lists Bitbucket Cloud repositories and ranks them by watchers or forks.
+pub struct BitbucketClient {
+    client: Client,
+    /// Each entry is either "username:app_password" (basic auth) or an access token;
+    /// empty for anonymous access.
+    tokens: TokenPool,
+    limiter: RateLimiter,
+    /// Sends the requests built with `client`.
+    transport: Arc<dyn HttpTransport>,
+    rank_by: BitbucketRankBy,
+}
+
+impl BitbucketClient {
+    pub fn new(
+        client: Client,
+        tokens: TokenPool,
+        limiter: RateLimiter,
+        rank_by: BitbucketRankBy,
+    ) -> Self {
+        Self {
+            transport: Arc::new(ReqwestTransport::new(client.clone())),
+            client,
+            tokens,
+            limiter,
+            rank_by,
+        }
+    }
+
+    /// Sends the requests through `transport` instead of the network.
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Sends an authenticated GET and deserializes the JSON body.
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, String)],
+    ) -> Result<T> {
+        let resp = send_with_retry(self, |token| {
+            let request = self.client.get(url).query(params);
+            match token.map(|t| (t, t.split_once(':'))) {
+                Some((_, Some((user, password)))) => request.basic_auth(user, Some(password)),
+                Some((token, None)) => request.bearer_auth(token),
+                None => request,
+            }
+        })
+        .await?;
+        Ok(serde_json::from_str(&resp.body).context("Failed to deserialize JSON response")?)
+    }
+
+    /// Counts the entries of a paginated collection such as `/watchers` or `/forks`.
+    async fn count_collection(&self, url: &str) -> Result<u64> {
+        let collection: CollectionSize =
+            self.get_json(url, &[("pagelen", "1".to_string())]).await?;
+        Ok(collection.size)
+    }
+
+    /// Converts a repository into a `Repo`, looking up its watcher and fork counts.
+    async fn resolve_repo(&self, repo: Repository) -> Result<Repo> {
+        let watchers = self.count_collection(&repo.links.watchers.href).await?;
+        let forks = self.count_collection(&repo.links.forks.href).await?;
+        Ok(Repo {
+            name: repo.name,
+            html_url: repo.links.html.href,
+            // Bitbucket has no stars.
+            stargazers_count: 0,
+            forks_count: forks,
+            watchers_count: watchers,
+            language: repo.language,
+            description: repo.description.filter(|d| !d.is_empty()),
+            open_issues_count: 0,
+            created_at: repo.created_on,
+            pushed_at: repo.updated_on,
+            size: repo.size.unwrap_or_default() / 1024,
+            license: None,
+            topics: Vec::new(),
+            owner: repo.workspace.map(|w| Owner {
+                login: w.slug,
+                avatar_url: w.links.and_then(|l| l.avatar).map(|a| a.href),
+                kind: None,
+            }),
+            // Bitbucket repositories cannot be archived.
+            archived: false,
+            fork: repo.parent.is_some(),
+            default_branch: repo.mainbranch.map(|b| b.name),
+            mirror_url: None,
+            details: Default::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl ForgeClient for BitbucketClient {
+    fn name(&self) -> &'static str {
+        "bitbucket"
+    }
+
+    fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    fn tokens(&self) -> &TokenPool {
+        &self.tokens
+    }
+
+    fn transport(&self) -> &dyn HttpTransport {
+        self.transport.as_ref()
+    }
+
+    fn language_query(&self, language: &str, _star_ceiling: Option<u64>) -> String {
+        format!("language=\"{}\"", bitbucket_language_name(language))
+    }
+
+    /// Fetches one page of repositories with their counts. The API cannot sort by
+    /// popularity, so the pipeline collects the requested number and `rank` orders them.
+    /// The cursor is the `next` URL returned by Bitbucket.
+    async fn search_page(&self, query: &str, cursor: Option<&str>) -> Result<SearchPage> {
+        let listing: RepositoryPage = match cursor {
+            Some(url) => self.get_json(url, &[]).await?,
+            None => {
+                let params = [("q", query.to_string()), ("pagelen", "100".to_string())];
+                self.get_json(&format!("{}/repositories", BITBUCKET_API_URL), &params)
+                    .await?
+            }
+        };
+
+        let mut repos = Vec::with_capacity(listing.values.len());
+        for repo in listing.values {
+            repos.push(self.resolve_repo(repo).await?);
+        }
+        debug!(
+            "Bitbucket page for '{}' returned {} repos.",
+            query,
+            repos.len()
+        );
+
+        Ok(SearchPage {
+            repos,
+            next_cursor: listing.next,
+            ..SearchPage::default()
+        })
+    }
+
+    fn rank(&self, repos: &mut [Repo]) {
+        rank_repos(repos, self.rank_by);
+    }
+
+    /// Bitbucket's watchers are the accounts following the repository already.
+    async fn subscribers_count(&self, repo: &Repo) -> Result<Option<u64>> {
+        Ok(Some(repo.watchers_count))
+    }
+}
+
+/// Sorts repositories by the chosen metric, most popular first.
+fn rank_repos(repos: &mut [Repo], rank_by: BitbucketRankBy) {
+    match rank_by {
+        BitbucketRankBy::Watchers => repos.sort_by_key(|r| Reverse(r.watchers_count)),
+        BitbucketRankBy::Forks => repos.sort_by_key(|r| Reverse(r.forks_count)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitbucketRankBy, Repository, bitbucket_language_name, rank_repos};
+    use crate::Repo;
+
+    fn repo(name: &str, watchers: u64, forks: u64) -> Repo {
+        Repo {
+            name: name.to_string(),
+            html_url: format!("https://bitbucket.org/team/{}", name),
+            stargazers_count: 0,
+            forks_count: forks,
+            watchers_count: watchers,
+            language: Some("rust".to_string()),
+            description: None,
+            open_issues_count: 0,
+            created_at: "2020-01-01T00:00:00+00:00".to_string(),
+            pushed_at: "2020-01-01T00:00:00+00:00".to_string(),
+            size: 0,
+            license: None,
+            topics: vec![],
+            owner: None,
+            archived: false,
+            fork: false,
+            default_branch: None,
+            mirror_url: None,
+            details: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_rank_repos_by_key() {
+        let mut repos = vec![repo("a", 5, 50), repo("b", 20, 1), repo("c", 10, 10)];
+
+        rank_repos(&mut repos, BitbucketRankBy::Watchers);
+        let names: Vec<_> = repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["b", "c", "a"]);
+
+        rank_repos(&mut repos, BitbucketRankBy::Forks);
+        let names: Vec<_> = repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn test_repository_deserializes() {
+        let body = r#"{
+            "name": "tool",
+            "language": "rust",
+            "description": "",
+            "created_on": "2019-01-01T00:00:00+00:00",
+            "updated_on": "2024-01-01T00:00:00+00:00",
+            "size": 20480,
+            "links": {
+                "html": { "href": "https://bitbucket.org/team/tool" },
+                "watchers": { "href": "https://api.bitbucket.org/2.0/repositories/team/tool/watchers" },
+                "forks": { "href": "https://api.bitbucket.org/2.0/repositories/team/tool/forks" }
+            }
+        }"#;
+        let repository: Repository = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            repository.links.html.href,
+            "https://bitbucket.org/team/tool"
+        );
+        assert_eq!(bitbucket_language_name("CPP"), "c++");
+        assert_eq!(bitbucket_language_name("Rust"), "rust");
+    }
+}
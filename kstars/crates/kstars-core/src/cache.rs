@@ -0,0 +1,392 @@
+use crate::{error::Result, process::human_readable_size};
+use anyhow::Context;
+use clap::Subcommand;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+/// Name of the folder of the output directory holding cached pages.
+pub const CACHE_DIR_NAME: &str = ".cache";
+
+/// Subfolders of the cache root holding the languages of forges other than GitHub,
+/// whose languages sit directly in the root.
+const FORGE_DIRS: [&str; 2] = ["gitlab", "bitbucket"];
+
+/// Subfolder of the cache root holding the sampled star histories, one file per
+/// repository. Cleared with the whole cache but not listed as a language.
+pub const STAR_HISTORY_DIR_NAME: &str = "star_history";
+
+/// Actions of the `cache` subcommand.
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheAction {
+    /// List cached languages with their pages, size and age.
+    List,
+    /// Delete the cache, or only that of one language.
+    Clear {
+        /// API name of the language whose pages are deleted, e.g. "CPP".
+        #[arg(long)]
+        language: Option<String>,
+    },
+    /// Delete pages fetched longer ago than the given age.
+    Prune {
+        /// Age of the pages to delete, e.g. "7d".
+        #[arg(long, value_parser = parse_age)]
+        older_than: Duration,
+    },
+}
+
+/// Cached pages of one language.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CachedLanguage {
+    /// Forge the pages come from.
+    pub forge: String,
+    pub language: String,
+    pub dir: PathBuf,
+    pub pages: usize,
+    pub bytes: u64,
+    /// Age of the oldest page.
+    pub oldest: Option<Duration>,
+}
+
+/// Parses an age given on the command line, a number followed by a unit:
+/// "90s", "30m", "24h", "7d" or "2w".
+pub fn parse_age(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("expected an age like 30m, 24h or 7d, got {:?}", value);
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let secs = amount
+        .checked_mul(unit_secs)
+        .ok_or_else(|| format!("age {:?} is too long", value))?;
+    Ok(Duration::from_secs(secs))
+}
+
+/// Which cached pages a fetch may reuse.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CachePolicy {
+    /// Pages fetched longer ago than this are fetched again.
+    pub max_age: Option<Duration>,
+    /// Ignore every cached page.
+    pub refresh: bool,
+}
+
+/// Gets the path to the file storing when a cached page was fetched.
+fn timestamp_file_path(page_file: &Path) -> PathBuf {
+    page_file.with_extension("timestamp")
+}
+
+/// Records that the page cached at `page_file` was fetched just now.
+pub fn write_timestamp(page_file: &Path) -> std::io::Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    fs::write(timestamp_file_path(page_file), now.to_string())
+}
+
+/// Time elapsed since the page cached at `page_file` was fetched. `None` for pages
+/// cached before timestamps were recorded.
+pub fn page_age(page_file: &Path) -> Option<Duration> {
+    let fetched_at: u64 = fs::read_to_string(timestamp_file_path(page_file))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH + Duration::from_secs(fetched_at))
+            .unwrap_or_default(),
+    )
+}
+
+/// Age of a cached page, from its timestamp or else from when the file was written.
+fn age_on_disk(page_file: &Path) -> Option<Duration> {
+    page_age(page_file).or_else(|| {
+        let modified = fs::metadata(page_file).and_then(|m| m.modified()).ok()?;
+        Some(modified.elapsed().unwrap_or_default())
+    })
+}
+
+/// Whether `path` is a cached page, e.g. "page_3.json".
+fn is_page_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+        && path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.starts_with("page_"))
+}
+
+/// Lists every file below `dir`, including those of star bucket and filter subfolders.
+fn files_below(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(files_below(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Lists the cached languages of every forge, sorted by forge and language.
+pub fn list_languages(cache_root: &Path) -> Result<Vec<CachedLanguage>> {
+    let mut language_dirs = Vec::new();
+    if cache_root.is_dir() {
+        for entry in
+            fs::read_dir(cache_root).with_context(|| format!("Failed to read {:?}", cache_root))?
+        {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !path.is_dir() || name == STAR_HISTORY_DIR_NAME {
+                continue;
+            }
+            if FORGE_DIRS.contains(&name.as_ref()) {
+                for language in fs::read_dir(&path)? {
+                    let language = language?.path();
+                    if language.is_dir() {
+                        language_dirs.push((name.to_string(), language));
+                    }
+                }
+            } else {
+                language_dirs.push(("github".to_string(), path.clone()));
+            }
+        }
+    }
+
+    let mut languages = Vec::new();
+    for (forge, dir) in language_dirs {
+        let files = files_below(&dir)?;
+        let pages: Vec<&PathBuf> = files.iter().filter(|f| is_page_file(f)).collect();
+        languages.push(CachedLanguage {
+            forge,
+            language: dir.file_name().unwrap_or_default().to_string_lossy().into(),
+            pages: pages.len(),
+            bytes: files
+                .iter()
+                .filter_map(|f| fs::metadata(f).ok())
+                .map(|m| m.len())
+                .sum(),
+            oldest: pages.iter().filter_map(|p| age_on_disk(p)).max(),
+            dir,
+        });
+    }
+    languages.sort_by(|a, b| (&a.forge, &a.language).cmp(&(&b.forge, &b.language)));
+    Ok(languages)
+}
+
+/// Formats an age with its two largest units, e.g. "3d 4h".
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+/// Deletes the cached pages of `language` for every forge, or the whole cache.
+/// Returns the number of folders deleted.
+pub fn clear(cache_root: &Path, language: Option<&str>) -> Result<usize> {
+    let dirs: Vec<PathBuf> = match language {
+        Some(language) => list_languages(cache_root)?
+            .into_iter()
+            .filter(|cached| cached.language == language)
+            .map(|cached| cached.dir)
+            .collect(),
+        None if cache_root.is_dir() => vec![cache_root.to_path_buf()],
+        None => vec![],
+    };
+    for dir in &dirs {
+        fs::remove_dir_all(dir).with_context(|| format!("Failed to delete {:?}", dir))?;
+    }
+    Ok(dirs.len())
+}
+
+/// Deletes the pages older than `older_than`, with their cursor and timestamp files,
+/// and the folders left empty. Returns the number of pages deleted.
+pub fn prune(cache_root: &Path, older_than: Duration) -> Result<usize> {
+    let mut pruned = 0;
+    for cached in list_languages(cache_root)? {
+        for page in files_below(&cached.dir)?.iter().filter(|f| is_page_file(f)) {
+            if age_on_disk(page).is_some_and(|age| age > older_than) {
+                for path in [
+                    page.clone(),
+                    page.with_extension("cursor"),
+                    timestamp_file_path(page),
+                ] {
+                    if path.exists() {
+                        fs::remove_file(&path)
+                            .with_context(|| format!("Failed to delete {:?}", path))?;
+                    }
+                }
+                pruned += 1;
+            }
+        }
+        remove_empty_dirs(&cached.dir)?;
+    }
+    Ok(pruned)
+}
+
+/// Deletes `dir` and its subfolders when they hold no files.
+fn remove_empty_dirs(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            remove_empty_dirs(&path)?;
+        }
+    }
+    if fs::read_dir(dir)?.next().is_none() {
+        fs::remove_dir(dir).with_context(|| format!("Failed to delete {:?}", dir))?;
+    }
+    Ok(())
+}
+
+/// Runs a `cache` action on the cache of the result folder `output_dir`.
+pub fn run(output_dir: &Path, action: CacheAction) -> Result<()> {
+    let cache_root = output_dir.join(CACHE_DIR_NAME);
+    match action {
+        CacheAction::List => {
+            let languages = list_languages(&cache_root)?;
+            if languages.is_empty() {
+                println!("No cached pages in {:?}", cache_root);
+            }
+            for cached in languages {
+                println!(
+                    "{}/{}: {} page(s), {}, oldest {}",
+                    cached.forge,
+                    cached.language,
+                    cached.pages,
+                    human_readable_size(cached.bytes / 1024),
+                    cached
+                        .oldest
+                        .map(format_age)
+                        .unwrap_or_else(|| "-".to_string())
+                );
+            }
+        }
+        CacheAction::Clear { language } => {
+            let cleared = clear(&cache_root, language.as_deref())?;
+            info!("Deleted {} cache folder(s) from {:?}", cleared, cache_root);
+        }
+        CacheAction::Prune { older_than } => {
+            let pruned = prune(&cache_root, older_than)?;
+            info!(
+                "Deleted {} cached page(s) older than {}",
+                pruned,
+                format_age(older_than)
+            );
+        }
+    }
+    Ok(())
+}
+
+impl CachePolicy {
+    /// Whether the page cached at `page_file` may be reused. Pages of unknown age
+    /// count as stale once a maximum age is set.
+    pub fn allows(&self, page_file: &Path) -> bool {
+        if self.refresh {
+            return false;
+        }
+        self.max_age
+            .is_none_or(|max_age| page_age(page_file).is_some_and(|age| age <= max_age))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachePolicy, clear, list_languages, parse_age, prune, write_timestamp};
+    use std::{fs, path::Path, time::Duration};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("24h"), Ok(Duration::from_secs(86400)));
+        assert_eq!(parse_age("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert!(parse_age("24").is_err());
+        assert!(parse_age("h").is_err());
+        assert!(parse_age("3y").is_err());
+        assert!(parse_age("18446744073709551615w").is_err());
+    }
+
+    #[test]
+    fn test_cache_policy_allows_fresh_pages() {
+        let temp_dir = tempdir().unwrap();
+        let page = temp_dir.path().join("page_1.json");
+        let day = CachePolicy {
+            max_age: Some(Duration::from_secs(86400)),
+            refresh: false,
+        };
+        assert!(CachePolicy::default().allows(&page));
+        // Without a timestamp the age of the page is unknown.
+        assert!(!day.allows(&page));
+
+        write_timestamp(&page).unwrap();
+        assert!(day.allows(&page));
+        let refresh = CachePolicy {
+            refresh: true,
+            ..day
+        };
+        assert!(!refresh.allows(&page));
+    }
+
+    /// Caches one page in `dir`, fetched `age_secs` ago.
+    fn cache_page(dir: &Path, age_secs: u64) {
+        fs::create_dir_all(dir).unwrap();
+        let page = dir.join("page_1.json");
+        fs::write(&page, "[]").unwrap();
+        fs::write(dir.join("page_1.cursor"), "").unwrap();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        fs::write(
+            page.with_extension("timestamp"),
+            (now - age_secs).to_string(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_list_prune_and_clear() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        cache_page(&root.join("Rust"), 60);
+        cache_page(&root.join("CPP"), 10 * 86400);
+        cache_page(&root.join("gitlab/Rust"), 60);
+
+        let listed: Vec<(String, String, usize)> = list_languages(root)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.forge, c.language, c.pages))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("github".to_string(), "CPP".to_string(), 1),
+                ("github".to_string(), "Rust".to_string(), 1),
+                ("gitlab".to_string(), "Rust".to_string(), 1),
+            ]
+        );
+
+        assert_eq!(prune(root, parse_age("7d").unwrap()).unwrap(), 1);
+        assert!(!root.join("CPP").exists());
+        assert_eq!(clear(root, Some("Rust")).unwrap(), 2);
+        assert!(list_languages(root).unwrap().is_empty());
+    }
+}
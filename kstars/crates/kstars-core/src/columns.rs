@@ -0,0 +1,297 @@
+use crate::{
+    Repo,
+    enrich::Enrichment,
+    error::{Result, bail},
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// A column of the delimited result files. `--columns` selects and orders them.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[value(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Ranking,
+    ProjectName,
+    Stars,
+    Forks,
+    Watchers,
+    OpenIssues,
+    CreatedAt,
+    LastCommit,
+    SizeKb,
+    Description,
+    Language,
+    RepoUrl,
+    License,
+    Topics,
+    Owner,
+    OwnerAvatar,
+    OwnerType,
+    Archived,
+    Fork,
+    DefaultBranch,
+    LatestRelease,
+    ReleaseDate,
+    ReleaseCount,
+    #[value(name = "commits_52w")]
+    #[serde(rename = "commits_52w")]
+    Commits52w,
+    ReadmeExcerpt,
+    Subscribers,
+    ClosedIssues,
+    IssueCloseRate,
+    #[value(name = "open_prs")]
+    #[serde(rename = "open_prs")]
+    OpenPullRequests,
+    #[value(name = "merged_prs")]
+    #[serde(rename = "merged_prs")]
+    MergedPullRequests,
+    Sponsorable,
+    SecurityPolicy,
+    Ci,
+}
+
+impl Column {
+    /// Every column in default order: those of `CSV_HEADER`, then the enrichment ones.
+    pub const ALL: [Column; 33] = [
+        Column::Ranking,
+        Column::ProjectName,
+        Column::Stars,
+        Column::Forks,
+        Column::Watchers,
+        Column::OpenIssues,
+        Column::CreatedAt,
+        Column::LastCommit,
+        Column::SizeKb,
+        Column::Description,
+        Column::Language,
+        Column::RepoUrl,
+        Column::License,
+        Column::Topics,
+        Column::Owner,
+        Column::OwnerAvatar,
+        Column::OwnerType,
+        Column::Archived,
+        Column::Fork,
+        Column::DefaultBranch,
+        Column::LatestRelease,
+        Column::ReleaseDate,
+        Column::ReleaseCount,
+        Column::Commits52w,
+        Column::ReadmeExcerpt,
+        Column::Subscribers,
+        Column::ClosedIssues,
+        Column::IssueCloseRate,
+        Column::OpenPullRequests,
+        Column::MergedPullRequests,
+        Column::Sponsorable,
+        Column::SecurityPolicy,
+        Column::Ci,
+    ];
+
+    /// Header cell of the column, as read by the website.
+    pub fn header(self) -> &'static str {
+        match self {
+            Column::Ranking => "Ranking",
+            Column::ProjectName => "Project Name",
+            Column::Stars => "Stars",
+            Column::Forks => "Forks",
+            Column::Watchers => "Watchers",
+            Column::OpenIssues => "Open Issues",
+            Column::CreatedAt => "Created At",
+            Column::LastCommit => "Last Commit",
+            Column::SizeKb => "Size (KB)",
+            Column::Description => "Description",
+            Column::Language => "Language",
+            Column::RepoUrl => "Repo URL",
+            Column::License => "License",
+            Column::Topics => "Topics",
+            Column::Owner => "Owner",
+            Column::OwnerAvatar => "Owner Avatar",
+            Column::OwnerType => "Owner Type",
+            Column::Archived => "Archived",
+            Column::Fork => "Fork",
+            Column::DefaultBranch => "Default Branch",
+            Column::LatestRelease => "Latest Release",
+            Column::ReleaseDate => "Release Date",
+            Column::ReleaseCount => "Releases",
+            Column::Commits52w => "Commits (52w)",
+            Column::ReadmeExcerpt => "README Excerpt",
+            Column::Subscribers => "Subscribers",
+            Column::ClosedIssues => "Closed Issues",
+            Column::IssueCloseRate => "Issue Close Rate",
+            Column::OpenPullRequests => "Open PRs",
+            Column::MergedPullRequests => "Merged PRs",
+            Column::Sponsorable => "Sponsorable",
+            Column::SecurityPolicy => "Security Policy",
+            Column::Ci => "CI",
+        }
+    }
+
+    /// Cell of the column for the repository ranked at `ranking`.
+    pub fn value(self, ranking: usize, repo: &Repo) -> String {
+        let release = repo.details.latest_release.as_ref();
+        let pull_requests = repo.details.pull_requests.as_ref();
+        let hygiene = repo.details.hygiene.as_ref();
+        match self {
+            Column::Ranking => ranking.to_string(),
+            Column::ProjectName => repo.name.clone(),
+            Column::Stars => repo.stargazers_count.to_string(),
+            Column::Forks => repo.forks_count.to_string(),
+            Column::Watchers => repo.watchers_count.to_string(),
+            Column::OpenIssues => repo.open_issues_count.to_string(),
+            Column::CreatedAt => repo.created_at.clone(),
+            Column::LastCommit => repo.pushed_at.clone(),
+            Column::SizeKb => repo.size.to_string(),
+            Column::Description => repo.description.clone().unwrap_or_default(),
+            Column::Language => repo.language.clone().unwrap_or_default(),
+            Column::RepoUrl => repo.html_url.clone(),
+            Column::License => repo
+                .license
+                .as_ref()
+                .map(|l| l.label().to_string())
+                .unwrap_or_default(),
+            // Topics never contain spaces or semicolons.
+            Column::Topics => repo.topics.join(";"),
+            Column::Owner => repo.owner_login().unwrap_or_default().to_string(),
+            Column::OwnerAvatar => repo.owner_avatar_url().unwrap_or_default().to_string(),
+            Column::OwnerType => repo
+                .owner_type()
+                .map(|kind| kind.name().to_string())
+                .unwrap_or_default(),
+            Column::Archived => repo.archived.to_string(),
+            Column::Fork => repo.fork.to_string(),
+            Column::DefaultBranch => repo.default_branch.clone().unwrap_or_default(),
+            Column::LatestRelease => release.map(|r| r.tag_name.clone()).unwrap_or_default(),
+            Column::ReleaseDate => release
+                .and_then(|r| r.published_at.clone())
+                .unwrap_or_default(),
+            Column::ReleaseCount => repo
+                .details
+                .release_count
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            Column::Commits52w => repo
+                .details
+                .commits_last_year
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            Column::ReadmeExcerpt => repo.details.readme_excerpt.clone().unwrap_or_default(),
+            Column::Subscribers => repo
+                .details
+                .subscribers_count
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            Column::ClosedIssues => repo
+                .details
+                .closed_issues
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            Column::IssueCloseRate => repo
+                .details
+                .issue_close_rate
+                .map(|rate| format!("{:.2}", rate))
+                .unwrap_or_default(),
+            Column::OpenPullRequests => pull_requests
+                .map(|p| p.open.to_string())
+                .unwrap_or_default(),
+            Column::MergedPullRequests => pull_requests
+                .map(|p| p.merged.to_string())
+                .unwrap_or_default(),
+            Column::Sponsorable => repo
+                .details
+                .sponsorable
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            Column::SecurityPolicy => hygiene
+                .map(|h| h.security_policy.to_string())
+                .unwrap_or_default(),
+            Column::Ci => hygiene.map(|h| h.ci.to_string()).unwrap_or_default(),
+        }
+    }
+
+    /// Enrichment that has to run for the column to have values, if any.
+    pub fn enrichment(self) -> Option<Enrichment> {
+        match self {
+            Column::LatestRelease | Column::ReleaseDate | Column::ReleaseCount => {
+                Some(Enrichment::Releases)
+            }
+            Column::Commits52w => Some(Enrichment::CommitActivity),
+            Column::ReadmeExcerpt => Some(Enrichment::Readme),
+            Column::Subscribers => Some(Enrichment::Subscribers),
+            Column::ClosedIssues | Column::IssueCloseRate => Some(Enrichment::Issues),
+            Column::OpenPullRequests | Column::MergedPullRequests => Some(Enrichment::PullRequests),
+            Column::Sponsorable => Some(Enrichment::Funding),
+            Column::SecurityPolicy | Column::Ci => Some(Enrichment::Hygiene),
+            _ => None,
+        }
+    }
+}
+
+/// Columns written when `--columns` is not given: every column whose data is fetched.
+pub fn default_columns(enrichments: &[Enrichment]) -> Vec<Column> {
+    Column::ALL
+        .into_iter()
+        .filter(|c| c.enrichment().is_none_or(|e| enrichments.contains(&e)))
+        .collect()
+}
+
+/// Rejects an empty selection, columns selected more than once and columns whose
+/// enrichment was not requested.
+pub fn check_selection(columns: &[Column], enrichments: &[Enrichment]) -> Result<()> {
+    if columns.is_empty() {
+        bail!("At least one column must be selected.");
+    }
+    for (i, column) in columns.iter().enumerate() {
+        if columns[..i].contains(column) {
+            bail!("Column \"{}\" is selected more than once.", column.header());
+        }
+        if let Some(enrichment) = column.enrichment()
+            && !enrichments.contains(&enrichment)
+        {
+            bail!(
+                "Column \"{}\" needs `--enrich {}`.",
+                column.header(),
+                enrichment
+                    .to_possible_value()
+                    .expect("enrichments are not skipped")
+                    .get_name()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Column, check_selection, default_columns};
+    use crate::{CSV_HEADER, enrich::Enrichment};
+    use clap::ValueEnum;
+
+    #[test]
+    fn test_columns_match_csv_header() {
+        let headers: Vec<&str> = default_columns(&[])
+            .into_iter()
+            .map(Column::header)
+            .collect();
+        assert_eq!(headers, CSV_HEADER);
+        assert_eq!(Column::from_str("repo_url", false), Ok(Column::RepoUrl));
+        assert!(check_selection(&[Column::Stars, Column::RepoUrl], &[]).is_ok());
+        assert!(check_selection(&[Column::Stars, Column::Stars], &[]).is_err());
+        assert!(check_selection(&[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_enrichment_columns_need_their_enrichment() {
+        let releases = [Enrichment::Releases];
+        assert_eq!(default_columns(&releases).len(), CSV_HEADER.len() + 3);
+        assert!(check_selection(&[Column::LatestRelease], &[]).is_err());
+        assert!(check_selection(&[Column::LatestRelease], &releases).is_ok());
+        assert_eq!(
+            Column::from_str("commits_52w", false),
+            Ok(Column::Commits52w)
+        );
+        assert!(check_selection(&[Column::Commits52w], &releases).is_err());
+    }
+}
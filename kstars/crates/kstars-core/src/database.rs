@@ -0,0 +1,267 @@
+use crate::{OwnerType, Repo, error::Result, sink::OutputSink};
+use anyhow::Context;
+use rusqlite::{Connection, params};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tracing::info;
+
+/// File name of the database inside the output folder.
+pub const DATABASE_FILE_NAME: &str = "kstars.db";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL,
+    finished_at TEXT,
+    provider TEXT NOT NULL,
+    records INTEGER NOT NULL,
+    languages INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS repos (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    language TEXT NOT NULL,
+    rank INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    url TEXT NOT NULL,
+    stars INTEGER NOT NULL,
+    forks INTEGER NOT NULL,
+    watchers INTEGER NOT NULL,
+    open_issues INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    last_commit TEXT NOT NULL,
+    size_kb INTEGER NOT NULL,
+    description TEXT,
+    license TEXT,
+    topics TEXT,
+    owner TEXT,
+    archived INTEGER NOT NULL DEFAULT 0,
+    fork INTEGER NOT NULL DEFAULT 0,
+    default_branch TEXT,
+    owner_avatar TEXT,
+    owner_type TEXT,
+    PRIMARY KEY (run_id, language, rank)
+);
+CREATE INDEX IF NOT EXISTS repos_url ON repos(url);
+";
+
+/// Columns added to `repos` after its first release, with their definitions.
+const ADDED_COLUMNS: [(&str, &str); 8] = [
+    ("license", "TEXT"),
+    ("topics", "TEXT"),
+    ("owner", "TEXT"),
+    ("archived", "INTEGER NOT NULL DEFAULT 0"),
+    ("fork", "INTEGER NOT NULL DEFAULT 0"),
+    ("default_branch", "TEXT"),
+    ("owner_avatar", "TEXT"),
+    ("owner_type", "TEXT"),
+];
+
+/// Brings the `repos` table of a database created by an older version up to date.
+fn add_missing_columns(conn: &Connection) -> Result<()> {
+    let mut statement = conn.prepare("SELECT name FROM pragma_table_info('repos')")?;
+    let existing = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (name, definition) in ADDED_COLUMNS {
+        if !existing.iter().any(|column| column == name) {
+            conn.execute_batch(&format!(
+                "ALTER TABLE repos ADD COLUMN {} {}",
+                name, definition
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// SQLite database collecting the rankings of every language of a run.
+///
+/// Each fetch adds a row to `runs`; the rankings it produces go to `repos`, keyed by
+/// run, language and rank, so earlier runs stay available for comparison.
+pub struct Database {
+    conn: Mutex<Connection>,
+    run_id: i64,
+}
+
+impl Database {
+    /// Opens (or creates) the database at `path` and records the start of a run.
+    pub fn open(path: &Path, provider: &str, records: u32) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open database: {:?}", path))?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create database tables")?;
+        add_missing_columns(&conn).context("Failed to upgrade the repos table")?;
+        conn.execute(
+            "INSERT INTO runs (started_at, provider, records) VALUES (?1, ?2, ?3)",
+            params![chrono::Utc::now().to_rfc3339(), provider, records],
+        )?;
+        let run_id = conn.last_insert_rowid();
+        info!("Recording run {} in {:?}", run_id, path);
+        Ok(Self {
+            conn: Mutex::new(conn),
+            run_id,
+        })
+    }
+
+    /// Stores the ranking of one language in a single transaction.
+    pub fn write_language(&self, language: &str, repos: &[Repo]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO repos (run_id, language, rank, name, url, stars, forks,
+                 watchers, open_issues, created_at, last_commit, size_kb, description, license,
+                 topics, owner, archived, fork, default_branch, owner_avatar, owner_type)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                 ?17, ?18, ?19, ?20, ?21)",
+            )?;
+            // SQLite integers are signed 64-bit; counts never get near the limit.
+            for (i, repo) in repos.iter().enumerate() {
+                insert.execute(params![
+                    self.run_id,
+                    language,
+                    i as i64 + 1,
+                    repo.name,
+                    repo.html_url,
+                    repo.stargazers_count as i64,
+                    repo.forks_count as i64,
+                    repo.watchers_count as i64,
+                    repo.open_issues_count as i64,
+                    repo.created_at,
+                    repo.pushed_at,
+                    repo.size as i64,
+                    repo.description,
+                    repo.license.as_ref().map(|l| l.label()),
+                    repo.topics.join(";"),
+                    repo.owner_login(),
+                    repo.archived,
+                    repo.fork,
+                    repo.default_branch,
+                    repo.owner_avatar_url(),
+                    repo.owner_type().map(OwnerType::name),
+                ])?;
+            }
+        }
+        tx.execute(
+            "UPDATE runs SET languages = languages + 1 WHERE id = ?1",
+            params![self.run_id],
+        )?;
+        tx.commit()?;
+        info!(
+            "Stored {} repositories for {} in the database",
+            repos.len(),
+            language
+        );
+        Ok(())
+    }
+
+    /// Records the end of the run.
+    pub fn finish(&self) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "UPDATE runs SET finished_at = ?1 WHERE id = ?2",
+            params![chrono::Utc::now().to_rfc3339(), self.run_id],
+        )?;
+        Ok(())
+    }
+}
+
+/// One language's rows in the shared database, inserted together in `finish`.
+pub struct SqliteSink {
+    database: Arc<Database>,
+    language: String,
+    repos: Vec<Repo>,
+}
+
+impl SqliteSink {
+    pub fn new(database: Arc<Database>, language: &str) -> Self {
+        Self {
+            database,
+            language: language.to_string(),
+            repos: Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for SqliteSink {
+    fn write_repo(&mut self, _ranking: usize, repo: &Repo) -> Result<()> {
+        self.repos.push(repo.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.database.write_language(&self.language, &self.repos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Database, SCHEMA};
+    use crate::Repo;
+    use tempfile::tempdir;
+
+    fn repo(name: &str, stars: u64) -> Repo {
+        Repo {
+            name: name.to_string(),
+            html_url: format!("https://github.com/example/{}", name),
+            stargazers_count: stars,
+            watchers_count: stars,
+            language: Some("Rust".to_string()),
+            created_at: "2020-01-01T00:00:00Z".to_string(),
+            pushed_at: "2024-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_database_records_runs_and_rankings() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("kstars.db");
+
+        for _ in 0..2 {
+            let db = Database::open(&path, "github", 2).unwrap();
+            db.write_language("Rust", &[repo("a", 20), repo("b", 10)])
+                .unwrap();
+            db.write_language("Go", &[repo("c", 30)]).unwrap();
+            db.finish().unwrap();
+        }
+
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        let (runs, languages): (i64, i64) = conn
+            .query_row(
+                "SELECT COUNT(*), SUM(languages) FROM runs WHERE finished_at IS NOT NULL",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((runs, languages), (2, 4));
+        let second: String = conn
+            .query_row(
+                "SELECT name FROM repos WHERE run_id = 2 AND language = 'Rust' AND rank = 2",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(second, "b");
+    }
+
+    #[test]
+    fn test_database_from_older_version_is_upgraded() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("kstars.db");
+        let old_schema = SCHEMA.replace(
+            "    license TEXT,\n    topics TEXT,\n    owner TEXT,\n    \
+             archived INTEGER NOT NULL DEFAULT 0,\n    fork INTEGER NOT NULL DEFAULT 0,\n    \
+             default_branch TEXT,\n    owner_avatar TEXT,\n    owner_type TEXT,\n",
+            "",
+        );
+        assert_ne!(old_schema, SCHEMA);
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch(&old_schema)
+            .unwrap();
+
+        let db = Database::open(&path, "github", 1).unwrap();
+        db.write_language("Rust", &[repo("a", 20)]).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{atomic::AtomicFile, error::Result};
+use anyhow::Context;
+use csv::{Reader, ReaderBuilder, Writer, WriterBuilder};
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+/// Parses a field delimiter given on the command line: a single ASCII character,
+/// or "tab" / "\t" for tab-separated files.
+pub fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value {
+        "tab" | "\\t" | "\t" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => Err(format!(
+            "expected a single ASCII character or \"tab\", got {:?}",
+            value
+        )),
+    }
+}
+
+/// File extension of delimited files: "tsv" when tab-separated, "csv" otherwise.
+pub fn extension(delimiter: u8) -> &'static str {
+    if delimiter == b'\t' { "tsv" } else { "csv" }
+}
+
+/// Opens a delimited file for reading; the first row is the header.
+pub fn reader(path: &Path, delimiter: u8) -> Result<Reader<File>> {
+    Ok(ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_path(path)
+        .with_context(|| format!("Failed to open {:?}", path))?)
+}
+
+/// Creates a delimited file for writing. It replaces `path` once committed with
+/// [`commit`]; dropping it leaves `path` untouched.
+pub fn writer(path: &Path, delimiter: u8) -> Result<Writer<AtomicFile>> {
+    let file = AtomicFile::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    Ok(WriterBuilder::new().delimiter(delimiter).from_writer(file))
+}
+
+/// Flushes a file opened with [`writer`] and moves it over its destination.
+pub fn commit(writer: Writer<AtomicFile>) -> Result<()> {
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
+}
+
+/// Marks the files of rows collected before a run was interrupted, e.g.
+/// "Rust.partial.csv". They are not result files.
+pub const PARTIAL_SUFFIX: &str = "partial";
+
+/// Marks the files of rows that changed since the previous snapshot, e.g.
+/// "Rust.delta.csv". They are not result files either.
+pub const DELTA_SUFFIX: &str = "delta";
+
+/// Lists the result files of a folder written with `delimiter`, sorted by name.
+pub fn result_files(dir: &Path, delimiter: u8) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("Failed to read directory: {:?}", dir))?
+    {
+        let path = entry?.path();
+        let derived = Path::new(path.file_stem().unwrap_or_default())
+            .extension()
+            .is_some_and(|ext| ext == PARTIAL_SUFFIX || ext == DELTA_SUFFIX);
+        if !derived
+            && path
+                .extension()
+                .is_some_and(|ext| ext == extension(delimiter))
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extension, parse_delimiter};
+
+    #[test]
+    fn test_parse_delimiter() {
+        assert_eq!(parse_delimiter(";"), Ok(b';'));
+        assert_eq!(parse_delimiter("tab"), Ok(b'\t'));
+        assert!(parse_delimiter(";;").is_err());
+        assert_eq!(extension(b'\t'), "tsv");
+        assert_eq!(extension(b';'), "csv");
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{delimited, error::Result};
+use anyhow::Context;
+use csv::StringRecord;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+/// Column the rows of two snapshots are matched on.
+const KEY_COLUMN: &str = "Repo URL";
+
+/// Path of the delta file of a result file, e.g. "Rust.delta.csv" for "Rust.csv".
+pub fn delta_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(
+        "{}.{}.{}",
+        stem,
+        delimited::DELTA_SUFFIX,
+        extension
+    ))
+}
+
+/// Values of a row by column name.
+type Row = HashMap<String, String>;
+
+/// Rows of a result file by repository URL, in ranking order.
+struct Table {
+    headers: Vec<String>,
+    rows: Vec<(String, Row)>,
+}
+
+/// A row that was added, removed or changed.
+struct Change<'a> {
+    kind: &'static str,
+    url: &'a str,
+    old: Option<&'a Row>,
+    new: Option<&'a Row>,
+}
+
+fn value<'a>(row: Option<&'a Row>, column: &str) -> &'a str {
+    row.and_then(|row| row.get(column))
+        .map_or("", String::as_str)
+}
+
+fn read_table(path: &Path, delimiter: u8) -> Result<Option<Table>> {
+    let mut reader = delimited::reader(path, delimiter)?;
+    let headers: Vec<String> = reader.headers()?.iter().map(str::to_string).collect();
+    let Some(key) = headers.iter().position(|h| h == KEY_COLUMN) else {
+        warn!(
+            "{:?} has no {:?} column; no delta is written.",
+            path, KEY_COLUMN
+        );
+        return Ok(None);
+    };
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.with_context(|| format!("Failed to read a row of {:?}", path))?;
+        let values = headers
+            .iter()
+            .cloned()
+            .zip(record.iter().map(str::to_string))
+            .collect();
+        rows.push((record.get(key).unwrap_or_default().to_string(), values));
+    }
+    Ok(Some(Table { headers, rows }))
+}
+
+/// Rows of a delta file by kind of change.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DeltaCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+/// Writes to `output` the rows of `new` that were added, removed or changed since
+/// `old`, with the old and new value of every column. Returns the number of rows
+/// written of each kind, or `None` when the files cannot be matched.
+pub fn write_delta(
+    old: &Path,
+    new: &Path,
+    output: &Path,
+    delimiter: u8,
+) -> Result<Option<DeltaCounts>> {
+    let (Some(old), Some(new)) = (read_table(old, delimiter)?, read_table(new, delimiter)?) else {
+        return Ok(None);
+    };
+    let mut columns: Vec<&String> = new.headers.iter().filter(|h| *h != KEY_COLUMN).collect();
+    for header in &old.headers {
+        if header != KEY_COLUMN && !columns.contains(&header) {
+            columns.push(header);
+        }
+    }
+    let old_rows: HashMap<&str, &Row> = old
+        .rows
+        .iter()
+        .map(|(url, row)| (url.as_str(), row))
+        .collect();
+    let new_urls: HashSet<&str> = new.rows.iter().map(|(url, _)| url.as_str()).collect();
+
+    let mut changes = Vec::new();
+    let mut counts = DeltaCounts::default();
+    for (url, row) in &new.rows {
+        let old = old_rows.get(url.as_str()).copied();
+        let kind = match old {
+            None => {
+                counts.added += 1;
+                "added"
+            }
+            Some(old) if old != row => {
+                counts.changed += 1;
+                "changed"
+            }
+            Some(_) => continue,
+        };
+        changes.push(Change {
+            kind,
+            url,
+            old,
+            new: Some(row),
+        });
+    }
+    for (url, row) in &old.rows {
+        if !new_urls.contains(url.as_str()) {
+            counts.removed += 1;
+            changes.push(Change {
+                kind: "removed",
+                url,
+                old: Some(row),
+                new: None,
+            });
+        }
+    }
+
+    let mut writer = delimited::writer(output, delimiter)?;
+    let mut header = StringRecord::from(vec!["Change", KEY_COLUMN]);
+    for column in &columns {
+        header.push_field(&format!("Old {}", column));
+        header.push_field(&format!("New {}", column));
+    }
+    writer.write_record(&header)?;
+    for change in &changes {
+        let mut record = StringRecord::from(vec![change.kind, change.url]);
+        for column in &columns {
+            record.push_field(value(change.old, column));
+            record.push_field(value(change.new, column));
+        }
+        writer.write_record(&record)?;
+    }
+    delimited::commit(writer)?;
+    Ok(Some(counts))
+}
+
+/// Result files of `dir` and of its subfolders, where nested `--filename-template`
+/// layouts write them. Hidden folders such as the page cache are skipped.
+fn nested_result_files(dir: &Path, delimiter: u8) -> Result<Vec<PathBuf>> {
+    let mut files = delimited::result_files(dir, delimiter)?;
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("Failed to read directory: {:?}", dir))?
+    {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+            files.extend(nested_result_files(&entry.path(), delimiter)?);
+        }
+    }
+    Ok(files)
+}
+
+/// Writes the delta file of every result file below `dir` that has a counterpart at
+/// the same path below `previous_dir`. Returns the number of delta files written.
+pub fn write_deltas(previous_dir: &Path, dir: &Path, delimiter: u8) -> Result<usize> {
+    let files = nested_result_files(dir, delimiter)?;
+    let mut matched = 0;
+    let mut written = 0;
+    for path in &files {
+        let relative = path.strip_prefix(dir).expect("result files are below dir");
+        let previous = previous_dir.join(relative);
+        if !previous.exists() {
+            continue;
+        }
+        matched += 1;
+        let output = delta_path(path);
+        if let Some(counts) = write_delta(&previous, path, &output, delimiter)? {
+            info!(
+                "{} added, {} removed and {} changed rows written to {:?}",
+                counts.added, counts.removed, counts.changed, output
+            );
+            written += 1;
+        }
+    }
+    if matched == 0 && !files.is_empty() {
+        warn!(
+            "None of the {} result files of {:?} has a counterpart in {:?}; no deltas written. \
+             File names that change between runs, e.g. with {{date}}, cannot be matched.",
+            files.len(),
+            dir,
+            previous_dir
+        );
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeltaCounts, delta_path, write_delta, write_deltas};
+    use crate::delimited;
+    use std::{fs, path::Path};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_deltas_keeps_changed_rows_only() {
+        let temp_dir = tempdir().unwrap();
+        let (old_dir, new_dir) = (temp_dir.path().join("old"), temp_dir.path().join("new"));
+        fs::create_dir_all(&old_dir).unwrap();
+        fs::create_dir_all(&new_dir).unwrap();
+        fs::write(
+            old_dir.join("Rust.csv"),
+            "Ranking,Repo URL,Stars\n\
+             1,https://github.com/a/a,300\n\
+             2,https://github.com/b/b,200\n\
+             3,https://github.com/c/c,100\n",
+        )
+        .unwrap();
+        fs::write(
+            new_dir.join("Rust.csv"),
+            "Ranking,Repo URL,Stars\n\
+             1,https://github.com/a/a,300\n\
+             2,https://github.com/c/c,250\n\
+             3,https://github.com/d/d,120\n",
+        )
+        .unwrap();
+        // Languages missing from the previous snapshot get no delta.
+        fs::write(new_dir.join("Go.csv"), "Ranking,Repo URL,Stars\n").unwrap();
+
+        assert_eq!(write_deltas(&old_dir, &new_dir, b',').unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(new_dir.join("Rust.delta.csv")).unwrap(),
+            "Change,Repo URL,Old Ranking,New Ranking,Old Stars,New Stars\n\
+             changed,https://github.com/c/c,3,2,100,250\n\
+             added,https://github.com/d/d,,3,,120\n\
+             removed,https://github.com/b/b,2,,200,\n"
+        );
+        assert!(!new_dir.join("Go.delta.csv").exists());
+        // Delta files are not result files themselves.
+        assert_eq!(delimited::result_files(&new_dir, b',').unwrap().len(), 2);
+        assert_eq!(
+            delta_path(Path::new("out/C++.tsv")),
+            Path::new("out/C++.delta.tsv")
+        );
+    }
+
+    #[test]
+    fn test_write_deltas_walks_nested_layouts() {
+        let temp_dir = tempdir().unwrap();
+        let (old_dir, new_dir) = (temp_dir.path().join("old"), temp_dir.path().join("new"));
+        for dir in [&old_dir, &new_dir] {
+            fs::create_dir_all(dir.join("github")).unwrap();
+            fs::create_dir_all(dir.join(".cache")).unwrap();
+            // Files of the page cache are never compared.
+            fs::write(dir.join(".cache/Rust.csv"), "Ranking,Repo URL\n").unwrap();
+        }
+        fs::write(
+            old_dir.join("github/Rust.csv"),
+            "Ranking,Repo URL,Stars\n\
+             1,https://github.com/a/a,300\n\
+             2,https://github.com/b/b,200\n",
+        )
+        .unwrap();
+        fs::write(
+            new_dir.join("github/Rust.csv"),
+            "Ranking,Repo URL,Stars\n\
+             1,https://github.com/a/a,350\n\
+             2,https://github.com/c/c,250\n",
+        )
+        .unwrap();
+
+        assert_eq!(write_deltas(&old_dir, &new_dir, b',').unwrap(), 1);
+        assert!(new_dir.join("github/Rust.delta.csv").exists());
+        assert!(!new_dir.join(".cache/Rust.delta.csv").exists());
+        assert_eq!(
+            write_delta(
+                &old_dir.join("github/Rust.csv"),
+                &new_dir.join("github/Rust.csv"),
+                &temp_dir.path().join("Rust.delta.csv"),
+                b',',
+            )
+            .unwrap(),
+            Some(DeltaCounts {
+                added: 1,
+                removed: 1,
+                changed: 1,
+            })
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use crate::{
+    delimited,
+    error::{KstarsError, Result, bail},
+};
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+/// Formats the diff of two result folders can be written in.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// Short summary per language.
+    Text,
+    /// One row per entered, dropped or moved repository.
+    Csv,
+    Json,
+    /// Tables per language, ready for a newsletter.
+    Markdown,
+}
+
+/// A repository listed in a result file.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RankedRepo {
+    pub name: String,
+    pub url: String,
+    /// Position in the ranking, starting at 1.
+    pub rank: usize,
+    /// Stars, when the file has a "Stars" column.
+    pub stars: Option<u64>,
+}
+
+/// A repository ranked in both results whose rank or stars changed.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RankChange {
+    pub name: String,
+    pub url: String,
+    pub old_rank: usize,
+    pub new_rank: usize,
+    /// Positions gained; negative when the repository fell.
+    pub rank_delta: i64,
+    pub star_delta: Option<i64>,
+}
+
+/// Changes of one language's ranking.
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct RankingDiff {
+    pub entered: Vec<RankedRepo>,
+    pub dropped: Vec<RankedRepo>,
+    /// Ordered by the new ranking.
+    pub moved: Vec<RankChange>,
+}
+
+/// Diff of the result file of one language.
+#[derive(Serialize, Debug)]
+pub struct LanguageDiff {
+    /// Name of the result file without its extension.
+    pub language: String,
+    #[serde(flatten)]
+    pub diff: RankingDiff,
+}
+
+/// Reads the repositories of a result file in ranking order.
+pub fn read_ranking(path: &Path, delimiter: u8) -> Result<Vec<RankedRepo>> {
+    let mut reader = delimited::reader(path, delimiter)?;
+    let headers = reader.headers()?.clone();
+    let position = |name: &str| headers.iter().position(|h| h == name);
+    let column = |name: &str| {
+        position(name).ok_or_else(|| KstarsError::SchemaMismatch {
+            path: path.to_path_buf(),
+            reason: format!("no \"{}\" column", name),
+        })
+    };
+    let (name_column, url_column) = (column("Project Name")?, column("Repo URL")?);
+    let stars_column = position("Stars");
+
+    let mut repos = Vec::new();
+    for record in reader.records() {
+        let record = record.with_context(|| format!("Failed to read a row of {:?}", path))?;
+        repos.push(RankedRepo {
+            name: record.get(name_column).unwrap_or_default().to_string(),
+            url: record.get(url_column).unwrap_or_default().to_string(),
+            rank: repos.len() + 1,
+            stars: stars_column
+                .and_then(|column| record.get(column))
+                .and_then(|stars| stars.parse().ok()),
+        });
+    }
+    Ok(repos)
+}
+
+/// Compares two rankings of the same language by repository URL.
+pub fn diff_rankings(old: &[RankedRepo], new: &[RankedRepo]) -> RankingDiff {
+    let old_by_url: HashMap<&str, &RankedRepo> = old.iter().map(|r| (r.url.as_str(), r)).collect();
+    let new_urls: HashSet<&str> = new.iter().map(|r| r.url.as_str()).collect();
+    let mut diff = RankingDiff {
+        dropped: old
+            .iter()
+            .filter(|r| !new_urls.contains(r.url.as_str()))
+            .cloned()
+            .collect(),
+        ..RankingDiff::default()
+    };
+    for repo in new {
+        let Some(before) = old_by_url.get(repo.url.as_str()) else {
+            diff.entered.push(repo.clone());
+            continue;
+        };
+        let star_delta = repo
+            .stars
+            .zip(before.stars)
+            .map(|(now, then)| now as i64 - then as i64);
+        if before.rank != repo.rank || star_delta.is_some_and(|delta| delta != 0) {
+            diff.moved.push(RankChange {
+                name: repo.name.clone(),
+                url: repo.url.clone(),
+                old_rank: before.rank,
+                new_rank: repo.rank,
+                rank_delta: before.rank as i64 - repo.rank as i64,
+                star_delta,
+            });
+        }
+    }
+    diff
+}
+
+/// Resolves a result folder given on the command line: an existing folder, or the
+/// name of a snapshot in `results_dir`, e.g. "2025-06-01" or "latest".
+pub fn resolve_dir(results_dir: &Path, dir: &str) -> Result<PathBuf> {
+    let path = Path::new(dir);
+    if path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    let snapshot = results_dir.join(dir);
+    if snapshot.is_dir() {
+        return Ok(snapshot);
+    }
+    bail!(
+        "{:?} is neither a folder nor a snapshot in {:?}",
+        dir,
+        results_dir
+    )
+}
+
+/// Diffs every result file present in both folders.
+pub fn diff_dirs(old_dir: &Path, new_dir: &Path, delimiter: u8) -> Result<Vec<LanguageDiff>> {
+    let mut diffs = Vec::new();
+    for old_path in delimited::result_files(old_dir, delimiter)? {
+        let file_name = old_path.file_name().expect("result files have a name");
+        let new_path = new_dir.join(file_name);
+        if !new_path.exists() {
+            warn!("{:?} has no counterpart in {:?}", file_name, new_dir);
+            continue;
+        }
+        diffs.push(LanguageDiff {
+            language: old_path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            diff: diff_rankings(
+                &read_ranking(&old_path, delimiter)?,
+                &read_ranking(&new_path, delimiter)?,
+            ),
+        });
+    }
+    Ok(diffs)
+}
+
+/// Formats a change with its sign, e.g. "+3" or "-12".
+fn signed(delta: i64) -> String {
+    format!("{:+}", delta)
+}
+
+fn render_text(diffs: &[LanguageDiff]) -> String {
+    let mut out = String::new();
+    for LanguageDiff { language, diff } in diffs {
+        let _ = writeln!(
+            out,
+            "{}: {} new, {} dropped, {} moved",
+            language,
+            diff.entered.len(),
+            diff.dropped.len(),
+            diff.moved.iter().filter(|c| c.rank_delta != 0).count()
+        );
+        for repo in &diff.entered {
+            let _ = writeln!(out, "  + {} ({})", repo.name, repo.url);
+        }
+        for repo in &diff.dropped {
+            let _ = writeln!(out, "  - {} ({})", repo.name, repo.url);
+        }
+        for change in diff.moved.iter().filter(|c| c.rank_delta != 0) {
+            let _ = writeln!(
+                out,
+                "  {} {} (#{} -> #{})",
+                signed(change.rank_delta),
+                change.name,
+                change.old_rank,
+                change.new_rank
+            );
+        }
+    }
+    out
+}
+
+fn render_csv(diffs: &[LanguageDiff], delimiter: u8) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
+    writer.write_record([
+        "Language",
+        "Change",
+        "Project Name",
+        "Repo URL",
+        "Old Rank",
+        "New Rank",
+        "Rank Delta",
+        "Stars Delta",
+    ])?;
+    let number = |n: Option<i64>| n.map(|n| n.to_string()).unwrap_or_default();
+    for LanguageDiff { language, diff } in diffs {
+        for repo in &diff.entered {
+            let rank = repo.rank.to_string();
+            writer.write_record([
+                language, "entered", &repo.name, &repo.url, "", &rank, "", "",
+            ])?;
+        }
+        for repo in &diff.dropped {
+            let rank = repo.rank.to_string();
+            writer.write_record([
+                language, "dropped", &repo.name, &repo.url, &rank, "", "", "",
+            ])?;
+        }
+        for change in &diff.moved {
+            writer.write_record([
+                language.as_str(),
+                "moved",
+                &change.name,
+                &change.url,
+                &change.old_rank.to_string(),
+                &change.new_rank.to_string(),
+                &change.rank_delta.to_string(),
+                &number(change.star_delta),
+            ])?;
+        }
+    }
+    let bytes = writer.into_inner().context("Failed to write the diff")?;
+    Ok(String::from_utf8(bytes).context("The diff is not valid UTF-8")?)
+}
+
+/// Escapes the characters that would break a table cell or a link.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['[', ']'], "")
+}
+
+/// Header and alignment rows of the Markdown tables.
+const ENTERED_TABLE_HEADER: &str = "| Rank | Project | Stars |\n\
+                                    |-----:|---------|------:|";
+const DROPPED_TABLE_HEADER: &str = "| Last Rank | Project |\n\
+                                    |----------:|---------|";
+const MOVERS_TABLE_HEADER: &str = "| Rank | Change | Project | Stars Δ |\n\
+                                   |-----:|-------:|---------|--------:|";
+
+fn render_markdown(diffs: &[LanguageDiff]) -> String {
+    let mut out = String::new();
+    for LanguageDiff { language, diff } in diffs {
+        let _ = writeln!(out, "## {}\n", language);
+        if !diff.entered.is_empty() {
+            let _ = writeln!(out, "### New entrants\n\n{}", ENTERED_TABLE_HEADER);
+            for repo in &diff.entered {
+                let stars = repo.stars.map(|s| s.to_string()).unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    "| {} | [{}]({}) | {} |",
+                    repo.rank,
+                    cell(&repo.name),
+                    repo.url,
+                    stars
+                );
+            }
+            out.push('\n');
+        }
+        if !diff.dropped.is_empty() {
+            let _ = writeln!(out, "### Dropped\n\n{}", DROPPED_TABLE_HEADER);
+            for repo in &diff.dropped {
+                let _ = writeln!(
+                    out,
+                    "| {} | [{}]({}) |",
+                    repo.rank,
+                    cell(&repo.name),
+                    repo.url
+                );
+            }
+            out.push('\n');
+        }
+        let mut movers: Vec<&RankChange> =
+            diff.moved.iter().filter(|c| c.rank_delta != 0).collect();
+        if !movers.is_empty() {
+            movers.sort_by_key(|c| std::cmp::Reverse(c.rank_delta.abs()));
+            let _ = writeln!(out, "### Biggest movers\n\n{}", MOVERS_TABLE_HEADER);
+            for change in movers {
+                let stars = change.star_delta.map(signed).unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | [{}]({}) | {} |",
+                    change.new_rank,
+                    signed(change.rank_delta),
+                    cell(&change.name),
+                    change.url,
+                    stars
+                );
+            }
+            out.push('\n');
+        }
+        if diff.entered.is_empty() && diff.dropped.is_empty() && diff.moved.is_empty() {
+            out.push_str("No changes.\n\n");
+        }
+    }
+    out
+}
+
+/// Compares the result files of `old_dir` and `new_dir` and writes the changes of
+/// every language in `format` to `output`, or prints them. Returns the changes.
+pub fn run(
+    old_dir: &Path,
+    new_dir: &Path,
+    delimiter: u8,
+    format: DiffFormat,
+    output: Option<&Path>,
+) -> Result<Vec<LanguageDiff>> {
+    let diffs = diff_dirs(old_dir, new_dir, delimiter)?;
+    let rendered = match format {
+        DiffFormat::Text => render_text(&diffs),
+        DiffFormat::Csv => render_csv(&diffs, delimiter)?,
+        DiffFormat::Json => serde_json::to_string_pretty(&diffs)? + "\n",
+        DiffFormat::Markdown => render_markdown(&diffs),
+    };
+    match output {
+        Some(path) => {
+            fs::write(path, rendered).with_context(|| format!("Failed to write {:?}", path))?;
+            info!("Diff written to {:?}", path);
+        }
+        None => print!("{}", rendered),
+    }
+    info!("Compared {:?} with {:?}", old_dir, new_dir);
+    Ok(diffs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LanguageDiff, RankChange, RankedRepo, diff_rankings, render_csv};
+
+    fn ranked(name: &str, rank: usize, stars: u64) -> RankedRepo {
+        RankedRepo {
+            name: name.to_string(),
+            url: format!("https://github.com/example/{}", name),
+            rank,
+            stars: Some(stars),
+        }
+    }
+
+    #[test]
+    fn test_diff_rankings() {
+        let old = [
+            ranked("a", 1, 300),
+            ranked("b", 2, 200),
+            ranked("c", 3, 100),
+        ];
+        let new = [
+            ranked("b", 1, 350),
+            ranked("d", 2, 250),
+            ranked("a", 3, 240),
+        ];
+
+        let diff = diff_rankings(&old, &new);
+
+        assert_eq!(diff.entered, [ranked("d", 2, 250)]);
+        assert_eq!(diff.dropped, [ranked("c", 3, 100)]);
+        assert_eq!(
+            diff.moved[0],
+            RankChange {
+                name: "b".to_string(),
+                url: "https://github.com/example/b".to_string(),
+                old_rank: 2,
+                new_rank: 1,
+                rank_delta: 1,
+                star_delta: Some(150),
+            }
+        );
+        assert_eq!(diff.moved[1].rank_delta, -2);
+        assert_eq!(diff.moved[1].star_delta, Some(-60));
+
+        let csv = render_csv(
+            &[LanguageDiff {
+                language: "Rust".to_string(),
+                diff,
+            }],
+            b',',
+        )
+        .unwrap();
+        assert!(csv.contains("Rust,entered,d,https://github.com/example/d,,2,,\n"));
+        assert!(csv.contains("Rust,moved,a,https://github.com/example/a,1,3,-2,-60\n"));
+    }
+}
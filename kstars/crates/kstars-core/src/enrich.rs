@@ -0,0 +1,417 @@
+use crate::{
+    Repo, atomic,
+    cache::{self, CachePolicy},
+    error::Result,
+    forge::{self, ForgeClient},
+};
+use anyhow::Context;
+use chrono::Utc;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path, time::Duration};
+use tracing::{info, warn};
+
+/// Extra data fetched per repository once a ranking is final. Each one costs an
+/// additional request for every repository, so they are opt-in.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Enrichment {
+    /// Tag and date of the latest release, and the number of releases.
+    Releases,
+    /// Number of commits over the last 52 weeks.
+    CommitActivity,
+    /// First paragraph of the README, a fallback for empty descriptions.
+    Readme,
+    /// Number of accounts watching the repository, which the search reports as
+    /// its stars.
+    Subscribers,
+    /// Closed issues and the share of issues that were closed, a better sign of
+    /// upkeep than the open issues alone.
+    Issues,
+    /// Open and merged pull requests, which tell active projects from mirrored
+    /// dumps.
+    PullRequests,
+    /// Whether the repository accepts sponsorship, from its FUNDING.yml.
+    Funding,
+    /// Whether the repository has a security policy and CI workflows. Costs up to
+    /// four requests per repository.
+    Hygiene,
+    /// Approximate stars over time of the top repositories, sampled from their
+    /// stargazers and written to a JSON sidecar of the ranking.
+    StarHistory,
+}
+
+/// README excerpts longer than this many characters are cut short.
+const MAX_EXCERPT_CHARS: usize = 300;
+
+/// Stargazer pages requested per repository by the `star-history` enrichment.
+const STAR_HISTORY_SAMPLES: u64 = 10;
+
+/// Stargazers per page of the stargazers API.
+const STARGAZERS_PER_PAGE: u64 = 100;
+
+/// GitHub lists at most this many pages of stargazers, so the curve of larger
+/// repositories stops at 40 000 stars before its last point.
+const MAX_STARGAZER_PAGES: u64 = 400;
+
+/// Sampled star histories are fetched again after this long, unless the cache
+/// policy asks for less.
+const STAR_HISTORY_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Suffix of the sidecar holding the star histories of a ranking, e.g.
+/// "Rust.star_history.json".
+pub const STAR_HISTORY_SUFFIX: &str = "star_history.json";
+
+/// Point of a star history, written as a compact `["2024-05-01", 1200]` pair.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StarPoint(pub String, pub u64);
+
+/// Enrichments looked up for every repository of a ranking.
+pub fn per_repo(enrichments: &[Enrichment]) -> impl Iterator<Item = Enrichment> + '_ {
+    enrichments
+        .iter()
+        .copied()
+        .filter(|&e| e != Enrichment::StarHistory)
+}
+
+/// Adds the requested enrichments to every repository of a ranking. A repository
+/// whose lookup fails is logged and left without the data. Star histories are
+/// gathered separately by [`star_histories`]. Returns the number of requests sent.
+pub async fn enrich_repos(
+    forge: &dyn ForgeClient,
+    repos: &mut [Repo],
+    enrichments: &[Enrichment],
+) -> u32 {
+    // Lookups cost from none, for data the search brought, to several requests.
+    let ((), requests) = forge::count_requests(async {
+        for enrichment in per_repo(enrichments) {
+            info!(
+                "Enriching {} repositories with {:?}",
+                repos.len(),
+                enrichment
+            );
+            for repo in repos.iter_mut() {
+                let result = match enrichment {
+                    Enrichment::Releases => fetch_releases(forge, repo).await,
+                    Enrichment::CommitActivity => forge
+                        .commits_last_year(repo)
+                        .await
+                        .map(|commits| repo.details.commits_last_year = commits),
+                    Enrichment::Readme => forge.readme(repo).await.map(|readme| {
+                        repo.details.readme_excerpt = readme.as_deref().and_then(readme_excerpt)
+                    }),
+                    // The GraphQL search already reads them.
+                    Enrichment::Subscribers if repo.details.subscribers_count.is_some() => Ok(()),
+                    Enrichment::Subscribers => forge
+                        .subscribers_count(repo)
+                        .await
+                        .map(|count| repo.details.subscribers_count = count),
+                    Enrichment::Issues if repo.details.closed_issues.is_some() => Ok(()),
+                    Enrichment::Issues => forge.issue_counts(repo).await.map(|counts| {
+                        if let Some(counts) = counts {
+                            repo.details.set_issue_counts(counts);
+                        }
+                    }),
+                    Enrichment::PullRequests if repo.details.pull_requests.is_some() => Ok(()),
+                    Enrichment::PullRequests => forge
+                        .pull_request_counts(repo)
+                        .await
+                        .map(|counts| repo.details.pull_requests = counts),
+                    Enrichment::Funding if repo.details.sponsorable.is_some() => Ok(()),
+                    Enrichment::Funding => forge
+                        .sponsorable(repo)
+                        .await
+                        .map(|sponsorable| repo.details.sponsorable = sponsorable),
+                    Enrichment::Hygiene => forge
+                        .hygiene(repo)
+                        .await
+                        .map(|hygiene| repo.details.hygiene = hygiene),
+                    Enrichment::StarHistory => unreachable!("not a per-repository enrichment"),
+                };
+                if let Err(e) = result {
+                    warn!(
+                        "Failed to fetch {:?} for {}: {}",
+                        enrichment, repo.html_url, e
+                    );
+                }
+            }
+        }
+    })
+    .await;
+    requests
+}
+
+/// Reads the latest release of `repo` and, unless the search brought it, its number
+/// of releases.
+async fn fetch_releases(forge: &dyn ForgeClient, repo: &mut Repo) -> Result<()> {
+    repo.details.latest_release = forge.latest_release(repo).await?;
+    if repo.details.release_count.is_none() {
+        repo.details.release_count = forge.release_count(repo).await?;
+    }
+    Ok(())
+}
+
+/// Stargazer pages to sample for a repository with `stars` stars: every page when
+/// there are few, else pages spread evenly from the first to the last listed one.
+fn sample_pages(stars: u64) -> Vec<u64> {
+    let pages = stars.div_ceil(STARGAZERS_PER_PAGE).min(MAX_STARGAZER_PAGES);
+    if pages <= STAR_HISTORY_SAMPLES {
+        return (1..=pages).collect();
+    }
+    (0..STAR_HISTORY_SAMPLES)
+        .map(|i| 1 + i * (pages - 1) / (STAR_HISTORY_SAMPLES - 1))
+        .collect()
+}
+
+/// Samples the stargazers of `repo`: the first star of each sampled page gives the
+/// date the repository reached that many stars. Returns the points and the number
+/// of requests sent.
+async fn sample_star_history(
+    forge: &dyn ForgeClient,
+    repo: &Repo,
+) -> Result<(Vec<StarPoint>, u32)> {
+    let mut points = Vec::new();
+    let mut requests = 0;
+    for page in sample_pages(repo.stargazers_count) {
+        let dates = forge.stargazer_dates(repo, page as u32).await?;
+        requests += 1;
+        let Some(first) = dates.first() else {
+            break;
+        };
+        let date = first.get(..10).unwrap_or(first).to_string();
+        points.push(StarPoint(date, (page - 1) * STARGAZERS_PER_PAGE + 1));
+    }
+    Ok((points, requests))
+}
+
+/// Approximate star histories of the first `top` repositories of a ranking, keyed by
+/// URL and ending with today's star count. Samples are cached per repository in
+/// `cache_root`; repositories whose lookup fails, or whose forge has no star dates,
+/// are left out. Returns the histories and the number of requests sent.
+pub async fn star_histories(
+    forge: &dyn ForgeClient,
+    repos: &[Repo],
+    top: usize,
+    cache_root: &Path,
+    policy: CachePolicy,
+) -> (BTreeMap<String, Vec<StarPoint>>, u32) {
+    let cache_dir = cache_root.join(cache::STAR_HISTORY_DIR_NAME);
+    let max_age = policy.max_age.unwrap_or(STAR_HISTORY_MAX_AGE);
+    let today = Utc::now().date_naive().to_string();
+    let mut histories = BTreeMap::new();
+    let mut requests = 0;
+    let repos = &repos[..top.min(repos.len())];
+    info!("Sampling the star history of {} repositories", repos.len());
+    for repo in repos {
+        let Some(owner) = repo.owner_login() else {
+            continue;
+        };
+        let cache_file = cache_dir.join(format!("{}__{}.json", owner, repo.name));
+        let cached = (!policy.refresh)
+            .then(|| cache::page_age(&cache_file))
+            .flatten()
+            .filter(|age| *age <= max_age)
+            .and_then(|_| fs::read_to_string(&cache_file).ok())
+            .and_then(|json| serde_json::from_str::<Vec<StarPoint>>(&json).ok());
+        let mut points = match cached {
+            Some(points) => points,
+            None => match sample_star_history(forge, repo).await {
+                Ok((points, sent)) => {
+                    requests += sent;
+                    if let Err(e) = save_star_history(&cache_file, &points) {
+                        warn!(
+                            "Failed caching the star history of {}: {}",
+                            repo.html_url, e
+                        );
+                    }
+                    points
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to fetch {:?} for {}: {}",
+                        Enrichment::StarHistory,
+                        repo.html_url,
+                        e
+                    );
+                    continue;
+                }
+            },
+        };
+        if points.is_empty() {
+            continue;
+        }
+        points.push(StarPoint(today.clone(), repo.stargazers_count));
+        histories.insert(repo.html_url.clone(), points);
+    }
+    (histories, requests)
+}
+
+fn save_star_history(cache_file: &Path, points: &[StarPoint]) -> Result<()> {
+    if let Some(dir) = cache_file.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    }
+    atomic::write(cache_file, serde_json::to_string(points)?)?;
+    cache::write_timestamp(cache_file)?;
+    Ok(())
+}
+
+/// Whether a Markdown line belongs to a prose paragraph rather than a heading,
+/// badge, image, HTML block, table, list or rule.
+fn is_prose(line: &str) -> bool {
+    !line.is_empty()
+        && !line.starts_with(['#', '<', '|', '>', '-', '*', '=', '+'])
+        && !line.starts_with("![")
+        && !line.starts_with("[![")
+}
+
+/// Drops emphasis and code markers and keeps only the text of links.
+fn strip_inline_markup(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '['
+            && let Some((label, after)) = rest[1..].split_once("](")
+            && let Some((_, tail)) = after.split_once(')')
+        {
+            out.push_str(label);
+            rest = tail;
+            continue;
+        }
+        if !matches!(c, '*' | '`') {
+            out.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Extracts the first prose paragraph of a Markdown README as plain text.
+pub fn readme_excerpt(readme: &str) -> Option<String> {
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut in_code_block = false;
+    for line in readme.lines().map(str::trim) {
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        if is_prose(line) {
+            paragraph.push(line);
+        } else if !paragraph.is_empty() {
+            break;
+        }
+    }
+
+    let text = strip_inline_markup(&paragraph.join(" "));
+    let excerpt = match text.char_indices().nth(MAX_EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text,
+    };
+    (!excerpt.is_empty()).then_some(excerpt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Enrichment, StarPoint, enrich_repos, readme_excerpt, sample_pages};
+    use crate::{
+        Repo,
+        github::{ApiBackend, GITHUB_API_URL, GithubClient},
+        rate_limit::RateLimiter,
+        token_pool::TokenPool,
+        transport::ReplayTransport,
+    };
+    use reqwest::Method;
+    use std::{sync::Arc, time::Duration};
+
+    #[test]
+    fn test_readme_excerpt_skips_headings_and_badges() {
+        let readme = "# Tool\n\
+                      [![CI](https://ci.example.com/badge.svg)](https://ci.example.com)\n\
+                      \n\
+                      ```sh\n\
+                      cargo install tool\n\
+                      ```\n\
+                      A **fast** tool for\n\
+                      [parsing](https://example.com) `logs`.\n\
+                      \n\
+                      Second paragraph.\n";
+        assert_eq!(
+            readme_excerpt(readme).as_deref(),
+            Some("A fast tool for parsing logs.")
+        );
+        assert_eq!(readme_excerpt("# Only a title\n"), None);
+    }
+
+    #[test]
+    fn test_sample_pages_spread_over_listed_stargazers() {
+        assert_eq!(sample_pages(0), Vec::<u64>::new());
+        assert_eq!(sample_pages(250), [1, 2, 3]);
+        assert_eq!(sample_pages(1000), (1..=10).collect::<Vec<_>>());
+        assert_eq!(sample_pages(1901), [1, 3, 5, 7, 9, 11, 13, 15, 17, 20]);
+        // Only the first 400 pages can be listed.
+        let pages = sample_pages(250_000);
+        assert_eq!((pages[0], pages[9]), (1, 400));
+        assert_eq!(
+            serde_json::to_string(&StarPoint("2024-05-01".to_string(), 101)).unwrap(),
+            r#"["2024-05-01",101]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enrich_repos_counts_the_requests_sent() {
+        let repos = "https://api.github.com/repos/o/r";
+        let transport = ReplayTransport::new()
+            .with(
+                Method::GET,
+                &format!("{}/contents/SECURITY.md", repos),
+                404,
+                &[],
+                "",
+            )
+            .with(
+                Method::GET,
+                &format!("{}/contents/.github/SECURITY.md", repos),
+                404,
+                &[],
+                "",
+            )
+            .with(
+                Method::GET,
+                &format!("{}/contents/docs/SECURITY.md", repos),
+                404,
+                &[],
+                "",
+            )
+            .with(
+                Method::GET,
+                &format!("{}/contents/.github/workflows", repos),
+                404,
+                &[],
+                "",
+            );
+        let github = GithubClient::new(
+            reqwest::Client::new(),
+            TokenPool::new(vec![]),
+            RateLimiter::new(Duration::ZERO),
+            ApiBackend::Rest,
+            GITHUB_API_URL,
+        )
+        .with_transport(Arc::new(transport));
+        let mut repo: Repo = serde_json::from_value(serde_json::json!({
+            "name": "r", "html_url": "https://github.com/o/r", "stargazers_count": 1,
+            "forks_count": 0, "watchers_count": 1, "language": null, "description": null,
+            "open_issues_count": 0, "created_at": "", "pushed_at": "", "size": 0,
+            "owner": {"login": "o"},
+        }))
+        .unwrap();
+        // The search already brought the subscribers, so they cost nothing.
+        repo.details.subscribers_count = Some(3);
+
+        let enrichments = [Enrichment::Hygiene, Enrichment::Subscribers];
+        let requests = enrich_repos(&github, std::slice::from_mut(&mut repo), &enrichments).await;
+        assert_eq!(requests, 4);
+        assert!(!repo.details.hygiene.unwrap().security_policy);
+    }
+}
@@ -0,0 +1,110 @@
+use reqwest::StatusCode;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Result of the fallible functions of this crate.
+pub type Result<T, E = KstarsError> = std::result::Result<T, E>;
+
+/// Failures of this crate, for callers that react to some of them, e.g. by
+/// waiting for a rate limit to reset or asking for another token. Errors given
+/// context on their way up end up in [`KstarsError::Other`]; [`KstarsError::kind`]
+/// finds the failure they wrap.
+#[derive(Debug, Error)]
+pub enum KstarsError {
+    /// Every token hit its rate limit and waiting for it would take too long.
+    #[error("Rate limit exhausted until {reset} (Unix time)")]
+    RateLimited {
+        /// Unix time at which the limit resets.
+        reset: u64,
+    },
+    /// The forge rejected the credentials, or none were given.
+    #[error("Authentication failed: {0}")]
+    AuthFailed(String),
+    /// The forge answered a request with an error status.
+    #[error("Request failed with status {status}: {message}")]
+    Api { status: StatusCode, message: String },
+    /// A file written by kstars has an unsupported layout, e.g. a manifest of a
+    /// newer version or a result file without a required column.
+    #[error("{path:?} does not match the expected schema: {reason}")]
+    SchemaMismatch { path: PathBuf, reason: String },
+    /// A cached page can't be read back; fetching again replaces it.
+    #[error("Cache file {path:?} is corrupt: {source}")]
+    CacheCorrupt {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// Another run holds the lock of the output folder.
+    #[error("{path:?} is held by {holder}")]
+    Locked { path: PathBuf, holder: String },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[cfg(feature = "postgres")]
+    #[error(transparent)]
+    Postgres(#[from] tokio_postgres::Error),
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[error(transparent)]
+    Git(#[from] git2::Error),
+    /// Any other failure, with the context it was given.
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Returns early with a [`KstarsError::Other`], formatted like `anyhow::bail!`.
+macro_rules! bail {
+    ($($arg:tt)*) => {
+        return Err($crate::error::KstarsError::Other(anyhow::anyhow!($($arg)*)))
+    };
+}
+pub(crate) use bail;
+
+impl KstarsError {
+    /// The most specific failure this error wraps: itself, unless it is an
+    /// [`KstarsError::Other`] whose chain holds one of the other variants.
+    pub fn kind(&self) -> &KstarsError {
+        match self {
+            KstarsError::Other(error) => error
+                .chain()
+                .skip(1)
+                .find_map(|cause| cause.downcast_ref::<KstarsError>())
+                .map_or(self, KstarsError::kind),
+            _ => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KstarsError;
+    use anyhow::Context;
+
+    #[test]
+    fn test_kind_finds_the_wrapped_failure() {
+        let failure: Result<(), KstarsError> = Err(KstarsError::RateLimited { reset: 1700000000 });
+        let error = KstarsError::from(
+            failure
+                .context("Failed to fetch page 3")
+                .context("Failed to fetch Rust")
+                .unwrap_err(),
+        );
+
+        assert_eq!(
+            format!("{:#}", error),
+            "Failed to fetch Rust: Failed to fetch page 3: Rate limit exhausted until 1700000000 (Unix time)"
+        );
+        assert!(matches!(
+            error.kind(),
+            KstarsError::RateLimited { reset: 1700000000 }
+        ));
+        let other = KstarsError::from(anyhow::anyhow!("Something else"));
+        assert!(matches!(other.kind(), KstarsError::Other(_)));
+    }
+}
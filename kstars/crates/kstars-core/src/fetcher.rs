@@ -0,0 +1,1743 @@
+#[cfg(feature = "bigquery")]
+use crate::bigquery_sink::{BigQuerySink, BigQueryTable};
+#[cfg(feature = "postgres")]
+use crate::postgres_sink;
+#[cfg(feature = "sheets")]
+use crate::sheets_sink;
+use crate::{
+    LanguageMapping, OutputFormat, Repo, atomic,
+    cache::{self, CachePolicy},
+    columns::{self, Column},
+    database::{self, Database, SqliteSink},
+    delimited,
+    enrich::{self, Enrichment},
+    error::{KstarsError, Result, bail},
+    filters::{self, SearchFilters, SearchSort, TieBreak},
+    forge::{ForgeClient, SearchPage},
+    history::History,
+    layout::{FileNameValues, FilenameTemplate},
+    markdown::MarkdownSink,
+    merge,
+    metrics::Metrics,
+    parquet_writer::ParquetSink,
+    progress::Progress,
+    report::{LanguageReport, LanguageStatus, RunReport},
+    run_state::RunState,
+    sanitize::Sanitizer,
+    shutdown::Shutdown,
+    sink::{self, CsvSink, JsonSink, JsonlSink, OutputSink},
+};
+use anyhow::Context;
+use chrono::Local;
+use indicatif::ProgressBar;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+use tokio::{
+    sync::{Notify, Semaphore},
+    task::JoinSet,
+};
+use tracing::{debug, error, info, warn};
+
+/// Settings and shared handles used by every language task.
+struct FetchContext {
+    forge: Box<dyn ForgeClient>,
+    records: u32,
+    output_dir: String,
+    /// Path of the result files of a language below `output_dir`.
+    filename_template: FilenameTemplate,
+    /// Date of the run, filled into the file names.
+    date: String,
+    formats: Vec<OutputFormat>,
+    /// Field separator of CSV files.
+    delimiter: u8,
+    /// Columns of CSV files.
+    columns: Vec<Column>,
+    /// Prepended to the names of the datasets: "trending_" for trending runs.
+    dataset_prefix: &'static str,
+    /// Enrichments applied to each ranking before it is written.
+    enrichments: Vec<Enrichment>,
+    /// Repositories per language whose star history is sampled.
+    star_history_top: usize,
+    /// Filters every repository has to pass.
+    filters: SearchFilters,
+    /// Cleans up descriptions before they are written.
+    sanitizer: Option<Sanitizer>,
+    /// Order of the search results and of the rankings.
+    sort: SearchSort,
+    /// Order of repositories that rank equal.
+    tie_breaks: Vec<TieBreak>,
+    /// Cached pages that may be reused.
+    cache: CachePolicy,
+    /// Languages completed by this run and the one it resumes.
+    run_state: RunState,
+    /// Set when the run is interrupted.
+    shutdown: Shutdown,
+    /// Stop the run when a language fails.
+    fail_fast: bool,
+    /// Merge the rankings into the files of previous runs instead of replacing them.
+    merge: bool,
+    /// Turns every page requested from the forge waits for when languages interleave.
+    page_turns: Option<PageTurns>,
+    /// Progress bars of the run.
+    progress: Progress,
+    /// Counters of the run for monitoring.
+    metrics: Arc<Metrics>,
+    /// Shared database, opened when the SQLite format is requested.
+    database: Option<Arc<Database>>,
+    /// Time series the rankings are appended to.
+    history: Option<History>,
+    #[cfg(feature = "postgres")]
+    postgres: Option<postgres_sink::PostgresSink>,
+    #[cfg(feature = "sheets")]
+    sheets: Option<sheets_sink::SheetsSink>,
+    #[cfg(feature = "bigquery")]
+    bigquery: Option<BigQuerySink>,
+}
+
+impl FetchContext {
+    /// Name of the dataset of a language in files, databases and the run state.
+    fn dataset_name(&self, language: &str) -> String {
+        format!("{}{}", self.dataset_prefix, language)
+    }
+}
+
+/// Gets the path to the cache directory for a specific language.
+/// GitHub keeps the original layout; other forges get their own subfolder.
+fn get_language_cache_dir(output_dir: &str, forge_name: &str, language_api_name: &str) -> PathBuf {
+    let cache_root = PathBuf::from(output_dir).join(cache::CACHE_DIR_NAME); // Store cache in a hidden subfolder
+    match forge_name {
+        "github" => cache_root.join(language_api_name),
+        other => cache_root.join(other).join(language_api_name),
+    }
+}
+
+/// Gets the path to the cache directory of a star bucket beyond the first one.
+fn get_bucket_cache_dir(language_cache_dir: &Path, bucket: u32) -> PathBuf {
+    language_cache_dir.join(format!("bucket_{}", bucket))
+}
+
+/// Gets the path to the cache file for a specific page.
+fn get_page_cache_file_path(cache_dir: &Path, page: u32) -> PathBuf {
+    cache_dir.join(format!("page_{}.json", page))
+}
+
+/// Gets the path to the file storing the cursor of the page that follows a cached page.
+fn get_page_cursor_file_path(cache_dir: &Path, page: u32) -> PathBuf {
+    cache_dir.join(format!("page_{}.cursor", page))
+}
+
+/// Saves a list of repositories for a specific page to its cache file.
+fn save_page_to_cache(path: &Path, repos: &[Repo]) -> Result<()> {
+    debug!("Saving page cache to: {:?}", path);
+    let file =
+        File::create(path).with_context(|| format!("Failed to create cache file: {:?}", path))?;
+    let writer = BufWriter::new(file);
+    serde_json::to_writer(writer, repos)
+        .with_context(|| format!("Failed to serialize and write cache file: {:?}", path))?;
+    debug!("Page cache saved successfully.");
+    Ok(())
+}
+
+/// Loads a list of repositories for a specific page from its cache file.
+fn load_page_from_cache(path: &Path) -> Result<Vec<Repo>> {
+    debug!("Attempting to load page cache from: {:?}", path);
+    let file =
+        File::open(path).with_context(|| format!("Failed to open cache file: {:?}", path))?;
+    let reader = BufReader::new(file);
+    let repos: Vec<Repo> =
+        serde_json::from_reader(reader).map_err(|source| KstarsError::CacheCorrupt {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    info!("Loaded {} repos from cache file: {:?}", repos.len(), path);
+    Ok(repos)
+}
+
+/// Loads a cached page together with the cursor of the page after it.
+/// Returns `None` when the page is missing, incomplete, corrupted or stale.
+fn load_cached_search_page(cache_dir: &Path, page: u32, policy: CachePolicy) -> Option<SearchPage> {
+    let page_cache_file = get_page_cache_file_path(cache_dir, page);
+    let cursor_file = get_page_cursor_file_path(cache_dir, page);
+    if !page_cache_file.exists() || !cursor_file.exists() {
+        return None;
+    }
+    if !policy.allows(&page_cache_file) {
+        debug!("Ignoring stale cache file {:?}", page_cache_file);
+        return None;
+    }
+
+    match load_page_from_cache(&page_cache_file) {
+        Ok(repos) => {
+            let next_cursor = fs::read_to_string(&cursor_file)
+                .ok()
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty());
+            Some(SearchPage {
+                repos,
+                next_cursor,
+                ..SearchPage::default()
+            })
+        }
+        Err(e) => {
+            warn!(
+                "Failed to load cache file {:?}: {}. Will attempt to fetch from API.",
+                page_cache_file, e
+            );
+            // Remove the corrupted cache file
+            let _ = fs::remove_file(&page_cache_file);
+            None
+        }
+    }
+}
+
+/// Saves a fetched page, the cursor of the page after it and when it was fetched.
+fn save_cached_search_page(cache_dir: &Path, page: u32, search_page: &SearchPage) -> Result<()> {
+    let page_cache_file = get_page_cache_file_path(cache_dir, page);
+    save_page_to_cache(&page_cache_file, &search_page.repos)?;
+    let cursor_file = get_page_cursor_file_path(cache_dir, page);
+    fs::write(
+        &cursor_file,
+        search_page.next_cursor.as_deref().unwrap_or(""),
+    )
+    .with_context(|| format!("Failed to write cursor file: {:?}", cursor_file))?;
+    cache::write_timestamp(&page_cache_file)
+        .with_context(|| format!("Failed to write timestamp of {:?}", page_cache_file))?;
+    Ok(())
+}
+
+/// Turns of the languages fetching pages together. A language takes its next turn
+/// once no other language has taken fewer, so page requests go round-robin across
+/// languages while still overlapping. Only the choice of the next turn is locked.
+#[derive(Default)]
+struct PageTurns {
+    /// Turns taken by every seated language, by seat.
+    taken: Mutex<HashMap<u64, u64>>,
+    next_seat: AtomicU64,
+    changed: Notify,
+}
+
+impl PageTurns {
+    /// Seats a language for as long as the seat lives. It starts level with the
+    /// languages already seated, so it neither jumps ahead of them nor holds them back.
+    fn seat(&self) -> PageSeat<'_> {
+        let id = self.next_seat.fetch_add(1, Ordering::Relaxed);
+        let mut taken = self
+            .taken
+            .lock()
+            .expect("page turns lock is never poisoned");
+        let start = taken.values().min().copied().unwrap_or(0);
+        taken.insert(id, start);
+        PageSeat { turns: self, id }
+    }
+}
+
+/// Place of a language in [`PageTurns`].
+struct PageSeat<'a> {
+    turns: &'a PageTurns,
+    id: u64,
+}
+
+impl PageSeat<'_> {
+    /// Waits until no other seated language has taken fewer turns, then takes one.
+    async fn take_turn(&self) {
+        loop {
+            // Registered before checking, so a change in between is not missed.
+            let changed = self.turns.changed.notified();
+            {
+                let mut taken = self
+                    .turns
+                    .taken
+                    .lock()
+                    .expect("page turns lock is never poisoned");
+                let own = taken[&self.id];
+                if taken.values().all(|&other| other >= own) {
+                    taken.insert(self.id, own + 1);
+                    drop(taken);
+                    self.turns.changed.notify_waiters();
+                    return;
+                }
+            }
+            changed.await;
+        }
+    }
+}
+
+impl Drop for PageSeat<'_> {
+    fn drop(&mut self) {
+        self.turns
+            .taken
+            .lock()
+            .expect("page turns lock is never poisoned")
+            .remove(&self.id);
+        self.turns.changed.notify_waiters();
+    }
+}
+
+/// Fetches up to `records` repositories for a single search query, caching each page
+/// in `cache_dir`. Pages are followed until the forge reports no further page.
+/// Cached pages are reused as the cache policy allows. Once the run is interrupted, the
+/// pages collected so far are returned. Also returns the total the forge reported
+/// with the first page, unless that page came from the cache.
+async fn fetch_search_pages(
+    ctx: &FetchContext,
+    query: &str,
+    records: u32,
+    cache_dir: &Path,
+    bar: &ProgressBar,
+    report: &mut LanguageReport,
+) -> Result<(Vec<Repo>, Option<u64>)> {
+    let (forge, shutdown) = (ctx.forge.as_ref(), &ctx.shutdown);
+    fs::create_dir_all(cache_dir)
+        .with_context(|| format!("Failed to create cache directory: {:?}", cache_dir))?;
+    info!("Using cache directory: {:?}", cache_dir);
+
+    let mut all_repos = Vec::new();
+    let mut cursor: Option<String> = None;
+    let mut total_count = None;
+    let seat = ctx.page_turns.as_ref().map(PageTurns::seat);
+
+    for page in 1u32.. {
+        if page > 1 && cursor.is_none() {
+            debug!("No further pages for '{}'.", query);
+            break;
+        }
+        if shutdown.is_triggered() {
+            break;
+        }
+        bar.set_message(format!("page {}", page));
+
+        let search_page = match load_cached_search_page(cache_dir, page, ctx.cache) {
+            Some(cached) => {
+                debug!("Loaded page {} for '{}' from cache.", page, query);
+                report.cache_hits += 1;
+                cached
+            }
+            None => {
+                if let Some(seat) = &seat {
+                    seat.take_turn().await;
+                }
+                report.api_calls += 1;
+                info!(
+                    "Fetching page {} for '{}' from {}",
+                    page,
+                    query,
+                    forge.name()
+                );
+                let fetched = tokio::select! {
+                    fetched = forge.search_page(query, cursor.as_deref()) => fetched,
+                    _ = shutdown.triggered() => break,
+                };
+                let fetched = fetched
+                    .inspect_err(|e| {
+                        error!(
+                            "Failed to fetch page {} for '{}': {}. Stopping processing for this language.",
+                            page, query, e
+                        )
+                    })
+                    .with_context(|| format!("API fetch failed for page {}", page))?;
+                if let Err(e) = save_cached_search_page(cache_dir, page, &fetched) {
+                    // Log error but continue, caching isn't critical for the final result
+                    error!("Failed to save page {} to cache: {}", page, e);
+                }
+                if page == 1 {
+                    total_count = fetched.total_count;
+                }
+                if fetched.incomplete_results {
+                    warn!(
+                        "{} flagged page {} for '{}' as incomplete; the ranking may miss repositories.",
+                        forge.name(),
+                        page,
+                        query
+                    );
+                    report.incomplete_results = true;
+                }
+                fetched
+            }
+        };
+
+        if search_page.repos.is_empty() {
+            break;
+        }
+        all_repos.extend(search_page.repos);
+        cursor = search_page.next_cursor;
+
+        // Check if we have reached the desired number of records
+        if all_repos.len() >= records as usize {
+            info!(
+                "Reached target of {} records for '{}'. Stopping fetch.",
+                records, query
+            );
+            // Trim excess records if we fetched a full page but only needed part of it
+            all_repos.truncate(records as usize);
+            break;
+        }
+    }
+
+    Ok((all_repos, total_count))
+}
+
+/// Ranks `repos` the forge's way, ordering ties by the tie-breakers when the search
+/// order can be restored at all.
+fn rank(ctx: &FetchContext, repos: &mut [Repo]) {
+    if ctx.sort.is_rankable() {
+        filters::sort_ties(repos, &ctx.tie_breaks);
+    }
+    ctx.forge.rank(repos);
+}
+
+/// Fetches up to `records` repositories for the specified language, using caching.
+///
+/// Forges that cap results per query (GitHub stops at 1000) get larger requests split
+/// into star buckets: once a query is exhausted, the next one is capped at the lowest
+/// star count seen so far (`stars:<=N`). Buckets overlap on that boundary, so results
+/// are deduplicated by URL and re-ranked by the forge before being returned.
+///
+/// Every new repository is also written to the `streams` sinks as soon as its page
+/// has been fetched, ranked in the order the forge returned it.
+async fn fetch_top_repos_for_language(
+    ctx: &FetchContext,
+    language_api_name: &str,
+    records: u32,
+    streams: &mut [Box<dyn OutputSink>],
+    report: &mut LanguageReport,
+) -> Result<Vec<Repo>> {
+    let forge = ctx.forge.as_ref();
+    info!(
+        "Fetching top repositories for language: {} from {}",
+        language_api_name,
+        forge.name()
+    );
+    let mut cache_dir = get_language_cache_dir(&ctx.output_dir, forge.name(), language_api_name);
+    if let Some(key) = filters::cache_key(&ctx.filters, ctx.sort) {
+        cache_dir.push(key);
+    }
+    let query_cap = forge.max_results_per_query().unwrap_or(u32::MAX);
+    let bar = ctx.progress.language(language_api_name, records);
+
+    let mut all_repos: Vec<Repo> = Vec::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
+    let mut star_ceiling: Option<u64> = None;
+
+    for bucket in 0u32.. {
+        // The first bucket keeps the plain layout so existing caches stay valid.
+        let bucket_dir = match bucket {
+            0 => cache_dir.clone(),
+            n => get_bucket_cache_dir(&cache_dir, n),
+        };
+        let query = forge.language_query(language_api_name, star_ceiling);
+        // Repos tied at the ceiling were already collected and will come back again.
+        let overlap = all_repos
+            .iter()
+            .filter(|r| Some(r.stargazers_count) == star_ceiling)
+            .count() as u32;
+        let wanted = (records - all_repos.len() as u32 + overlap).min(query_cap);
+        info!(
+            "Fetching bucket {} for {}: '{}'",
+            bucket, language_api_name, query
+        );
+
+        let (bucket_repos, total_count) =
+            fetch_search_pages(ctx, &query, wanted, &bucket_dir, &bar, report).await?;
+        if bucket == 0 {
+            report.total_count = total_count;
+        }
+        let exhausted = (bucket_repos.len() as u32) < wanted;
+        let lowest_stars = bucket_repos.iter().map(|r| r.stargazers_count).min();
+
+        let before = all_repos.len();
+        for mut repo in bucket_repos {
+            if let Some(license) = &mut repo.license {
+                license.normalize();
+            }
+            if !ctx.filters.matches(&repo) {
+                continue;
+            }
+            if let Some(sanitizer) = &ctx.sanitizer {
+                sanitizer.apply(&mut repo);
+            }
+            if seen_urls.insert(repo.html_url.clone()) {
+                if all_repos.len() < records as usize {
+                    for sink in streams.iter_mut() {
+                        sink.write_repo(all_repos.len() + 1, &repo)?;
+                    }
+                }
+                all_repos.push(repo);
+                bar.set_position(all_repos.len().min(records as usize) as u64);
+            }
+        }
+        let progressed = all_repos.len() > before;
+
+        let capped = forge.max_results_per_query().is_some();
+        if all_repos.len() >= records as usize
+            || exhausted
+            || !capped
+            || ctx.shutdown.is_triggered()
+        {
+            break;
+        }
+        let Some(lowest_stars) = lowest_stars else {
+            break;
+        };
+        // With more than 1000 repos tied at the boundary the same page keeps coming
+        // back; step below it and accept losing some of the tied repos.
+        star_ceiling = if progressed {
+            Some(lowest_stars)
+        } else if lowest_stars > 0 {
+            warn!(
+                "Too many {} repos with {} stars to list them all. Skipping the rest of the tie.",
+                language_api_name, lowest_stars
+            );
+            Some(lowest_stars - 1)
+        } else {
+            break;
+        };
+    }
+
+    bar.finish_and_clear();
+    rank(ctx, &mut all_repos);
+    all_repos.truncate(records as usize);
+    if all_repos.len() < records as usize && !ctx.shutdown.is_triggered() {
+        warn!(
+            "Only {} of the {} requested {} repositories exist{}; the ranking is shorter.",
+            all_repos.len(),
+            records,
+            language_api_name,
+            report
+                .total_count
+                .map(|total| format!(" ({} reports {} in total)", forge.name(), total))
+                .unwrap_or_default()
+        );
+    }
+
+    info!(
+        "Total repositories collected for {}: {}",
+        language_api_name,
+        all_repos.len()
+    );
+    Ok(all_repos)
+}
+
+/// Opens the sink writing `format` for one language.
+fn open_sink(
+    ctx: &FetchContext,
+    format: OutputFormat,
+    path: &Path,
+    language: &str,
+) -> Result<Box<dyn OutputSink>> {
+    Ok(match format {
+        OutputFormat::Csv => Box::new(CsvSink::create(path, ctx.delimiter, &ctx.columns)?),
+        OutputFormat::Json => Box::new(JsonSink::create(path)?),
+        OutputFormat::Jsonl => Box::new(JsonlSink::create(path)?),
+        OutputFormat::Parquet => Box::new(ParquetSink::new(path)),
+        OutputFormat::Markdown => Box::new(MarkdownSink::create(path)?),
+        OutputFormat::Sqlite => {
+            let database = ctx
+                .database
+                .as_ref()
+                .context("The SQLite database was not opened")?;
+            Box::new(SqliteSink::new(Arc::clone(database), language))
+        }
+    })
+}
+
+/// `stem` with `.extension` appended, keeping any dots already in its name.
+fn with_extension(stem: &Path, extension: &str) -> PathBuf {
+    let mut path = stem.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+/// Repositories of a language's existing delimited files, later files taking
+/// precedence. Missing files are skipped.
+fn existing_repos(paths: &[PathBuf], delimiter: u8) -> Result<Vec<Repo>> {
+    let mut repos = Vec::new();
+    for path in paths.iter().filter(|path| path.exists()) {
+        repos = merge::merge_repos(repos, merge::read_repos(path, delimiter)?);
+    }
+    Ok(repos)
+}
+
+/// Fetches the repositories for one language and writes its result files, returning
+/// whether every file was written. Failures are logged, added to `report` and leave
+/// the cache in place so a re-run can resume.
+async fn process_language(
+    ctx: &FetchContext,
+    mapping: &LanguageMapping,
+    report: &mut LanguageReport,
+) -> bool {
+    let output_dir = &ctx.output_dir;
+    info!(
+        "Processing language: {} ({})",
+        mapping.display_name, mapping.api_name
+    );
+
+    // Define cache dir path for potential cleanup
+    let cache_dir = get_language_cache_dir(output_dir, ctx.forge.name(), &mapping.api_name);
+    let dataset = ctx.dataset_name(&mapping.display_name);
+    let stem = Path::new(output_dir).join(ctx.filename_template.render(&FileNameValues {
+        date: &ctx.date,
+        provider: ctx.forge.name(),
+        language: &dataset,
+        api_name: &ctx.dataset_name(&mapping.api_name),
+    }));
+    let output_path = |format: OutputFormat| match format {
+        OutputFormat::Sqlite => Path::new(output_dir).join(database::DATABASE_FILE_NAME),
+        _ => with_extension(&stem, format.extension(ctx.delimiter)),
+    };
+    // Rows collected before an interruption.
+    let partial_path = with_extension(
+        &stem,
+        &format!(
+            "{}.{}",
+            delimited::PARTIAL_SUFFIX,
+            delimited::extension(ctx.delimiter)
+        ),
+    );
+    if let Some(parent) = stem.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        error!(
+            "Failed creating {:?} for {}: {}. Skipping this language.",
+            parent, mapping.display_name, e
+        );
+        report
+            .errors
+            .push(format!("Failed creating {:?}: {}", parent, e));
+        return false;
+    }
+    // The ranking and the rows an interrupted run saved are merged into the new one.
+    let existing = if ctx.merge {
+        let paths = [output_path(OutputFormat::Csv), partial_path.clone()];
+        match existing_repos(&paths, ctx.delimiter) {
+            Ok(existing) => existing,
+            Err(e) => {
+                error!(
+                    "Failed reading the existing results of {} to merge: {:#}. Skipping this language.",
+                    mapping.display_name, e
+                );
+                report
+                    .errors
+                    .push(format!("Failed reading the existing results: {:#}", e));
+                return false;
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Streamed formats are written while the repositories come in, unless enrichment
+    // or merging has to complete the records first. Star histories go to a sidecar
+    // instead.
+    let streamed = |format: &OutputFormat| {
+        format.is_streamed() && enrich::per_repo(&ctx.enrichments).next().is_none() && !ctx.merge
+    };
+    let mut streams = Vec::new();
+    for &format in ctx.formats.iter().filter(|f| streamed(f)) {
+        let opened = open_sink(ctx, format, &output_path(format), &dataset)
+            .and_then(|mut sink| sink.write_header().map(|_| sink));
+        match opened {
+            Ok(sink) => streams.push(sink),
+            Err(e) => {
+                error!(
+                    "Failed creating {:?} output for {}: {}. Skipping this language.",
+                    format, mapping.display_name, e
+                );
+                report
+                    .errors
+                    .push(format!("Failed creating {:?} output: {}", format, e));
+                return false;
+            }
+        }
+    }
+
+    let records = mapping.records.unwrap_or(ctx.records);
+    match fetch_top_repos_for_language(ctx, &mapping.api_name, records, &mut streams, report).await
+    {
+        Ok(repos) if ctx.shutdown.is_triggered() => {
+            for sink in &mut streams {
+                let _ = sink.finish();
+            }
+            match CsvSink::create(&partial_path, ctx.delimiter, &ctx.columns)
+                .and_then(|mut sink| sink::write_all(&mut sink, &repos))
+            {
+                Ok(_) => info!(
+                    "Saved {} records collected for {} before the interruption in {:?}",
+                    repos.len(),
+                    mapping.display_name,
+                    partial_path
+                ),
+                Err(e) => {
+                    error!(
+                        "Failed writing partial results of {}: {}",
+                        mapping.display_name, e
+                    );
+                    report
+                        .errors
+                        .push(format!("Failed writing partial results: {}", e));
+                }
+            }
+            report.set_ranking(&repos);
+            false
+        }
+        Ok(mut repos) => {
+            let mut written = true;
+            for sink in &mut streams {
+                if let Err(e) = sink.finish() {
+                    error!(
+                        "Failed finishing streamed output for {}: {}",
+                        mapping.display_name, e
+                    );
+                    report
+                        .errors
+                        .push(format!("Failed finishing streamed output: {}", e));
+                    written = false;
+                }
+            }
+
+            report.api_calls +=
+                enrich::enrich_repos(ctx.forge.as_ref(), &mut repos, &ctx.enrichments).await;
+            if !existing.is_empty() {
+                let fetched = repos.len();
+                repos = merge::merge_repos(existing, repos);
+                rank(ctx, &mut repos);
+                info!(
+                    "Merged {} fetched {} repositories with the existing results into {}.",
+                    fetched,
+                    mapping.display_name,
+                    repos.len()
+                );
+            }
+            report.set_ranking(&repos);
+
+            if ctx.enrichments.contains(&Enrichment::StarHistory) {
+                let (histories, requests) = enrich::star_histories(
+                    ctx.forge.as_ref(),
+                    &repos,
+                    ctx.star_history_top,
+                    &Path::new(output_dir).join(cache::CACHE_DIR_NAME),
+                    ctx.cache,
+                )
+                .await;
+                report.api_calls += requests;
+                let sidecar = with_extension(&stem, enrich::STAR_HISTORY_SUFFIX);
+                let written = serde_json::to_string(&histories)
+                    .map_err(KstarsError::from)
+                    .and_then(|json| atomic::write(&sidecar, json));
+                if let Err(e) = written {
+                    warn!("Failed writing star history {:?}: {}", sidecar, e);
+                }
+            }
+
+            // Write the final combined files
+            for &format in ctx.formats.iter().filter(|f| !streamed(f)) {
+                let file_path = output_path(format);
+                let result = open_sink(ctx, format, &file_path, &dataset)
+                    .and_then(|mut sink| sink::write_all(sink.as_mut(), &repos));
+                match result {
+                    Ok(_) => info!(
+                        "Saved {} records for {} in {:?}",
+                        repos.len(),
+                        mapping.display_name,
+                        file_path
+                    ),
+                    Err(e) => {
+                        error!(
+                            "Failed writing {:?} for {}: {}. Cache files in {:?} were NOT deleted.",
+                            file_path, mapping.display_name, e, cache_dir
+                        );
+                        report
+                            .errors
+                            .push(format!("Failed writing {:?}: {}", file_path, e));
+                        written = false;
+                    }
+                }
+            }
+
+            #[cfg(feature = "postgres")]
+            if let Some(postgres) = &ctx.postgres
+                && let Err(e) = postgres.upsert_language(&dataset, &repos).await
+            {
+                error!(
+                    "Failed upserting {} into PostgreSQL: {}. Cache files in {:?} were NOT deleted.",
+                    mapping.display_name, e, cache_dir
+                );
+                report
+                    .errors
+                    .push(format!("Failed upserting into PostgreSQL: {}", e));
+                written = false;
+            }
+
+            #[cfg(feature = "sheets")]
+            if let Some(sheets) = &ctx.sheets
+                && let Err(e) = sheets.write_language(&dataset, &ctx.columns, &repos).await
+            {
+                error!(
+                    "Failed writing {} to Google Sheets: {}. Cache files in {:?} were NOT deleted.",
+                    mapping.display_name, e, cache_dir
+                );
+                report
+                    .errors
+                    .push(format!("Failed writing to Google Sheets: {}", e));
+                written = false;
+            }
+
+            #[cfg(feature = "bigquery")]
+            if let Some(bigquery) = &ctx.bigquery
+                && let Err(e) = bigquery.insert_language(&dataset, &repos).await
+            {
+                error!(
+                    "Failed streaming {} into BigQuery: {}. Cache files in {:?} were NOT deleted.",
+                    mapping.display_name, e, cache_dir
+                );
+                report
+                    .errors
+                    .push(format!("Failed streaming into BigQuery: {}", e));
+                written = false;
+            }
+
+            if let Some(history) = &ctx.history
+                && let Err(e) = history.append(&dataset, &repos)
+            {
+                warn!(
+                    "Failed appending {} to the history: {}",
+                    mapping.display_name, e
+                );
+            }
+
+            if written && partial_path.exists() {
+                let _ = fs::remove_file(&partial_path);
+            }
+            // Clean up cache directory for this language *only* on success
+            if written && cache_dir.exists() {
+                info!("Cleaning up cache directory: {:?}", cache_dir);
+                if let Err(e) = fs::remove_dir_all(&cache_dir) {
+                    warn!("Failed to remove cache directory {:?}: {}", cache_dir, e);
+                }
+            }
+            written
+        }
+        Err(e) => {
+            error!(
+                "Failed fetching repos for {}: {}. Skipping this language. Cache files in {:?} may remain.",
+                mapping.api_name, e, cache_dir
+            );
+            report
+                .errors
+                .push(format!("Failed fetching repositories: {:#}", e));
+            false
+        }
+    }
+}
+
+/// Settings of a fetch run, built up from [`FetchOptions::new`]:
+///
+/// ```no_run
+/// # use kstars_core::{FetchOptions, OutputFormat};
+/// let options = FetchOptions::new("./results")
+///     .records(500)
+///     .formats(vec![OutputFormat::Csv, OutputFormat::Json])
+///     .concurrency(4);
+/// ```
+pub struct FetchOptions {
+    output_dir: String,
+    filename_template: FilenameTemplate,
+    records: u32,
+    concurrency: u32,
+    interleave: bool,
+    formats: Vec<OutputFormat>,
+    delimiter: u8,
+    columns: Option<Vec<Column>>,
+    enrichments: Vec<Enrichment>,
+    star_history_top: usize,
+    trending: bool,
+    filters: SearchFilters,
+    sanitizer: Option<Sanitizer>,
+    sort: SearchSort,
+    tie_breaks: Vec<TieBreak>,
+    cache: CachePolicy,
+    resume: bool,
+    fail_fast: bool,
+    merge: bool,
+    history_dir: Option<PathBuf>,
+    shutdown: Shutdown,
+    progress: Progress,
+    metrics: Option<Arc<Metrics>>,
+    #[cfg(feature = "postgres")]
+    postgres_url: Option<String>,
+    #[cfg(feature = "sheets")]
+    google_sheets: Option<(reqwest::Client, PathBuf, String)>,
+    #[cfg(feature = "bigquery")]
+    bigquery: Option<(reqwest::Client, PathBuf, BigQueryTable)>,
+}
+
+impl FetchOptions {
+    /// Options writing 1000 repositories per language as CSV into `output_dir`, one
+    /// language at a time.
+    pub fn new(output_dir: impl Into<String>) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+            filename_template: FilenameTemplate::default(),
+            records: 1000,
+            concurrency: 1,
+            interleave: false,
+            formats: vec![OutputFormat::Csv],
+            delimiter: b',',
+            columns: None,
+            enrichments: Vec::new(),
+            star_history_top: 10,
+            trending: false,
+            filters: SearchFilters::default(),
+            sanitizer: None,
+            sort: SearchSort::default(),
+            tie_breaks: filters::DEFAULT_TIE_BREAKS.to_vec(),
+            cache: CachePolicy::default(),
+            resume: false,
+            fail_fast: false,
+            merge: false,
+            history_dir: None,
+            shutdown: Shutdown::new(),
+            progress: Progress::hidden(),
+            metrics: None,
+            #[cfg(feature = "postgres")]
+            postgres_url: None,
+            #[cfg(feature = "sheets")]
+            google_sheets: None,
+            #[cfg(feature = "bigquery")]
+            bigquery: None,
+        }
+    }
+
+    /// Layout of the result files below the output folder.
+    pub fn filename_template(mut self, template: FilenameTemplate) -> Self {
+        self.filename_template = template;
+        self
+    }
+
+    /// Repositories ranked per language.
+    pub fn records(mut self, records: u32) -> Self {
+        self.records = records;
+        self
+    }
+
+    /// Languages fetched in parallel; they share the forge's request budget.
+    pub fn concurrency(mut self, concurrency: u32) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Fetches every language at once, taking turns page by page, instead of
+    /// `concurrency` languages at a time. Requests are spread evenly across the
+    /// languages, and languages served from the cache don't wait for the others.
+    pub fn interleave(mut self, interleave: bool) -> Self {
+        self.interleave = interleave;
+        self
+    }
+
+    pub fn formats(mut self, formats: Vec<OutputFormat>) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// Field separator of delimited files.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Columns of delimited files. Defaults to every column with data.
+    pub fn columns(mut self, columns: Vec<Column>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    pub fn enrichments(mut self, enrichments: Vec<Enrichment>) -> Self {
+        self.enrichments = enrichments;
+        self
+    }
+
+    /// Repositories per language whose star history is sampled.
+    pub fn star_history_top(mut self, top: usize) -> Self {
+        self.star_history_top = top;
+        self
+    }
+
+    /// Writes the rankings as "trending_" datasets, for searches restricted to
+    /// recently created repositories.
+    pub fn trending(mut self, trending: bool) -> Self {
+        self.trending = trending;
+        self
+    }
+
+    /// Filters every repository has to pass. Forge clients apply them to their
+    /// searches separately.
+    pub fn filters(mut self, filters: SearchFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Cleans up the descriptions of the repositories before they are written.
+    pub fn sanitize_descriptions(mut self, sanitizer: Sanitizer) -> Self {
+        self.sanitizer = Some(sanitizer);
+        self
+    }
+
+    pub fn sort(mut self, sort: SearchSort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Orders repositories that rank equal, before ranking numbers are assigned.
+    pub fn tie_breaks(mut self, tie_breaks: Vec<TieBreak>) -> Self {
+        self.tie_breaks = tie_breaks;
+        self
+    }
+
+    pub fn cache(mut self, cache: CachePolicy) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Skips the languages completed by the previous, interrupted run.
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Stops the run when a language fails.
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Merges the rankings into the files of the previous run by repository URL,
+    /// keeping repositories this run did not fetch, instead of replacing them.
+    /// The previous rankings are read from CSV files, so the formats must include CSV.
+    pub fn merge(mut self, merge: bool) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    /// Appends the rankings to the history store in `dir`.
+    pub fn history_dir(mut self, dir: PathBuf) -> Self {
+        self.history_dir = Some(dir);
+        self
+    }
+
+    /// Handle that interrupts the run when triggered.
+    pub fn shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub fn progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Counters the languages are recorded in, shared with scheduled runs.
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Also upserts the rankings into the PostgreSQL database at `url`.
+    #[cfg(feature = "postgres")]
+    pub fn postgres_url(mut self, url: String) -> Self {
+        self.postgres_url = Some(url);
+        self
+    }
+
+    /// Also writes the rankings to the Google Sheets spreadsheet `spreadsheet_id`,
+    /// one sheet per language, as the service account whose key is at `credentials`.
+    #[cfg(feature = "sheets")]
+    pub fn google_sheets(
+        mut self,
+        client: reqwest::Client,
+        credentials: PathBuf,
+        spreadsheet_id: String,
+    ) -> Self {
+        self.google_sheets = Some((client, credentials, spreadsheet_id));
+        self
+    }
+
+    /// Also streams the rankings into the BigQuery `table`, partitioned by snapshot
+    /// date, as the service account whose key is at `credentials`.
+    #[cfg(feature = "bigquery")]
+    pub fn bigquery(
+        mut self,
+        client: reqwest::Client,
+        credentials: PathBuf,
+        table: BigQueryTable,
+    ) -> Self {
+        self.bigquery = Some((client, credentials, table));
+        self
+    }
+}
+
+/// Fetches the rankings of a list of languages from one forge and writes their
+/// datasets.
+pub struct Fetcher {
+    ctx: Arc<FetchContext>,
+    concurrency: u32,
+}
+
+impl Fetcher {
+    /// Prepares a run: creates the output folder and opens the stores the options
+    /// ask for.
+    pub async fn new(forge: Box<dyn ForgeClient>, options: FetchOptions) -> Result<Self> {
+        let columns = options
+            .columns
+            .unwrap_or_else(|| columns::default_columns(&options.enrichments));
+        columns::check_selection(&columns, &options.enrichments)?;
+        // Previous rankings are only read back from their CSV files.
+        if options.merge && !options.formats.contains(&OutputFormat::Csv) {
+            bail!("`--merge` reads the previous rankings from CSV files; add `--format csv`.");
+        }
+
+        let output = Path::new(&options.output_dir);
+        fs::create_dir_all(output).context("Failed to create output directory")?;
+        info!("Output directory ensured at: {}", options.output_dir);
+
+        let database = if options.formats.contains(&OutputFormat::Sqlite) {
+            let path = output.join(database::DATABASE_FILE_NAME);
+            Some(Arc::new(Database::open(
+                &path,
+                forge.name(),
+                options.records,
+            )?))
+        } else {
+            None
+        };
+        let today = Local::now().date_naive();
+        let history = match &options.history_dir {
+            Some(dir) => Some(History::open(dir, forge.name(), today)?),
+            None => None,
+        };
+        let run_state = RunState::start(output, forge.name(), options.records, options.resume)?;
+        let metrics = options
+            .metrics
+            .unwrap_or_else(|| Arc::new(Metrics::new(forge.name())));
+        let ctx = FetchContext {
+            forge,
+            records: options.records,
+            output_dir: options.output_dir,
+            filename_template: options.filename_template,
+            date: today.to_string(),
+            formats: options.formats,
+            delimiter: options.delimiter,
+            columns,
+            dataset_prefix: if options.trending {
+                filters::TRENDING_PREFIX
+            } else {
+                ""
+            },
+            enrichments: options.enrichments,
+            star_history_top: options.star_history_top,
+            filters: options.filters,
+            sanitizer: options.sanitizer,
+            sort: options.sort,
+            tie_breaks: options.tie_breaks,
+            cache: options.cache,
+            run_state,
+            shutdown: options.shutdown,
+            fail_fast: options.fail_fast,
+            merge: options.merge,
+            page_turns: options.interleave.then(PageTurns::default),
+            progress: options.progress,
+            metrics,
+            database,
+            history,
+            #[cfg(feature = "postgres")]
+            postgres: match &options.postgres_url {
+                Some(url) => Some(postgres_sink::PostgresSink::connect(url).await?),
+                None => None,
+            },
+            #[cfg(feature = "sheets")]
+            sheets: match options.google_sheets {
+                Some((client, credentials, spreadsheet_id)) => Some(
+                    sheets_sink::SheetsSink::connect(client, &credentials, spreadsheet_id).await?,
+                ),
+                None => None,
+            },
+            #[cfg(feature = "bigquery")]
+            bigquery: match options.bigquery {
+                Some((client, credentials, table)) => {
+                    Some(BigQuerySink::connect(client, &credentials, table).await?)
+                }
+                None => None,
+            },
+        };
+        Ok(Self {
+            ctx: Arc::new(ctx),
+            concurrency: options.concurrency,
+        })
+    }
+
+    /// Fetches every language of `languages` and writes its datasets and the run
+    /// report. Languages that fail are reported rather than returned as errors.
+    pub async fn run(&self, languages: &[LanguageMapping]) -> Result<RunReport> {
+        let ctx = &self.ctx;
+        let started_at = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let start = Instant::now();
+        let concurrency = match ctx.page_turns {
+            Some(_) => languages.len().max(1),
+            None => self.concurrency as usize,
+        };
+        let semaphore = Arc::new(Semaphore::new(concurrency));
+        info!(
+            "Processing {} languages with concurrency {}{}.",
+            languages.len(),
+            concurrency,
+            if ctx.page_turns.is_some() {
+                ", interleaving their pages"
+            } else {
+                ""
+            }
+        );
+        // For each language, fetch repositories and write CSV.
+        let mut tasks = JoinSet::new();
+        let languages_bar = ctx.progress.languages(languages.len());
+        let mut reports = Vec::new();
+        for mapping in languages.iter().cloned() {
+            if ctx
+                .run_state
+                .is_completed(&ctx.dataset_name(&mapping.api_name))
+            {
+                languages_bar.inc(1);
+                let mut report = LanguageReport::new(&mapping.api_name, &mapping.display_name);
+                report.status = LanguageStatus::Skipped;
+                ctx.metrics.record(&report);
+                reports.push(report);
+                info!(
+                    "Skipping {}, completed by the previous run.",
+                    mapping.display_name
+                );
+                continue;
+            }
+            let ctx = Arc::clone(ctx);
+            let semaphore = Arc::clone(&semaphore);
+            let languages_bar = languages_bar.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore
+                    .acquire_owned()
+                    .await
+                    .expect("semaphore is never closed");
+                let mut report = LanguageReport::new(&mapping.api_name, &mapping.display_name);
+                if ctx.shutdown.is_triggered() {
+                    report.status = LanguageStatus::Interrupted;
+                    return report;
+                }
+                let start = Instant::now();
+                let written = process_language(&ctx, &mapping, &mut report).await;
+                report.duration_secs = start.elapsed().as_secs_f64();
+                report.status = if written {
+                    LanguageStatus::Completed
+                } else if ctx.shutdown.is_triggered() {
+                    LanguageStatus::Interrupted
+                } else {
+                    LanguageStatus::Failed
+                };
+                if report.status == LanguageStatus::Failed && ctx.fail_fast {
+                    error!(
+                        "{} failed; stopping the run (--fail-fast).",
+                        mapping.display_name
+                    );
+                    ctx.shutdown.trigger();
+                }
+                if written
+                    && let Err(e) = ctx
+                        .run_state
+                        .mark_completed(&ctx.dataset_name(&mapping.api_name))
+                {
+                    warn!(
+                        "Failed to record {} as completed: {}",
+                        mapping.display_name, e
+                    );
+                }
+                languages_bar.inc(1);
+                ctx.metrics.record(&report);
+                report
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(report) => reports.push(report),
+                Err(e) => error!("A language task panicked: {}", e),
+            }
+        }
+        languages_bar.finish();
+        if let Some(database) = &ctx.database {
+            database.finish()?;
+        }
+        let any_failed = reports
+            .iter()
+            .any(|report| report.status == LanguageStatus::Failed);
+        let report = RunReport::new(started_at, start.elapsed(), reports);
+        report.write(Path::new(&ctx.output_dir))?;
+        if languages.iter().all(|mapping| {
+            ctx.run_state
+                .is_completed(&ctx.dataset_name(&mapping.api_name))
+        }) {
+            ctx.run_state.finish()?;
+        } else if !any_failed && !ctx.shutdown.is_triggered() {
+            warn!("Some languages were not completed; run again with --resume to retry them.");
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FetchContext, FetchOptions, Fetcher, PageTurns, fetch_top_repos_for_language};
+    use crate::error::Result;
+    use crate::{
+        LanguageMapping, OutputFormat, Repo,
+        cache::CachePolicy,
+        columns,
+        filters::{self, SearchFilters, SearchSort},
+        forge::{ForgeClient, SearchPage},
+        github::{ApiBackend, GithubClient},
+        layout::FilenameTemplate,
+        metrics::Metrics,
+        progress::Progress,
+        rate_limit::RateLimiter,
+        report::LanguageReport,
+        run_state::RunState,
+        shutdown::Shutdown,
+        sink::{JsonlSink, OutputSink},
+        token_pool::TokenPool,
+        transport::{HttpTransport, ReplayTransport},
+    };
+    use async_trait::async_trait;
+    use reqwest::Method;
+    use serde_json::json;
+    use std::{
+        fs,
+        path::Path,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicU32, Ordering},
+        },
+        time::Duration,
+    };
+    use tempfile::tempdir;
+
+    fn repo(name: &str, stars: u64) -> Repo {
+        Repo {
+            name: name.to_string(),
+            html_url: format!("https://github.com/example/{}", name),
+            stargazers_count: stars,
+            watchers_count: stars,
+            language: Some("Rust".to_string()),
+            created_at: "2020-01-01T00:00:00Z".to_string(),
+            pushed_at: "2024-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Forge double serving two repos per page and at most four results per query,
+    /// with the language and star ceiling encoded as "language:ceiling".
+    struct FakeForge {
+        repos: Vec<Repo>,
+        limiter: RateLimiter,
+        tokens: TokenPool,
+        transport: ReplayTransport,
+        requests: Arc<AtomicU32>,
+        /// Language of every page requested, with the requests already in flight.
+        pages: Arc<Mutex<Vec<(String, u32)>>>,
+        in_flight: AtomicU32,
+    }
+
+    #[async_trait]
+    impl ForgeClient for FakeForge {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn limiter(&self) -> &RateLimiter {
+            &self.limiter
+        }
+
+        fn tokens(&self) -> &TokenPool {
+            &self.tokens
+        }
+
+        fn transport(&self) -> &dyn HttpTransport {
+            &self.transport
+        }
+
+        fn language_query(&self, language: &str, star_ceiling: Option<u64>) -> String {
+            format!("{}:{}", language, star_ceiling.unwrap_or(u64::MAX))
+        }
+
+        fn max_results_per_query(&self) -> Option<u32> {
+            Some(4)
+        }
+
+        async fn search_page(&self, query: &str, cursor: Option<&str>) -> Result<SearchPage> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let (language, ceiling) = query.split_once(':').expect("queries name a language");
+            let ceiling: u64 = ceiling.parse().expect("queries end with a star ceiling");
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst);
+            self.pages
+                .lock()
+                .unwrap()
+                .push((language.to_string(), in_flight));
+            // Like a real request, give the other languages a chance to run.
+            tokio::task::yield_now().await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            let offset: usize = cursor.map_or(0, |cursor| cursor.parse().expect("numeric cursor"));
+            let matching: Vec<Repo> = self
+                .repos
+                .iter()
+                .filter(|r| r.stargazers_count <= ceiling)
+                .take(4)
+                .cloned()
+                .collect();
+            let repos: Vec<Repo> = matching.iter().skip(offset).take(2).cloned().collect();
+            let next = offset + 2;
+            Ok(SearchPage {
+                repos,
+                next_cursor: (next < matching.len()).then(|| next.to_string()),
+                ..SearchPage::default()
+            })
+        }
+    }
+
+    fn fake_context(
+        output_dir: &Path,
+        repos: Vec<Repo>,
+        requests: &Arc<AtomicU32>,
+        filters: SearchFilters,
+    ) -> FetchContext {
+        FetchContext {
+            forge: Box::new(FakeForge {
+                repos,
+                limiter: RateLimiter::new(Duration::ZERO),
+                tokens: TokenPool::new(vec![]),
+                transport: ReplayTransport::new(),
+                requests: Arc::clone(requests),
+                pages: Arc::default(),
+                in_flight: AtomicU32::new(0),
+            }),
+            records: 6,
+            output_dir: output_dir.to_string_lossy().into_owned(),
+            filename_template: FilenameTemplate::default(),
+            date: "2025-06-01".to_string(),
+            formats: vec![OutputFormat::Csv],
+            delimiter: b',',
+            columns: columns::default_columns(&[]),
+            dataset_prefix: "",
+            enrichments: vec![],
+            star_history_top: 10,
+            filters,
+            sanitizer: None,
+            sort: SearchSort::default(),
+            tie_breaks: filters::DEFAULT_TIE_BREAKS.to_vec(),
+            cache: CachePolicy::default(),
+            run_state: RunState::start(output_dir, "fake", 6, false).unwrap(),
+            shutdown: Shutdown::new(),
+            fail_fast: false,
+            merge: false,
+            page_turns: None,
+            progress: Progress::hidden(),
+            metrics: Arc::new(Metrics::new("fake")),
+            database: None,
+            history: None,
+            #[cfg(feature = "postgres")]
+            postgres: None,
+            #[cfg(feature = "sheets")]
+            sheets: None,
+            #[cfg(feature = "bigquery")]
+            bigquery: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_top_repos_splits_buckets_and_uses_cache() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let requests = Arc::new(AtomicU32::new(0));
+        let repos = vec![
+            repo("a", 70),
+            repo("b", 60),
+            repo("c", 50),
+            repo("d", 40),
+            repo("e", 40),
+            repo("f", 20),
+            repo("g", 10),
+        ];
+        let mut ctx = fake_context(temp_dir.path(), repos, &requests, SearchFilters::default());
+
+        let stream_path = temp_dir.path().join("Rust.jsonl");
+        let mut streams: Vec<Box<dyn OutputSink>> =
+            vec![Box::new(JsonlSink::create(&stream_path)?)];
+        let mut report = LanguageReport::new("Rust", "Rust");
+        let fetched =
+            fetch_top_repos_for_language(&ctx, "Rust", ctx.records, &mut streams, &mut report)
+                .await?;
+        streams[0].finish()?;
+        let names: Vec<&str> = fetched.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c", "d", "e", "f"]);
+
+        // Repos repeated across buckets are streamed only once.
+        let streamed = fs::read_to_string(&stream_path)?;
+        assert_eq!(streamed.lines().count(), 6);
+        assert!(streamed.lines().last().unwrap().contains(r#""name":"f""#));
+        let first_run_requests = requests.load(Ordering::SeqCst);
+        assert!(first_run_requests > 2);
+        assert_eq!(report.api_calls, first_run_requests);
+
+        // A second run is served entirely from the page cache.
+        let mut report = LanguageReport::new("Rust", "Rust");
+        let cached =
+            fetch_top_repos_for_language(&ctx, "Rust", ctx.records, &mut [], &mut report).await?;
+        assert_eq!(cached.len(), 6);
+        assert_eq!(requests.load(Ordering::SeqCst), first_run_requests);
+        assert_eq!(
+            (report.api_calls, report.cache_hits),
+            (0, first_run_requests)
+        );
+
+        // With --refresh every page is fetched again.
+        ctx.cache.refresh = true;
+        fetch_top_repos_for_language(&ctx, "Rust", ctx.records, &mut [], &mut report).await?;
+        assert_eq!(requests.load(Ordering::SeqCst), 2 * first_run_requests);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_fetch_requests_no_more_pages() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let requests = Arc::new(AtomicU32::new(0));
+        let repos = vec![repo("a", 70), repo("b", 60)];
+        let ctx = fake_context(temp_dir.path(), repos, &requests, SearchFilters::default());
+        ctx.shutdown.trigger();
+
+        let mut report = LanguageReport::new("Rust", "Rust");
+        let fetched =
+            fetch_top_repos_for_language(&ctx, "Rust", ctx.records, &mut [], &mut report).await?;
+        assert!(fetched.is_empty());
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_languages_take_turns_per_page() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let requests = Arc::new(AtomicU32::new(0));
+        let pages = Arc::new(Mutex::new(Vec::new()));
+        let repos = vec![repo("a", 70), repo("b", 60), repo("c", 50), repo("d", 40)];
+        let mut ctx = fake_context(
+            temp_dir.path(),
+            repos.clone(),
+            &requests,
+            SearchFilters::default(),
+        );
+        ctx.forge = Box::new(FakeForge {
+            repos,
+            limiter: RateLimiter::new(Duration::ZERO),
+            tokens: TokenPool::new(vec![]),
+            transport: ReplayTransport::new(),
+            requests: Arc::clone(&requests),
+            pages: Arc::clone(&pages),
+            in_flight: AtomicU32::new(0),
+        });
+        ctx.page_turns = Some(PageTurns::default());
+
+        let (mut go_report, mut rust_report) = (
+            LanguageReport::new("Go", "Go"),
+            LanguageReport::new("Rust", "Rust"),
+        );
+        let (go, rust) = tokio::join!(
+            fetch_top_repos_for_language(&ctx, "Go", 4, &mut [], &mut go_report),
+            fetch_top_repos_for_language(&ctx, "Rust", 4, &mut [], &mut rust_report),
+        );
+        assert_eq!(go?.len(), 4);
+        assert_eq!(rust?.len(), 4);
+
+        let pages = pages.lock().unwrap();
+        let languages: Vec<&str> = pages.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(languages, ["Go", "Rust", "Go", "Rust"]);
+        // Turns only order the requests; they still overlap.
+        assert!(pages.iter().any(|&(_, in_flight)| in_flight > 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_needs_csv_results() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let forge = || {
+            Box::new(FakeForge {
+                repos: vec![],
+                limiter: RateLimiter::new(Duration::ZERO),
+                tokens: TokenPool::new(vec![]),
+                transport: ReplayTransport::new(),
+                requests: Arc::default(),
+                pages: Arc::default(),
+                in_flight: AtomicU32::new(0),
+            })
+        };
+        let options = |formats| {
+            FetchOptions::new(temp_dir.path().to_string_lossy())
+                .formats(formats)
+                .merge(true)
+        };
+
+        let parquet_only = Fetcher::new(forge(), options(vec![OutputFormat::Parquet])).await;
+        let error = parquet_only.err().expect("merging needs CSV files");
+        assert!(error.to_string().contains("--format csv"), "{}", error);
+        let with_csv = options(vec![OutputFormat::Csv, OutputFormat::Parquet]);
+        assert!(Fetcher::new(forge(), with_csv).await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_top_repos_drops_filtered_repos() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let requests = Arc::new(AtomicU32::new(0));
+        let repos = ["a", "b", "c", "d", "e", "f", "g"]
+            .into_iter()
+            .zip([70, 60, 50, 40, 40, 20, 10])
+            .map(|(name, stars)| repo(name, stars))
+            .collect();
+        let filters = SearchFilters {
+            min_stars: Some(40),
+            ..SearchFilters::default()
+        };
+        let ctx = fake_context(temp_dir.path(), repos, &requests, filters);
+
+        let mut report = LanguageReport::new("Rust", "Rust");
+        let fetched =
+            fetch_top_repos_for_language(&ctx, "Rust", ctx.records, &mut [], &mut report).await?;
+
+        let names: Vec<&str> = fetched.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c", "d", "e"]);
+        // Pages are cached apart from unfiltered searches.
+        let key = filters::cache_key(&ctx.filters, ctx.sort).unwrap();
+        assert!(temp_dir.path().join(".cache/fake/Rust").join(key).is_dir());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_top_repos_drops_archived_and_mirrored_repos() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let requests = Arc::new(AtomicU32::new(0));
+        let mut repos: Vec<Repo> = ["a", "b", "c", "d"]
+            .into_iter()
+            .zip([70, 60, 50, 40])
+            .map(|(name, stars)| repo(name, stars))
+            .collect();
+        repos[1].archived = true;
+        repos[2].mirror_url = Some("https://git.example.com/c".to_string());
+        let filters = SearchFilters {
+            exclude_archived: true,
+            exclude_mirrors: true,
+            ..SearchFilters::default()
+        };
+        let ctx = fake_context(temp_dir.path(), repos, &requests, filters);
+
+        let mut report = LanguageReport::new("Rust", "Rust");
+        let fetched =
+            fetch_top_repos_for_language(&ctx, "Rust", ctx.records, &mut [], &mut report).await?;
+
+        let names: Vec<&str> = fetched.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["a", "d"]);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fetcher_pages_and_retries_against_replayed_github() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let page = |first: u64, count: u64| {
+            let items: Vec<_> = (first..first + count)
+                .map(|i| {
+                    json!({
+                        "name": format!("repo{}", i),
+                        "html_url": format!("https://github.com/example/repo{}", i),
+                        "stargazers_count": 1000 - i,
+                        "forks_count": 0,
+                        "watchers_count": 0,
+                        "language": "Rust",
+                        "description": null,
+                        "open_issues_count": 0,
+                        "created_at": "2020-01-01T00:00:00Z",
+                        "pushed_at": "2024-01-01T00:00:00Z",
+                        "size": 0,
+                    })
+                })
+                .collect();
+            // The last page comes from a search that timed out.
+            json!({ "total_count": 150, "incomplete_results": first > 0, "items": items })
+                .to_string()
+        };
+        let search = "https://api.example.com/search/repositories";
+        let transport = Arc::new(
+            ReplayTransport::new()
+                .with(Method::GET, &format!("{}?page=1", search), 502, &[], "")
+                .with(
+                    Method::GET,
+                    &format!("{}?page=1", search),
+                    200,
+                    &[(
+                        "link",
+                        &format!(
+                            "<{}?q=x&page=2>; rel=\"next\", <{}?q=x&page=2>; rel=\"last\"",
+                            search, search
+                        ),
+                    )],
+                    &page(0, 100),
+                )
+                .with(
+                    Method::GET,
+                    &format!("{}?page=2", search),
+                    403,
+                    &[("retry-after", "30")],
+                    "secondary rate limit",
+                )
+                .with(
+                    Method::GET,
+                    &format!("{}?page=2", search),
+                    200,
+                    &[],
+                    &page(100, 50),
+                ),
+        );
+        let github = GithubClient::new(
+            reqwest::Client::new(),
+            TokenPool::new(vec![]),
+            RateLimiter::new(Duration::ZERO),
+            ApiBackend::Rest,
+            "https://api.example.com",
+        )
+        .with_transport(transport.clone());
+        let options = FetchOptions::new(temp_dir.path().to_string_lossy()).records(150);
+        let fetcher = Fetcher::new(Box::new(github), options).await?;
+
+        let rust = LanguageMapping {
+            api_name: "Rust".to_string(),
+            display_name: "Rust".to_string(),
+            records: None,
+        };
+        let report = fetcher.run(&[rust]).await?;
+
+        assert_eq!(report.totals.records, 150);
+        assert_eq!(report.languages[0].total_count, Some(150));
+        assert!(report.languages[0].incomplete_results);
+        let csv = fs::read_to_string(temp_dir.path().join("Rust.csv"))?;
+        assert_eq!(csv.lines().count(), 151);
+        assert!(csv.lines().last().unwrap().contains("repo149"));
+        // The 502 and the rate limit were each retried once.
+        assert_eq!(transport.requests().len(), 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,420 @@
+use crate::{OwnerType, Repo};
+use chrono::{Days, NaiveDate};
+use clap::ValueEnum;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+/// Prefix of the files written by `--trending` runs, e.g. "trending_Rust.csv", which
+/// sit next to the all-time rankings.
+pub const TRENDING_PREFIX: &str = "trending_";
+
+/// Keys GitHub can sort search results by.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SortKey {
+    #[default]
+    Stars,
+    Forks,
+    /// Last update of the repository.
+    Updated,
+    HelpWantedIssues,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Order of the search results, which becomes the order of the ranking.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SearchSort {
+    pub key: SortKey,
+    pub order: SortOrder,
+}
+
+impl SearchSort {
+    /// Value of the REST API's `sort` parameter.
+    pub fn key_name(self) -> &'static str {
+        match self.key {
+            SortKey::Stars => "stars",
+            SortKey::Forks => "forks",
+            SortKey::Updated => "updated",
+            SortKey::HelpWantedIssues => "help-wanted-issues",
+        }
+    }
+
+    /// Value of the REST API's `order` parameter.
+    pub fn order_name(self) -> &'static str {
+        match self.order {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+
+    /// Whether results come most starred first, the only order star buckets can split.
+    pub fn is_by_stars_desc(self) -> bool {
+        self == SearchSort::default()
+    }
+
+    /// Whether [`SearchSort::rank`] can restore this order, so that ties may be
+    /// broken before ranking.
+    pub fn is_rankable(self) -> bool {
+        matches!(self.key, SortKey::Stars | SortKey::Forks)
+    }
+
+    /// Restores the search order after results of several queries were merged.
+    /// Only keys present in `Repo` can be sorted on; other orders are left as found.
+    pub fn rank(self, repos: &mut [Repo]) {
+        let key: fn(&Repo) -> u64 = match self.key {
+            SortKey::Stars => |r| r.stargazers_count,
+            SortKey::Forks => |r| r.forks_count,
+            SortKey::Updated | SortKey::HelpWantedIssues => return,
+        };
+        match self.order {
+            SortOrder::Asc => repos.sort_by_key(key),
+            SortOrder::Desc => repos.sort_by_key(|r| Reverse(key(r))),
+        }
+    }
+}
+
+/// Secondary keys ordering repositories that rank equal, so that ties don't land in
+/// whatever order the API returned them.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Most forks first.
+    Forks,
+    /// Most watchers first.
+    Watchers,
+    /// Full name ("owner/name"), alphabetically.
+    Name,
+    /// Oldest first.
+    Created,
+}
+
+/// Default `--tie-break`: forks, then full name.
+pub const DEFAULT_TIE_BREAKS: [TieBreak; 2] = [TieBreak::Forks, TieBreak::Name];
+
+impl TieBreak {
+    fn compare(self, a: &Repo, b: &Repo) -> Ordering {
+        match self {
+            TieBreak::Forks => b.forks_count.cmp(&a.forks_count),
+            TieBreak::Watchers => b.watchers_count.cmp(&a.watchers_count),
+            TieBreak::Name => full_name(a).cmp(&full_name(b)),
+            TieBreak::Created => a.created_at.cmp(&b.created_at),
+        }
+    }
+}
+
+/// "owner/name" of a repository, lowercased for sorting.
+fn full_name(repo: &Repo) -> String {
+    format!("{}/{}", repo.owner_login().unwrap_or_default(), repo.name).to_lowercase()
+}
+
+/// Orders `repos` by `tie_breaks`, then by URL. A stable sort by the ranking key
+/// afterwards keeps this order among repositories that rank equal.
+pub fn sort_ties(repos: &mut [Repo], tie_breaks: &[TieBreak]) {
+    repos.sort_by(|a, b| {
+        tie_breaks
+            .iter()
+            .fold(Ordering::Equal, |order, tie_break| {
+                order.then_with(|| tie_break.compare(a, b))
+            })
+            .then_with(|| a.html_url.cmp(&b.html_url))
+    });
+}
+
+/// Restrictions on the repositories a search returns, given on the command line.
+///
+/// GitHub receives them as search qualifiers. Results are also checked against them
+/// afterwards, which covers forges whose APIs cannot filter this way.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SearchFilters {
+    pub min_stars: Option<u64>,
+    pub created_after: Option<NaiveDate>,
+    pub pushed_after: Option<NaiveDate>,
+    pub exclude_forks: bool,
+    pub exclude_archived: bool,
+    pub exclude_mirrors: bool,
+    /// Organizations whose repositories are searched; with `users`, every other
+    /// owner is left out.
+    pub orgs: Vec<String>,
+    pub users: Vec<String>,
+    /// Kind of account whose repositories are kept; those of unknown kind are kept too.
+    pub owner_type: Option<OwnerType>,
+    /// SPDX identifiers or GitHub keys of the licenses kept; repositories without a
+    /// license are left out when any is given.
+    pub licenses: Vec<String>,
+    /// Qualifiers appended verbatim to the search query; GitHub only.
+    pub query_extra: Option<String>,
+}
+
+/// Parses a "YYYY-MM-DD" date given on the command line.
+pub fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| format!("expected a date like 2024-01-31: {}", e))
+}
+
+/// First creation date of a `--trending` run over the last `window`, counted in
+/// whole days back from `today`.
+pub fn trending_since(today: NaiveDate, window: Duration) -> NaiveDate {
+    let days = window.as_secs().div_ceil(24 * 60 * 60);
+    today - Days::new(days)
+}
+
+/// Whether an RFC 3339 timestamp falls on or after `date`. Timestamps that cannot be
+/// read are kept.
+fn on_or_after(timestamp: &str, date: NaiveDate) -> bool {
+    timestamp
+        .get(..10)
+        .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+        .is_none_or(|day| day >= date)
+}
+
+impl SearchFilters {
+    /// Whether no filter was given.
+    pub fn is_empty(&self) -> bool {
+        *self == SearchFilters::default()
+    }
+
+    /// GitHub search qualifiers, with the star range capped at `star_ceiling`.
+    pub fn github_qualifiers(&self, star_ceiling: Option<u64>) -> Vec<String> {
+        let mut qualifiers = Vec::new();
+        match (self.min_stars, star_ceiling) {
+            (Some(min), Some(max)) => qualifiers.push(format!("stars:{}..{}", min, max)),
+            (Some(min), None) => qualifiers.push(format!("stars:>={}", min)),
+            (None, Some(max)) => qualifiers.push(format!("stars:<={}", max)),
+            (None, None) => {}
+        }
+        if let Some(date) = self.created_after {
+            qualifiers.push(format!("created:>={}", date));
+        }
+        if let Some(date) = self.pushed_after {
+            qualifiers.push(format!("pushed:>={}", date));
+        }
+        for (excluded, qualifier) in [
+            (self.exclude_forks, "fork:false"),
+            (self.exclude_archived, "archived:false"),
+            (self.exclude_mirrors, "mirror:false"),
+        ] {
+            if excluded {
+                qualifiers.push(qualifier.to_string());
+            }
+        }
+        // GitHub matches any of several owner qualifiers.
+        qualifiers.extend(self.orgs.iter().map(|org| format!("org:{}", org)));
+        qualifiers.extend(self.users.iter().map(|user| format!("user:{}", user)));
+        // Several license qualifiers would all have to match, so they are only
+        // checked locally.
+        if let [license] = self.licenses.as_slice() {
+            qualifiers.push(format!("license:{}", license.to_lowercase()));
+        }
+        qualifiers.extend(self.query_extra.clone());
+        qualifiers
+    }
+
+    /// Whether `repo` passes every filter.
+    pub fn matches(&self, repo: &Repo) -> bool {
+        self.min_stars
+            .is_none_or(|min| repo.stargazers_count >= min)
+            && self
+                .created_after
+                .is_none_or(|date| on_or_after(&repo.created_at, date))
+            && self
+                .pushed_after
+                .is_none_or(|date| on_or_after(&repo.pushed_at, date))
+            && !(self.exclude_forks && repo.fork)
+            && !(self.exclude_archived && repo.archived)
+            && !(self.exclude_mirrors && repo.mirror_url.is_some())
+            && self.has_allowed_owner(repo)
+            && self
+                .owner_type
+                .is_none_or(|kind| repo.owner_type().is_none_or(|k| k == kind))
+            && self.has_allowed_license(repo)
+    }
+
+    /// Whether `repo` has one of the `licenses`, if any were given.
+    fn has_allowed_license(&self, repo: &Repo) -> bool {
+        self.licenses.is_empty()
+            || repo
+                .license
+                .as_ref()
+                .is_some_and(|license| self.licenses.iter().any(|id| license.is(id)))
+    }
+
+    /// Whether `repo` belongs to one of the `orgs` or `users`, if any were given.
+    /// Repositories of unknown owners are kept.
+    fn has_allowed_owner(&self, repo: &Repo) -> bool {
+        if self.orgs.is_empty() && self.users.is_empty() {
+            return true;
+        }
+        repo.owner_login().is_none_or(|login| {
+            self.orgs
+                .iter()
+                .chain(&self.users)
+                .any(|owner| owner.eq_ignore_ascii_case(login))
+        })
+    }
+}
+
+/// Name of the cache subfolder for searches with these filters and order, so pages
+/// cached for other searches are never reused. `None` for the default search.
+pub fn cache_key(filters: &SearchFilters, sort: SearchSort) -> Option<String> {
+    if filters.is_empty() && sort == SearchSort::default() {
+        return None;
+    }
+    let mut hasher = DefaultHasher::new();
+    (filters, sort).hash(&mut hasher);
+    Some(format!("filtered_{:016x}", hasher.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        SearchFilters, SearchSort, SortKey, SortOrder, TieBreak, cache_key, parse_date, sort_ties,
+        trending_since,
+    };
+    use crate::{License, Owner, OwnerType, Repo};
+    use std::time::Duration;
+
+    #[test]
+    fn test_github_qualifiers_combine_with_star_ceiling() {
+        let filters = SearchFilters {
+            min_stars: Some(100),
+            created_after: Some(parse_date("2023-01-01").unwrap()),
+            exclude_archived: true,
+            orgs: vec!["rust-lang".to_string(), "tokio-rs".to_string()],
+            query_extra: Some("topic:cli license:mit".to_string()),
+            ..SearchFilters::default()
+        };
+        assert_eq!(
+            filters.github_qualifiers(Some(500)),
+            [
+                "stars:100..500",
+                "created:>=2023-01-01",
+                "archived:false",
+                "org:rust-lang",
+                "org:tokio-rs",
+                "topic:cli license:mit"
+            ]
+        );
+        assert_eq!(
+            SearchFilters::default().github_qualifiers(Some(500)),
+            ["stars:<=500"]
+        );
+        let default_sort = SearchSort::default();
+        assert!(cache_key(&SearchFilters::default(), default_sort).is_none());
+        assert!(cache_key(&filters, default_sort).is_some());
+        let by_forks = SearchSort {
+            key: SortKey::Forks,
+            order: SortOrder::Asc,
+        };
+        assert_ne!(
+            cache_key(&SearchFilters::default(), by_forks),
+            cache_key(&filters, default_sort)
+        );
+        assert!(parse_date("2023-13-01").is_err());
+    }
+
+    #[test]
+    fn test_ties_are_broken_before_ranking() {
+        let repo = |name: &str, stars: u64, forks: u64| Repo {
+            name: name.to_string(),
+            html_url: format!("https://github.com/rust-lang/{}", name),
+            stargazers_count: stars,
+            forks_count: forks,
+            watchers_count: stars,
+            ..Default::default()
+        };
+        let mut repos = vec![
+            repo("cargo", 10, 1),
+            repo("rustup", 20, 1),
+            repo("book", 10, 1),
+            repo("rust", 10, 5),
+        ];
+
+        sort_ties(&mut repos, &[TieBreak::Forks, TieBreak::Name]);
+        SearchSort::default().rank(&mut repos);
+
+        let names: Vec<_> = repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["rustup", "rust", "book", "cargo"]);
+    }
+
+    #[test]
+    fn test_license_filter_matches_normalized_licenses() {
+        let mut license = License {
+            key: "apache-2.0".to_string(),
+            name: "Apache License 2.0".to_string(),
+            spdx_id: Some("NOASSERTION".to_string()),
+        };
+        license.normalize();
+        assert_eq!(license.spdx_id.as_deref(), Some("Apache-2.0"));
+        let mut other = License {
+            key: "other".to_string(),
+            name: "Other".to_string(),
+            spdx_id: Some("NOASSERTION".to_string()),
+        };
+        other.normalize();
+        assert_eq!(other.spdx_id, None);
+        assert_eq!(other.label(), "Other");
+
+        let filters = SearchFilters {
+            licenses: vec!["MIT".to_string(), "apache-2.0".to_string()],
+            ..SearchFilters::default()
+        };
+        let repo = |license: Option<License>| Repo {
+            name: "kstars".to_string(),
+            html_url: "https://github.com/luizvbo/kstars".to_string(),
+            license,
+            ..Default::default()
+        };
+        assert!(filters.matches(&repo(Some(license))));
+        assert!(!filters.matches(&repo(Some(other))));
+        assert!(!filters.matches(&repo(None)));
+        // A single license narrows the search itself.
+        let mit = SearchFilters {
+            licenses: vec!["MIT".to_string()],
+            ..SearchFilters::default()
+        };
+        assert_eq!(mit.github_qualifiers(None), ["license:mit"]);
+        assert!(filters.github_qualifiers(None).is_empty());
+    }
+
+    #[test]
+    fn test_owner_type_filter_keeps_unknown_owners() {
+        let organizations = SearchFilters {
+            owner_type: Some(OwnerType::Organization),
+            ..SearchFilters::default()
+        };
+        let mut owned = Repo {
+            name: "kstars".to_string(),
+            html_url: "https://github.com/luizvbo/kstars".to_string(),
+            ..Default::default()
+        };
+        assert!(organizations.matches(&owned));
+        owned.owner = Some(Owner {
+            login: "luizvbo".to_string(),
+            avatar_url: None,
+            kind: Some(OwnerType::User),
+        });
+        assert!(!organizations.matches(&owned));
+    }
+
+    #[test]
+    fn test_trending_since_counts_whole_days() {
+        let today = parse_date("2024-03-10").unwrap();
+        let day = 24 * 60 * 60;
+        assert_eq!(
+            trending_since(today, Duration::from_secs(30 * day)),
+            parse_date("2024-02-09").unwrap()
+        );
+        assert_eq!(
+            trending_since(today, Duration::from_secs(12 * 60 * 60)),
+            parse_date("2024-03-09").unwrap()
+        );
+    }
+}
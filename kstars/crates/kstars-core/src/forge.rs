@@ -0,0 +1,349 @@
+use crate::{
+    Hygiene, IssueCounts, PullRequestCounts, Release, Repo,
+    error::{KstarsError, Result},
+    rate_limit::{self, RateLimit, RateLimitKind, RateLimiter},
+    token_pool::{self, TokenPool},
+    transport::{HttpResponse, HttpTransport, TransportError},
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::{RequestBuilder, StatusCode, header::HeaderMap};
+use std::{cell::Cell, cmp::Reverse};
+use tracing::{debug, error, warn};
+
+/// One page of search results and the cursor of the page that follows it.
+#[derive(Debug, Clone, Default)]
+pub struct SearchPage {
+    pub repos: Vec<Repo>,
+    /// Opaque position of the next page: a page number, a GraphQL cursor or a URL,
+    /// depending on the forge. `None` once the results are exhausted.
+    pub next_cursor: Option<String>,
+    /// Repositories the query matches in total, when the forge reports it.
+    pub total_count: Option<u64>,
+    /// Whether the forge flagged the results as incomplete, e.g. a GitHub search
+    /// that timed out before looking at every repository.
+    pub incomplete_results: bool,
+}
+
+/// A code hosting service that can list the most popular repositories of a language.
+///
+/// The fetch pipeline only talks to this trait, so it handles caching, star buckets and
+/// ranking the same way for every provider and for test doubles.
+#[async_trait]
+pub trait ForgeClient: Send + Sync {
+    /// Short provider name used in logs and to keep caches apart.
+    fn name(&self) -> &'static str;
+
+    /// Limiter every request of this client has to wait on.
+    fn limiter(&self) -> &RateLimiter;
+
+    /// Access tokens requests are authenticated with (empty for anonymous access).
+    fn tokens(&self) -> &TokenPool;
+
+    /// Transport every request of this client is sent through.
+    fn transport(&self) -> &dyn HttpTransport;
+
+    /// Renews short-lived credentials in the token pool before a request is sent.
+    async fn refresh_tokens(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Search query listing the repositories of `language`, optionally restricted to
+    /// repositories with at most `star_ceiling` stars.
+    fn language_query(&self, language: &str, star_ceiling: Option<u64>) -> String;
+
+    /// Number of results a single query can page through, if the forge caps it.
+    /// Capped forges get their searches split into star buckets.
+    fn max_results_per_query(&self) -> Option<u32> {
+        None
+    }
+
+    /// Fetches the page of `query` at `cursor` (`None` for the first page).
+    async fn search_page(&self, query: &str, cursor: Option<&str>) -> Result<SearchPage>;
+
+    /// Orders collected repositories, most popular first.
+    fn rank(&self, repos: &mut [Repo]) {
+        repos.sort_by_key(|r| Reverse(r.stargazers_count));
+    }
+
+    /// Latest published release of `repo`, used by the `releases` enrichment.
+    /// Forges without a releases API report none.
+    async fn latest_release(&self, _repo: &Repo) -> Result<Option<Release>> {
+        Ok(None)
+    }
+
+    /// Number of releases of `repo`, also used by the `releases` enrichment.
+    async fn release_count(&self, _repo: &Repo) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Number of commits to `repo` over the last 52 weeks, used by the
+    /// `commit-activity` enrichment. `None` when the forge cannot tell.
+    async fn commits_last_year(&self, _repo: &Repo) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Number of accounts watching `repo`, used by the `subscribers` enrichment.
+    /// `None` when the forge cannot tell.
+    async fn subscribers_count(&self, _repo: &Repo) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Open and closed issues of `repo`, used by the `issues` enrichment. `None`
+    /// when the forge cannot tell.
+    async fn issue_counts(&self, _repo: &Repo) -> Result<Option<IssueCounts>> {
+        Ok(None)
+    }
+
+    /// Open and merged pull requests of `repo`, used by the `pull-requests`
+    /// enrichment. `None` when the forge cannot tell.
+    async fn pull_request_counts(&self, _repo: &Repo) -> Result<Option<PullRequestCounts>> {
+        Ok(None)
+    }
+
+    /// Whether `repo` lists funding links, used by the `funding` enrichment. `None`
+    /// when the forge cannot tell.
+    async fn sponsorable(&self, _repo: &Repo) -> Result<Option<bool>> {
+        Ok(None)
+    }
+
+    /// Security policy and CI files of `repo`, used by the `hygiene` enrichment.
+    /// `None` when the forge cannot tell.
+    async fn hygiene(&self, _repo: &Repo) -> Result<Option<Hygiene>> {
+        Ok(None)
+    }
+
+    /// Raw README of `repo`, used by the `readme` enrichment.
+    async fn readme(&self, _repo: &Repo) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Dates at which the stargazers on `page` (100 per page, oldest first) starred
+    /// `repo`, used by the `star-history` enrichment. Forges that do not record when
+    /// a repository was starred report none.
+    async fn stargazer_dates(&self, _repo: &Repo, _page: u32) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Inspects an error response and returns the rate limit it signals, if any.
+    fn rate_limit(&self, status: StatusCode, headers: &HeaderMap, body: &str) -> Option<RateLimit> {
+        rate_limit::classify(status, headers, body, rate_limit::server_now(headers))
+    }
+}
+
+/// URL of the `rel="next"` entry of a `Link` header, as GitHub and GitLab paginate.
+pub fn next_link(headers: &HeaderMap) -> Option<String> {
+    link(headers, "next")
+}
+
+/// Number of the last page of a listing, from the `rel="last"` entry of its `Link`
+/// header. A listing fitting in one page has none.
+pub fn last_page(headers: &HeaderMap) -> Option<u64> {
+    let url = link(headers, "last")?;
+    let (_, query) = url.split_once('?')?;
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("page="))
+        .and_then(|page| page.parse().ok())
+}
+
+/// URL of the entry of a `Link` header with the relation `rel`.
+fn link(headers: &HeaderMap, rel: &str) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    let quoted = format!("rel=\"{}\"", rel);
+    let bare = format!("rel={}", rel);
+    link.split(',').find_map(|entry| {
+        let (url, params) = entry.trim().split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == quoted || param.trim() == bare)
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+/// Status, headers and body of a successful (or accepted) response.
+pub struct ForgeResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+tokio::task_local! {
+    /// Requests sent so far by the task running [`count_requests`].
+    static SENT_REQUESTS: Cell<u32>;
+}
+
+/// Runs `future` and returns its output with the number of requests it sent through
+/// [`send_with_retry`] and [`send_accepting`], retries included.
+pub async fn count_requests<F: Future>(future: F) -> (F::Output, u32) {
+    SENT_REQUESTS
+        .scope(Cell::new(0), async move {
+            let output = future.await;
+            (output, SENT_REQUESTS.with(Cell::get))
+        })
+        .await
+}
+
+/// Sends the request produced by `build` once the forge's limiter allows it.
+///
+/// `build` receives the token picked from the forge's pool for this attempt, and the
+/// quota reported back is recorded against that token. When a token runs out of its
+/// primary quota the request is retried with another one; otherwise rate-limit
+/// responses pause the limiter and the request is rebuilt and retried. Timeouts,
+/// connection errors and 5xx responses are retried with a growing delay, up to the
+/// limiter's `max_retries`. Any other error status is returned as an error carrying
+/// the API message.
+pub async fn send_with_retry<F>(
+    forge: &(impl ForgeClient + ?Sized),
+    build: F,
+) -> Result<ForgeResponse>
+where
+    F: Fn(Option<&str>) -> RequestBuilder + Send,
+{
+    send_accepting(forge, &[], build).await
+}
+
+/// Like `send_with_retry`, but error statuses listed in `accepted` (e.g. a 404 for an
+/// optional resource) are returned as responses for the caller to inspect.
+pub async fn send_accepting<F>(
+    forge: &(impl ForgeClient + ?Sized),
+    accepted: &[StatusCode],
+    build: F,
+) -> Result<ForgeResponse>
+where
+    F: Fn(Option<&str>) -> RequestBuilder + Send,
+{
+    let mut retries = 0;
+    loop {
+        forge.limiter().acquire().await;
+        forge.refresh_tokens().await?;
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        // The URL does not depend on the token, so it picks the quota to check.
+        let resource = token_pool::resource(
+            build(None)
+                .build()
+                .context("Failed to build the request")?
+                .url(),
+        );
+        let checkout = forge.tokens().checkout(now, resource);
+        let request = build(checkout.as_ref().map(|c| c.token.as_str()))
+            .build()
+            .context("Failed to build the request")?;
+        // Outside of `count_requests` there is nothing to count.
+        let _ = SENT_REQUESTS.try_with(|sent| sent.set(sent.get() + 1));
+        let resp = match forge.transport().send(request).await {
+            Ok(resp) => resp,
+            Err(e @ TransportError::Transient(_)) if retries < forge.limiter().max_retries() => {
+                retries += 1;
+                let delay = rate_limit::transient_retry_delay(retries);
+                warn!(
+                    "{} request failed: {}. Retrying in {} seconds...",
+                    forge.name(),
+                    e,
+                    delay.as_secs()
+                );
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            Err(e) => return Err(anyhow::Error::new(e).context("HTTP request failed").into()),
+        };
+        let HttpResponse {
+            status,
+            headers,
+            body,
+        } = resp;
+        if let Some(checkout) = &checkout {
+            forge.tokens().record(checkout.index, resource, &headers);
+        }
+
+        if status.is_success() || accepted.contains(&status) {
+            return Ok(ForgeResponse {
+                status,
+                headers,
+                body,
+            });
+        }
+
+        if rate_limit::is_transient(status) && retries < forge.limiter().max_retries() {
+            retries += 1;
+            let delay = rate_limit::transient_retry_delay(retries);
+            warn!(
+                "{} request failed with status {}. Retrying in {} seconds...",
+                forge.name(),
+                status,
+                delay.as_secs()
+            );
+            tokio::time::sleep(delay).await;
+            continue;
+        }
+
+        if let Some(limit) = forge.rate_limit(status, &headers, &body) {
+            debug!("Rate limit error body: {}", body);
+            if let (RateLimitKind::Primary, Some(checkout)) = (limit.kind, &checkout) {
+                let reset_at = now + limit.wait.as_secs();
+                forge
+                    .tokens()
+                    .mark_exhausted(checkout.index, resource, reset_at);
+                if forge.tokens().has_available(now, resource) {
+                    warn!(
+                        "Token #{} hit its rate limit. Retrying with another token.",
+                        checkout.index + 1
+                    );
+                    continue;
+                }
+            }
+            forge.limiter().pause_for(limit).await?;
+            continue;
+        }
+
+        error!(
+            "{} request failed: {}. API message: {}",
+            forge.name(),
+            status,
+            body
+        );
+        if status == StatusCode::UNAUTHORIZED {
+            return Err(KstarsError::AuthFailed(body));
+        }
+        return Err(KstarsError::Api {
+            status,
+            message: body,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{last_page, next_link};
+    use reqwest::header::{HeaderMap, HeaderValue, LINK};
+
+    #[test]
+    fn test_next_link() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(next_link(&headers), None);
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                "<https://api.github.com/search/repositories?q=x&page=3>; rel=\"next\", \
+                 <https://api.github.com/search/repositories?q=x&page=10>; rel=\"last\"",
+            ),
+        );
+        assert_eq!(
+            next_link(&headers).as_deref(),
+            Some("https://api.github.com/search/repositories?q=x&page=3")
+        );
+        assert_eq!(last_page(&headers), Some(10));
+        headers.insert(
+            LINK,
+            HeaderValue::from_static("<https://api.github.com/x?page=1>; rel=\"prev\""),
+        );
+        assert_eq!(next_link(&headers), None);
+        assert_eq!(last_page(&headers), None);
+    }
+}
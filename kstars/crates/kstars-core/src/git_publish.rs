@@ -0,0 +1,232 @@
+use crate::{error::Result, manifest::Manifest, publish};
+use anyhow::Context;
+use chrono::Local;
+use git2::{
+    Commit, Cred, ErrorCode, FetchOptions, ObjectType, Oid, PushOptions, RemoteCallbacks,
+    Repository, Signature, Tree,
+};
+use std::{env, fs, path::Path, process};
+use tracing::info;
+
+/// Branch the processed files are committed to when none is given.
+pub const DEFAULT_BRANCH: &str = "data";
+
+/// Folder of the branch holding the processed files, where the website reads them.
+pub const DEFAULT_PATH: &str = "data/processed";
+
+/// Commit message when none is given; "{date}" is replaced by the snapshot date.
+pub const DEFAULT_MESSAGE: &str = "Update rankings of {date}";
+
+/// Repository and branch the processed files are committed to.
+#[derive(Debug, Clone)]
+pub struct GitTarget {
+    /// Path of a local repository, or URL of a remote one the commit is pushed to.
+    pub repo: String,
+    pub branch: String,
+    /// Folder of the branch whose contents are replaced by the processed files.
+    pub path: String,
+    /// Commit message template, e.g. [`DEFAULT_MESSAGE`].
+    pub message: String,
+}
+
+/// Commits the files of `dir` to the branch of `target`, replacing the folder at
+/// its path and keeping the rest of the branch. Remote repositories are fetched
+/// into a scratch repository and the branch is pushed back, authenticating with
+/// `token` over HTTPS. Returns the new commit, or `None` when nothing changed.
+pub fn publish_git(dir: &Path, target: &GitTarget, token: Option<&str>) -> Result<Option<String>> {
+    let date = Manifest::read(dir)
+        .map(|manifest| manifest.snapshot_date)
+        .unwrap_or_else(|_| Local::now().date_naive().to_string());
+    let message = target.message.replace("{date}", &date);
+
+    let commit = if Path::new(&target.repo).is_dir() {
+        let repo = Repository::open(&target.repo)
+            .with_context(|| format!("Failed to open repository {:?}", target.repo))?;
+        commit_dir(&repo, dir, target, &message)?
+    } else {
+        let scratch = env::temp_dir().join(format!("kstars-publish-{}", process::id()));
+        let _ = fs::remove_dir_all(&scratch);
+        let pushed = push_dir(&scratch, dir, target, &message, token);
+        let _ = fs::remove_dir_all(&scratch);
+        pushed?
+    };
+    match &commit {
+        Some(commit) => info!(
+            "Committed {:?} to {} of {} as {}",
+            dir, target.branch, target.repo, commit
+        ),
+        None => info!(
+            "{} of {} is up to date; nothing to commit.",
+            target.branch, target.repo
+        ),
+    }
+    Ok(commit.map(|commit| commit.to_string()))
+}
+
+/// Fetches the branch of a remote repository, commits onto it and pushes it back.
+fn push_dir(
+    scratch: &Path,
+    dir: &Path,
+    target: &GitTarget,
+    message: &str,
+    token: Option<&str>,
+) -> Result<Option<Oid>> {
+    let repo = Repository::init_bare(scratch)
+        .with_context(|| format!("Failed to create repository {:?}", scratch))?;
+    let mut remote = repo.remote_anonymous(&target.repo)?;
+    let mut fetch = FetchOptions::new();
+    fetch.remote_callbacks(callbacks(token));
+    remote
+        .fetch(
+            &[format!("+refs/heads/{0}:refs/heads/{0}", target.branch)],
+            Some(&mut fetch),
+            None,
+        )
+        .with_context(|| format!("Failed to fetch {} of {}", target.branch, target.repo))?;
+
+    let Some(commit) = commit_dir(&repo, dir, target, message)? else {
+        return Ok(None);
+    };
+    let mut push = PushOptions::new();
+    push.remote_callbacks(callbacks(token));
+    remote
+        .push(
+            &[format!("refs/heads/{0}:refs/heads/{0}", target.branch)],
+            Some(&mut push),
+        )
+        .with_context(|| format!("Failed to push {} to {}", target.branch, target.repo))?;
+    Ok(Some(commit))
+}
+
+/// Authenticates with `token` and fails pushes the remote rejects.
+fn callbacks(token: Option<&str>) -> RemoteCallbacks<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+    if let Some(token) = token {
+        callbacks.credentials(move |_, _, _| Cred::userpass_plaintext("x-access-token", token));
+    }
+    callbacks.push_update_reference(|reference, status| match status {
+        Some(reason) => Err(git2::Error::from_str(&format!(
+            "{} was rejected: {}",
+            reference, reason
+        ))),
+        None => Ok(()),
+    });
+    callbacks
+}
+
+/// Commits the files of `dir` onto the branch of `target` in `repo`, without
+/// touching its working tree.
+fn commit_dir(
+    repo: &Repository,
+    dir: &Path,
+    target: &GitTarget,
+    message: &str,
+) -> Result<Option<Oid>> {
+    let reference = format!("refs/heads/{}", target.branch);
+    let parent = match repo.find_reference(&reference) {
+        Ok(reference) => Some(reference.peel_to_commit()?),
+        Err(e) if e.code() == ErrorCode::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let base = parent.as_ref().map(Commit::tree).transpose()?;
+
+    let mut files = repo.treebuilder(None)?;
+    for path in publish::published_files(dir)? {
+        let content = fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let name = path.file_name().expect("files have a name");
+        files.insert(name, repo.blob(&content)?, 0o100644)?;
+    }
+    let folders: Vec<&str> = target.path.split('/').filter(|f| !f.is_empty()).collect();
+    let tree = with_subtree(repo, base.as_ref(), &folders, files.write()?)?;
+    if base.as_ref().is_some_and(|base| base.id() == tree) {
+        return Ok(None);
+    }
+
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("kstars", "kstars@users.noreply.github.com"))?;
+    let parents: Vec<&Commit> = parent.iter().collect();
+    let commit = repo.commit(
+        Some(&reference),
+        &signature,
+        &signature,
+        message,
+        &repo.find_tree(tree)?,
+        &parents,
+    )?;
+    Ok(Some(commit))
+}
+
+/// Writes `base` with the folder at `path` replaced by the tree `subtree`.
+fn with_subtree(
+    repo: &Repository,
+    base: Option<&Tree>,
+    path: &[&str],
+    subtree: Oid,
+) -> Result<Oid> {
+    let Some((folder, rest)) = path.split_first() else {
+        return Ok(subtree);
+    };
+    let child = base
+        .and_then(|base| base.get_name(folder))
+        .filter(|entry| entry.kind() == Some(ObjectType::Tree))
+        .map(|entry| repo.find_tree(entry.id()))
+        .transpose()?;
+    let mut builder = repo.treebuilder(base)?;
+    builder.insert(
+        folder,
+        with_subtree(repo, child.as_ref(), rest, subtree)?,
+        0o040000,
+    )?;
+    Ok(builder.write()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_MESSAGE, DEFAULT_PATH, GitTarget, publish_git};
+    use git2::Repository;
+    use std::{fs, path::Path};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_publish_git_commits_to_the_branch_of_a_remote() {
+        let temp_dir = tempdir().unwrap();
+        let processed = temp_dir.path().join("processed");
+        fs::create_dir(&processed).unwrap();
+        fs::write(processed.join("Rust.csv"), "Ranking\n1\n").unwrap();
+        fs::write(
+            processed.join("manifest.json"),
+            r#"{"schema_version": 2, "snapshot_date": "2025-06-01", "preview_sizes": [],
+                "languages": [], "overall_top": null}"#,
+        )
+        .unwrap();
+        let remote_path = temp_dir.path().join("remote.git");
+        let remote = Repository::init_bare(&remote_path).unwrap();
+        let mut target = GitTarget {
+            repo: format!("file://{}", remote_path.display()),
+            branch: "data".to_string(),
+            path: DEFAULT_PATH.to_string(),
+            message: DEFAULT_MESSAGE.to_string(),
+        };
+
+        let pushed = publish_git(&processed, &target, None).unwrap().unwrap();
+
+        let commit = remote
+            .find_reference("refs/heads/data")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(commit.id().to_string(), pushed);
+        assert_eq!(commit.message(), Some("Update rankings of 2025-06-01"));
+        let tree = commit.tree().unwrap();
+        assert!(tree.get_path(Path::new("data/processed/Rust.csv")).is_ok());
+        assert_eq!(publish_git(&processed, &target, None).unwrap(), None);
+
+        // A local repository gets the commit on top of the pushed one.
+        target.repo = remote_path.to_string_lossy().into_owned();
+        fs::write(processed.join("Go.csv"), "Ranking\n1\n").unwrap();
+        let committed = publish_git(&processed, &target, None).unwrap().unwrap();
+        let commit = remote.find_commit(committed.parse().unwrap()).unwrap();
+        assert_eq!(commit.parent_id(0).unwrap().to_string(), pushed);
+    }
+}
@@ -0,0 +1,623 @@
+use crate::{
+    Hygiene, IssueCounts, PullRequestCounts, Release, Repo,
+    error::Result,
+    filters::{SearchFilters, SearchSort},
+    forge::{self, ForgeClient, SearchPage, send_accepting, send_with_retry},
+    github_app::GithubApp,
+    graphql,
+    rate_limit::{self, RateLimit, RateLimitKind, RateLimiter},
+    token_pool::TokenPool,
+    transport::{HttpTransport, ReqwestTransport},
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
+use tracing::{debug, warn};
+
+/// REST API root of github.com.
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// Results per page; the search API allows at most 100.
+const PER_PAGE: u32 = 100;
+
+/// Maximum number of results the search API returns for a single query.
+pub const MAX_SEARCH_RESULTS: u32 = 1000;
+
+/// Attempts at reading repository statistics while GitHub is still computing them.
+const STATS_ATTEMPTS: u32 = 4;
+
+/// Pause between those attempts.
+const STATS_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Places GitHub looks for a security policy, in its order.
+const SECURITY_POLICY_PATHS: [&str; 3] = ["SECURITY.md", ".github/SECURITY.md", "docs/SECURITY.md"];
+
+/// Folder of the GitHub Actions workflows.
+const WORKFLOWS_PATH: &str = ".github/workflows";
+
+/// Fields of `/repos/{owner}/{name}` missing from search results.
+#[derive(Deserialize)]
+struct FullRepo {
+    subscribers_count: u64,
+}
+
+/// Entry of `/stargazers` in the star media type.
+#[derive(Deserialize)]
+struct Stargazer {
+    starred_at: String,
+}
+
+/// GitHub API flavours that can back the search.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiBackend {
+    /// REST v3 search endpoint.
+    Rest,
+    /// GraphQL v4 search, paginated by cursor.
+    Graphql,
+}
+
+/// Structure representing the search API response.
+#[derive(Deserialize, Debug)]
+struct SearchResponse {
+    /// Repositories matching the query, of which at most 1000 can be paged through.
+    #[serde(default)]
+    total_count: u64,
+    /// Set when the search timed out before looking at every repository.
+    #[serde(default)]
+    incomplete_results: bool,
+    items: Vec<Repo>,
+}
+
+/// Weekly commit counts of `/stats/participation`, oldest week first.
+#[derive(Deserialize, Debug)]
+struct Participation {
+    all: Vec<u64>,
+}
+
+/// Commits over the last 52 weeks in a `/stats/participation` response.
+fn commit_total(body: &str) -> Result<u64> {
+    let participation: Participation =
+        serde_json::from_str(body).context("Failed to deserialize participation statistics")?;
+    Ok(participation.all.iter().sum())
+}
+
+/// Value of the `page` query parameter of `url`, e.g. 3 for a `rel="next"` link to
+/// "…/search/repositories?q=…&page=3".
+fn page_number(url: &str) -> Option<u32> {
+    let url = reqwest::Url::parse(url).ok()?;
+    url.query_pairs()
+        .find(|(key, _)| key == "page")
+        .and_then(|(_, page)| page.parse().ok())
+}
+
+/// Derives the GraphQL endpoint from a REST API root.
+///
+/// github.com serves GraphQL at `https://api.github.com/graphql`, while GitHub
+/// Enterprise Server uses `https://<host>/api/graphql` next to `https://<host>/api/v3`.
+fn graphql_url(api_base_url: &str) -> String {
+    match api_base_url.strip_suffix("/v3") {
+        Some(api_root) => format!("{}/graphql", api_root),
+        None => format!("{}/graphql", api_base_url),
+    }
+}
+
+/// Searches github.com or a GitHub Enterprise Server through the REST or GraphQL API.
+pub struct GithubClient {
+    client: Client,
+    tokens: TokenPool,
+    limiter: RateLimiter,
+    /// Sends the requests built with `client`.
+    transport: Arc<dyn HttpTransport>,
+    api: ApiBackend,
+    /// REST API root without a trailing slash, e.g. `https://ghe.example.com/api/v3`.
+    api_base_url: String,
+    /// GitHub App whose installation tokens replace the pool's tokens as they expire.
+    app: Option<GithubApp>,
+    /// Qualifiers added to every search.
+    filters: SearchFilters,
+    /// Order search results come back in.
+    sort: SearchSort,
+}
+
+impl GithubClient {
+    pub fn new(
+        client: Client,
+        tokens: TokenPool,
+        limiter: RateLimiter,
+        api: ApiBackend,
+        api_base_url: &str,
+    ) -> Self {
+        Self {
+            transport: Arc::new(ReqwestTransport::new(client.clone())),
+            client,
+            tokens,
+            limiter,
+            api,
+            api_base_url: api_base_url.trim_end_matches('/').to_string(),
+            app: None,
+            filters: SearchFilters::default(),
+            sort: SearchSort::default(),
+        }
+    }
+
+    /// Restricts every search to repositories passing `filters`.
+    pub fn with_filters(mut self, filters: SearchFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Orders search results by `sort` instead of by stars, most starred first.
+    pub fn with_sort(mut self, sort: SearchSort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Authenticates as a GitHub App installation instead of with static tokens.
+    pub fn with_app(mut self, app: GithubApp) -> Self {
+        self.app = Some(app);
+        self
+    }
+
+    /// Sends the requests through `transport` instead of the network.
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// GET request to the REST API authenticated with `token`.
+    fn rest_get(&self, url: &str, token: Option<&str>) -> RequestBuilder {
+        self.rest_get_as(url, token, "application/vnd.github.v3+json")
+    }
+
+    /// GET request to the REST API asking for the `accept` media type.
+    fn rest_get_as(&self, url: &str, token: Option<&str>, accept: &str) -> RequestBuilder {
+        self.client
+            .get(url)
+            .header(reqwest::header::ACCEPT, accept)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("token {}", token.unwrap_or_default()),
+            )
+    }
+
+    /// REST URL of `path` below a repository, e.g. "releases/latest". `None` when the
+    /// owner of the repository is unknown.
+    fn repo_api_url(&self, repo: &Repo, path: &str) -> Option<String> {
+        let owner = repo.owner_login()?;
+        let url = format!("{}/repos/{}/{}", self.api_base_url, owner, repo.name);
+        if path.is_empty() {
+            return Some(url);
+        }
+        Some(format!("{}/{}", url, path))
+    }
+
+    /// Fetches a page of repositories for a search query (each page has 100 results).
+    /// The page after it is the `rel="next"` link GitHub sends, if any: its page
+    /// number, or the whole URL when it has none. Paging also stops once the pages
+    /// cover `total_count`.
+    async fn fetch_rest_page(&self, query: &str, cursor: Option<&str>) -> Result<SearchPage> {
+        let url = format!("{}/search/repositories", self.api_base_url);
+        // Numeric cursors, including those of caches written before, are page numbers.
+        let page: Option<u32> = cursor.map_or(Some(1), |cursor| cursor.parse().ok());
+        let resp = match page {
+            Some(page) => {
+                let params = [
+                    ("q", query.to_string()),
+                    ("sort", self.sort.key_name().to_string()),
+                    ("order", self.sort.order_name().to_string()),
+                    ("per_page", PER_PAGE.to_string()),
+                    ("page", page.to_string()),
+                ];
+                debug!("Requesting URL: {} with query {:?}", url, params);
+                send_with_retry(self, |token| self.rest_get(&url, token).query(&params)).await?
+            }
+            None => {
+                let next = cursor.expect("only link cursors have no page number");
+                debug!("Requesting URL: {}", next);
+                send_with_retry(self, |token| self.rest_get(next, token)).await?
+            }
+        };
+
+        // Deserialize the response into SearchResponse
+        let search_resp: SearchResponse =
+            serde_json::from_str(&resp.body).context("Failed to deserialize JSON response")?;
+        debug!(
+            "Page {} for '{}' returned {} of {} repos.",
+            cursor.unwrap_or("1"),
+            query,
+            search_resp.items.len(),
+            search_resp.total_count
+        );
+
+        let covered = page.is_some_and(|page| {
+            u64::from(page * PER_PAGE) >= search_resp.total_count.min(MAX_SEARCH_RESULTS.into())
+        });
+        let next_cursor = forge::next_link(&resp.headers)
+            .filter(|_| !covered)
+            .map(|link| page_number(&link).map_or(link, |page| page.to_string()));
+        Ok(SearchPage {
+            repos: search_resp.items,
+            next_cursor,
+            total_count: Some(search_resp.total_count),
+            incomplete_results: search_resp.incomplete_results,
+        })
+    }
+
+    /// Lists `path` with the contents API: `None` when it does not exist, else the
+    /// number of entries of a folder (1 for a file).
+    async fn contents(&self, repo: &Repo, path: &str) -> Result<Option<usize>> {
+        let Some(url) = self.repo_api_url(repo, &format!("contents/{}", path)) else {
+            return Ok(None);
+        };
+        let resp = send_accepting(self, &[StatusCode::NOT_FOUND], |token| {
+            self.rest_get(&url, token)
+        })
+        .await?;
+        if resp.status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let listing: serde_json::Value =
+            serde_json::from_str(&resp.body).context("Failed to deserialize contents")?;
+        Ok(Some(listing.as_array().map_or(1, Vec::len)))
+    }
+
+    /// Fetches one page through the GraphQL API.
+    async fn fetch_graphql_page(&self, query: &str, after: Option<&str>) -> Result<SearchPage> {
+        let request = graphql::build_search_request(query, self.sort, PER_PAGE, after);
+        debug!(
+            "Requesting GraphQL search for '{}' after cursor {:?}",
+            query, after
+        );
+        let page = self
+            .send_graphql(&request, graphql::parse_search_response)
+            .await?;
+        debug!(
+            "GraphQL page for '{}' returned {} repos.",
+            query,
+            page.repos.len()
+        );
+        Ok(page)
+    }
+
+    /// Sends a GraphQL request and parses its response. An exhausted point budget
+    /// comes back as a 200 with a `RATE_LIMITED` error, which pauses the limiter like
+    /// a 403 would before the request is sent again.
+    async fn send_graphql<T>(
+        &self,
+        request: &graphql::GraphqlRequest,
+        parse: fn(&str) -> Result<graphql::Outcome<T>>,
+    ) -> Result<T> {
+        let url = graphql_url(&self.api_base_url);
+        loop {
+            let resp = send_with_retry(self, |token| {
+                self.client
+                    .post(&url)
+                    .bearer_auth(token.unwrap_or_default())
+                    .json(request)
+            })
+            .await?;
+
+            match parse(&resp.body)? {
+                graphql::Outcome::Done(data) => return Ok(data),
+                graphql::Outcome::RateLimited => {
+                    let now = rate_limit::server_now(&resp.headers);
+                    let reset = resp
+                        .headers
+                        .get("x-ratelimit-reset")
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .unwrap_or(now + 60);
+                    self.limiter
+                        .pause_for(RateLimit {
+                            kind: RateLimitKind::Primary,
+                            wait: Duration::from_secs(reset.saturating_sub(now).max(1)),
+                        })
+                        .await?;
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ForgeClient for GithubClient {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    fn tokens(&self) -> &TokenPool {
+        &self.tokens
+    }
+
+    fn transport(&self) -> &dyn HttpTransport {
+        self.transport.as_ref()
+    }
+
+    async fn refresh_tokens(&self) -> Result<()> {
+        if let Some(app) = &self.app
+            && let Some(token) = app.refresh(&self.client, &self.api_base_url).await?
+        {
+            self.tokens.replace_tokens(vec![token]);
+        }
+        Ok(())
+    }
+
+    fn language_query(&self, language: &str, star_ceiling: Option<u64>) -> String {
+        let mut query = format!("language:{}", language);
+        for qualifier in self.filters.github_qualifiers(star_ceiling) {
+            query.push(' ');
+            query.push_str(&qualifier);
+        }
+        query
+    }
+
+    fn max_results_per_query(&self) -> Option<u32> {
+        // Star buckets only split a search ordered by stars, most starred first.
+        // Other orders end at the API's cap of 1000 results.
+        self.sort.is_by_stars_desc().then_some(MAX_SEARCH_RESULTS)
+    }
+
+    fn rank(&self, repos: &mut [Repo]) {
+        self.sort.rank(repos);
+    }
+
+    async fn search_page(&self, query: &str, cursor: Option<&str>) -> Result<SearchPage> {
+        match self.api {
+            ApiBackend::Rest => self.fetch_rest_page(query, cursor).await,
+            ApiBackend::Graphql => self.fetch_graphql_page(query, cursor).await,
+        }
+    }
+
+    /// Reads `/releases/latest`, which answers 404 when nothing was released.
+    async fn latest_release(&self, repo: &Repo) -> Result<Option<Release>> {
+        let Some(url) = self.repo_api_url(repo, "releases/latest") else {
+            return Ok(None);
+        };
+        let resp = send_accepting(self, &[StatusCode::NOT_FOUND], |token| {
+            self.rest_get(&url, token)
+        })
+        .await?;
+        if resp.status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let release = serde_json::from_str(&resp.body).context("Failed to deserialize release")?;
+        Ok(Some(release))
+    }
+
+    /// Lists one release per page, so the number of the last page is the count.
+    async fn release_count(&self, repo: &Repo) -> Result<Option<u64>> {
+        let Some(url) = self.repo_api_url(repo, "releases?per_page=1") else {
+            return Ok(None);
+        };
+        let resp = send_with_retry(self, |token| self.rest_get(&url, token)).await?;
+        if let Some(last) = forge::last_page(&resp.headers) {
+            return Ok(Some(last));
+        }
+        let releases: Vec<serde_json::Value> =
+            serde_json::from_str(&resp.body).context("Failed to deserialize releases")?;
+        Ok(Some(releases.len() as u64))
+    }
+
+    /// Sums the weekly counts of `/stats/participation`. GitHub answers 202 while it
+    /// computes the statistics of a repository, so the request is repeated a few times.
+    async fn commits_last_year(&self, repo: &Repo) -> Result<Option<u64>> {
+        let Some(url) = self.repo_api_url(repo, "stats/participation") else {
+            return Ok(None);
+        };
+        for attempt in 1..=STATS_ATTEMPTS {
+            let resp = send_with_retry(self, |token| self.rest_get(&url, token)).await?;
+            match resp.status {
+                StatusCode::ACCEPTED => {
+                    debug!(
+                        "Statistics of {} are being computed (attempt {}/{}).",
+                        repo.html_url, attempt, STATS_ATTEMPTS
+                    );
+                    if attempt < STATS_ATTEMPTS {
+                        tokio::time::sleep(STATS_RETRY_DELAY).await;
+                    }
+                }
+                // Empty repositories have no statistics.
+                StatusCode::NO_CONTENT => return Ok(None),
+                _ => return commit_total(&resp.body).map(Some),
+            }
+        }
+        warn!(
+            "GitHub was still computing the statistics of {}; leaving them empty.",
+            repo.html_url
+        );
+        Ok(None)
+    }
+
+    /// Reads `subscribers_count` from the repository itself; the search leaves it out.
+    async fn subscribers_count(&self, repo: &Repo) -> Result<Option<u64>> {
+        let Some(url) = self.repo_api_url(repo, "") else {
+            return Ok(None);
+        };
+        let resp = send_with_retry(self, |token| self.rest_get(&url, token)).await?;
+        let full: FullRepo =
+            serde_json::from_str(&resp.body).context("Failed to deserialize repository")?;
+        Ok(Some(full.subscribers_count))
+    }
+
+    /// Counts open and closed issues with one GraphQL query; REST counts pull
+    /// requests as issues.
+    async fn issue_counts(&self, repo: &Repo) -> Result<Option<IssueCounts>> {
+        let Some(owner) = repo.owner_login() else {
+            return Ok(None);
+        };
+        let request = graphql::build_issue_counts_request(owner, &repo.name);
+        self.send_graphql(&request, graphql::parse_issue_counts_response)
+            .await
+    }
+
+    /// Counts open and merged pull requests with one GraphQL query.
+    async fn pull_request_counts(&self, repo: &Repo) -> Result<Option<PullRequestCounts>> {
+        let Some(owner) = repo.owner_login() else {
+            return Ok(None);
+        };
+        let request = graphql::build_pull_request_counts_request(owner, &repo.name);
+        self.send_graphql(&request, graphql::parse_pull_request_counts_response)
+            .await
+    }
+
+    /// Reads the funding links GitHub parses out of FUNDING.yml.
+    async fn sponsorable(&self, repo: &Repo) -> Result<Option<bool>> {
+        let Some(owner) = repo.owner_login() else {
+            return Ok(None);
+        };
+        let request = graphql::build_funding_request(owner, &repo.name);
+        self.send_graphql(&request, graphql::parse_funding_response)
+            .await
+    }
+
+    /// Looks for a security policy where GitHub does, and for at least one workflow.
+    async fn hygiene(&self, repo: &Repo) -> Result<Option<Hygiene>> {
+        if repo.owner_login().is_none() {
+            return Ok(None);
+        }
+        let mut security_policy = false;
+        for path in SECURITY_POLICY_PATHS {
+            if self.contents(repo, path).await?.is_some() {
+                security_policy = true;
+                break;
+            }
+        }
+        let ci = self
+            .contents(repo, WORKFLOWS_PATH)
+            .await?
+            .is_some_and(|entries| entries > 0);
+        Ok(Some(Hygiene {
+            security_policy,
+            ci,
+        }))
+    }
+
+    /// Downloads the README in its raw form; 404 when the repository has none.
+    async fn readme(&self, repo: &Repo) -> Result<Option<String>> {
+        let Some(url) = self.repo_api_url(repo, "readme") else {
+            return Ok(None);
+        };
+        let resp = send_accepting(self, &[StatusCode::NOT_FOUND], |token| {
+            self.rest_get_as(&url, token, "application/vnd.github.raw+json")
+        })
+        .await?;
+        Ok((resp.status != StatusCode::NOT_FOUND).then_some(resp.body))
+    }
+
+    /// Lists one page of `/stargazers` in the media type that carries `starred_at`.
+    async fn stargazer_dates(&self, repo: &Repo, page: u32) -> Result<Vec<String>> {
+        let path = format!("stargazers?per_page={}&page={}", PER_PAGE, page);
+        let Some(url) = self.repo_api_url(repo, &path) else {
+            return Ok(Vec::new());
+        };
+        let resp = send_with_retry(self, |token| {
+            self.rest_get_as(&url, token, "application/vnd.github.star+json")
+        })
+        .await?;
+        let stargazers: Vec<Stargazer> =
+            serde_json::from_str(&resp.body).context("Failed to deserialize stargazers")?;
+        Ok(stargazers.into_iter().map(|s| s.starred_at).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiBackend, GITHUB_API_URL, GithubClient, commit_total, graphql_url};
+    use crate::{
+        Repo, forge::ForgeClient, rate_limit::RateLimiter, token_pool::TokenPool,
+        transport::ReplayTransport,
+    };
+    use reqwest::Method;
+    use std::{sync::Arc, time::Duration};
+
+    #[test]
+    fn test_language_query_with_star_ceiling() {
+        let github = GithubClient::new(
+            reqwest::Client::new(),
+            TokenPool::new(vec![]),
+            RateLimiter::new(Duration::ZERO),
+            ApiBackend::Rest,
+            GITHUB_API_URL,
+        );
+        assert_eq!(github.language_query("Rust", None), "language:Rust");
+        assert_eq!(
+            github.language_query("Rust", Some(1500)),
+            "language:Rust stars:<=1500"
+        );
+    }
+
+    #[test]
+    fn test_graphql_url_for_github_and_enterprise() {
+        assert_eq!(
+            graphql_url(GITHUB_API_URL),
+            "https://api.github.com/graphql"
+        );
+        assert_eq!(
+            graphql_url("https://ghe.example.com/api/v3"),
+            "https://ghe.example.com/api/graphql"
+        );
+    }
+
+    #[test]
+    fn test_commit_total_sums_the_weeks() {
+        let mut weeks = vec![0; 50];
+        weeks.extend([3, 4]);
+        let body = serde_json::json!({ "all": weeks, "owner": vec![0; 52] }).to_string();
+        assert_eq!(commit_total(&body).unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_hygiene_looks_where_github_does() {
+        let repos = "https://api.github.com/repos/o/r";
+        let transport = ReplayTransport::new()
+            .with(
+                Method::GET,
+                &format!("{}/contents/SECURITY.md", repos),
+                404,
+                &[],
+                "",
+            )
+            .with(
+                Method::GET,
+                &format!("{}/contents/.github/SECURITY.md", repos),
+                200,
+                &[],
+                r#"{"type": "file", "name": "SECURITY.md"}"#,
+            )
+            .with(
+                Method::GET,
+                &format!("{}/contents/.github/workflows", repos),
+                200,
+                &[],
+                "[]",
+            );
+        let github = GithubClient::new(
+            reqwest::Client::new(),
+            TokenPool::new(vec![]),
+            RateLimiter::new(Duration::ZERO),
+            ApiBackend::Rest,
+            GITHUB_API_URL,
+        )
+        .with_transport(Arc::new(transport));
+        let repo: Repo = serde_json::from_value(serde_json::json!({
+            "name": "r", "html_url": "https://github.com/o/r", "stargazers_count": 1,
+            "forks_count": 0, "watchers_count": 1, "language": null, "description": null,
+            "open_issues_count": 0, "created_at": "", "pushed_at": "", "size": 0,
+            "owner": {"login": "o"},
+        }))
+        .unwrap();
+
+        let hygiene = github.hygiene(&repo).await.unwrap().unwrap();
+        assert!(hygiene.security_policy);
+        // An empty workflows folder runs nothing.
+        assert!(!hygiene.ci);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::error::Result;
+use anyhow::Context;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::info;
+
+/// Installation tokens are replaced this many seconds before GitHub expires them.
+const REFRESH_MARGIN_SECS: i64 = 300;
+
+/// Claims of the JWT an app signs to authenticate as itself.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct AppClaims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+impl AppClaims {
+    /// Backdates `iat` to absorb clock drift; GitHub rejects JWTs valid for over 10 minutes.
+    fn new(app_id: &str, now: i64) -> Self {
+        Self {
+            iat: now - 60,
+            exp: now + 540,
+            iss: app_id.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Installation {
+    id: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct AccessTokenResponse {
+    token: String,
+    expires_at: String,
+}
+
+/// Whether a token expiring at `expires_at` can still be used at `now`.
+fn is_fresh(expires_at: i64, now: i64) -> bool {
+    expires_at - REFRESH_MARGIN_SECS > now
+}
+
+/// Credentials of a GitHub App, exchanged for installation tokens on demand.
+pub struct GithubApp {
+    app_id: String,
+    key: EncodingKey,
+    /// Installation to act as; the app's first installation when not given.
+    installation_id: Mutex<Option<u64>>,
+    /// Expiry of the token last handed to the pool.
+    expires_at: Mutex<Option<i64>>,
+}
+
+impl GithubApp {
+    /// Loads the app's PEM-encoded RSA private key from `private_key_path`.
+    pub fn new(
+        app_id: String,
+        private_key_path: &str,
+        installation_id: Option<u64>,
+    ) -> Result<Self> {
+        let pem = std::fs::read(private_key_path)
+            .with_context(|| format!("Failed to read private key: {}", private_key_path))?;
+        let key = EncodingKey::from_rsa_pem(&pem).context("Invalid GitHub App private key")?;
+        Ok(Self {
+            app_id,
+            key,
+            installation_id: Mutex::new(installation_id),
+            expires_at: Mutex::new(None),
+        })
+    }
+
+    fn jwt(&self, now: i64) -> Result<String> {
+        Ok(jsonwebtoken::encode(
+            &Header::new(Algorithm::RS256),
+            &AppClaims::new(&self.app_id, now),
+            &self.key,
+        )
+        .context("Failed to sign GitHub App JWT")?)
+    }
+
+    /// Returns a newly minted installation token when the current one is missing or
+    /// about to expire, and `None` while it is still fresh.
+    pub async fn refresh(&self, client: &Client, api_base_url: &str) -> Result<Option<String>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut current_expiry = self.expires_at.lock().await;
+        if current_expiry.is_some_and(|expires_at| is_fresh(expires_at, now)) {
+            return Ok(None);
+        }
+
+        let jwt = self.jwt(now)?;
+        let installation_id = self.installation_id(client, api_base_url, &jwt).await?;
+        let resp: AccessTokenResponse = client
+            .post(format!(
+                "{}/app/installations/{}/access_tokens",
+                api_base_url, installation_id
+            ))
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .bearer_auth(&jwt)
+            .send()
+            .await
+            .context("HTTP request failed")?
+            .error_for_status()
+            .context("Failed to create an installation access token")?
+            .json()
+            .await
+            .context("Failed to deserialize installation token response")?;
+
+        let expires_at = chrono::DateTime::parse_from_rfc3339(&resp.expires_at)
+            .with_context(|| format!("Invalid token expiry: {}", resp.expires_at))?
+            .timestamp();
+        info!(
+            "Minted installation token for installation {} (expires {}).",
+            installation_id, resp.expires_at
+        );
+        *current_expiry = Some(expires_at);
+        Ok(Some(resp.token))
+    }
+
+    async fn installation_id(&self, client: &Client, api_base_url: &str, jwt: &str) -> Result<u64> {
+        let mut installation_id = self.installation_id.lock().await;
+        if let Some(id) = *installation_id {
+            return Ok(id);
+        }
+        let installations: Vec<Installation> = client
+            .get(format!("{}/app/installations", api_base_url))
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .bearer_auth(jwt)
+            .send()
+            .await
+            .context("HTTP request failed")?
+            .error_for_status()
+            .context("Failed to list GitHub App installations")?
+            .json()
+            .await
+            .context("Failed to deserialize installations response")?;
+        let id = installations
+            .first()
+            .map(|i| i.id)
+            .context("The GitHub App is not installed anywhere.")?;
+        info!("Using GitHub App installation {}.", id);
+        *installation_id = Some(id);
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AppClaims, is_fresh};
+
+    #[test]
+    fn test_app_claims_stay_within_ten_minutes() {
+        let claims = AppClaims::new("123", 1_000_000);
+        assert_eq!(claims.iss, "123");
+        assert!(claims.iat < 1_000_000);
+        assert!(claims.exp - claims.iat <= 600);
+    }
+
+    #[test]
+    fn test_installation_token_refreshes_before_expiry() {
+        assert!(is_fresh(10_000, 5_000));
+        assert!(!is_fresh(10_000, 9_800));
+    }
+}
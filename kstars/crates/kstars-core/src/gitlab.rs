@@ -0,0 +1,240 @@
+use crate::{
+    Owner, OwnerType, Repo,
+    error::Result,
+    forge::{ForgeClient, SearchPage, send_with_retry},
+    rate_limit::RateLimiter,
+    token_pool::TokenPool,
+    transport::{HttpTransport, ReqwestTransport},
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::debug;
+
+const GITLAB_API_URL: &str = "https://gitlab.com/api/v4";
+
+/// Project fields returned by `GET /projects`.
+#[derive(Deserialize, Debug)]
+struct Project {
+    name: String,
+    web_url: String,
+    star_count: u64,
+    forks_count: u64,
+    description: Option<String>,
+    /// Missing when the issue tracker of the project is disabled.
+    open_issues_count: Option<u64>,
+    created_at: String,
+    last_activity_at: String,
+    #[serde(default)]
+    topics: Vec<String>,
+    namespace: Option<Namespace>,
+    #[serde(default)]
+    archived: bool,
+    /// Only present on forks.
+    forked_from_project: Option<serde_json::Value>,
+    default_branch: Option<String>,
+}
+
+/// Group or user a project belongs to.
+#[derive(Deserialize, Debug)]
+struct Namespace {
+    path: String,
+    /// Absolute, or relative to the GitLab instance.
+    avatar_url: Option<String>,
+    /// "group" or "user".
+    kind: Option<String>,
+}
+
+impl Project {
+    fn into_repo(self, language: &str) -> Repo {
+        let owner = self.namespace.map(|n| Owner {
+            avatar_url: n.avatar_url.and_then(|avatar| {
+                reqwest::Url::parse(&self.web_url)
+                    .and_then(|web_url| web_url.join(&avatar))
+                    .ok()
+                    .map(String::from)
+            }),
+            login: n.path,
+            kind: n.kind.as_deref().and_then(OwnerType::parse),
+        });
+        Repo {
+            name: self.name,
+            html_url: self.web_url,
+            stargazers_count: self.star_count,
+            forks_count: self.forks_count,
+            // GitLab has no separate watcher count; mirror GitHub's search API.
+            watchers_count: self.star_count,
+            language: Some(language.to_string()),
+            description: self.description,
+            open_issues_count: self.open_issues_count.unwrap_or_default(),
+            created_at: self.created_at,
+            pushed_at: self.last_activity_at,
+            // Repository size is only exposed to project members.
+            size: 0,
+            license: None,
+            topics: self.topics,
+            owner,
+            archived: self.archived,
+            fork: self.forked_from_project.is_some(),
+            default_branch: self.default_branch,
+            mirror_url: None,
+            details: Default::default(),
+        }
+    }
+}
+
+/// Translates kstars' GitHub-style API names into GitLab language names.
+fn gitlab_language_name(api_name: &str) -> &str {
+    match api_name {
+        "CPP" => "C++",
+        "CSharp" => "C#",
+        "Vim-script" => "Vim Script",
+        other => other,
+    }
+}
+
+/// Lists the most starred gitlab.com projects through the projects API.
+pub struct GitlabClient {
+    client: Client,
+    /// Personal access tokens; empty for anonymous access.
+    tokens: TokenPool,
+    limiter: RateLimiter,
+    /// Sends the requests built with `client`.
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl GitlabClient {
+    pub fn new(client: Client, tokens: TokenPool, limiter: RateLimiter) -> Self {
+        Self {
+            transport: Arc::new(ReqwestTransport::new(client.clone())),
+            client,
+            tokens,
+            limiter,
+        }
+    }
+
+    /// Sends the requests through `transport` instead of the network.
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+}
+
+#[async_trait]
+impl ForgeClient for GitlabClient {
+    fn name(&self) -> &'static str {
+        "gitlab"
+    }
+
+    fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    fn tokens(&self) -> &TokenPool {
+        &self.tokens
+    }
+
+    fn transport(&self) -> &dyn HttpTransport {
+        self.transport.as_ref()
+    }
+
+    /// The projects API filters by language name; there is no result cap to work
+    /// around, so star ceilings are never needed.
+    fn language_query(&self, language: &str, _star_ceiling: Option<u64>) -> String {
+        gitlab_language_name(language).to_string()
+    }
+
+    /// Fetches one page of the most starred projects written in `language`.
+    /// The cursor is the page number GitLab reports in `x-next-page`.
+    async fn search_page(&self, language: &str, cursor: Option<&str>) -> Result<SearchPage> {
+        let url = format!("{}/projects", GITLAB_API_URL);
+        let page = cursor.unwrap_or("1");
+        let params = [
+            ("with_programming_language", language.to_string()),
+            ("order_by", "star_count".to_string()),
+            ("sort", "desc".to_string()),
+            ("per_page", "100".to_string()),
+            ("page", page.to_string()),
+        ];
+        debug!("Requesting URL: {} with query {:?}", url, params);
+
+        let resp = send_with_retry(self, |token| {
+            let request = self.client.get(&url).query(&params);
+            match token {
+                Some(token) => request.header("PRIVATE-TOKEN", token),
+                None => request,
+            }
+        })
+        .await?;
+
+        let projects: Vec<Project> =
+            serde_json::from_str(&resp.body).context("Failed to deserialize JSON response")?;
+        debug!(
+            "GitLab page {} for {} returned {} projects.",
+            page,
+            language,
+            projects.len()
+        );
+        let next_cursor = resp
+            .headers
+            .get("x-next-page")
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string);
+
+        Ok(SearchPage {
+            repos: projects
+                .into_iter()
+                .map(|p| p.into_repo(language))
+                .collect(),
+            next_cursor,
+            ..SearchPage::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Project, gitlab_language_name};
+    use crate::OwnerType;
+
+    #[test]
+    fn test_project_maps_into_repo() {
+        let body = r#"{
+            "id": 1,
+            "name": "inkscape",
+            "web_url": "https://gitlab.com/inkscape/inkscape",
+            "star_count": 3000,
+            "forks_count": 900,
+            "description": null,
+            "created_at": "2017-06-09T14:16:35.615Z",
+            "last_activity_at": "2024-05-01T10:00:00.000Z",
+            "topics": ["graphics"],
+            "namespace": { "path": "inkscape", "kind": "group" },
+            "default_branch": "master"
+        }"#;
+        let project: Project = serde_json::from_str(body).unwrap();
+        let repo = project.into_repo("C++");
+
+        assert_eq!(repo.stargazers_count, 3000);
+        assert_eq!(repo.forks_count, 900);
+        assert_eq!(repo.open_issues_count, 0);
+        assert_eq!(repo.language.as_deref(), Some("C++"));
+        assert_eq!(repo.pushed_at, "2024-05-01T10:00:00.000Z");
+        assert_eq!(repo.topics, ["graphics"]);
+        assert_eq!(repo.owner_login(), Some("inkscape"));
+        assert_eq!(repo.owner_type(), Some(OwnerType::Organization));
+        assert_eq!(repo.default_branch.as_deref(), Some("master"));
+        assert!(!repo.archived && !repo.fork);
+    }
+
+    #[test]
+    fn test_gitlab_language_name() {
+        assert_eq!(gitlab_language_name("CPP"), "C++");
+        assert_eq!(gitlab_language_name("CSharp"), "C#");
+        assert_eq!(gitlab_language_name("Rust"), "Rust");
+    }
+}
@@ -0,0 +1,98 @@
+use crate::error::Result;
+use anyhow::Context;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::sync::Mutex;
+
+/// Access tokens are replaced this many seconds before Google expires them.
+const REFRESH_MARGIN_SECS: i64 = 300;
+
+/// Fields of a service account key file, as downloaded from the Google Cloud console.
+#[derive(Deserialize, Debug)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+/// Claims of the JWT a service account exchanges for an access token.
+#[derive(Serialize, Debug)]
+struct ServiceAccountClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+/// Google service account, exchanged for access tokens to `scope` on demand.
+pub struct ServiceAccount {
+    client_email: String,
+    token_uri: String,
+    key: EncodingKey,
+    scope: &'static str,
+    /// Current access token and its expiry.
+    token: Mutex<Option<(String, i64)>>,
+}
+
+impl ServiceAccount {
+    /// Loads the JSON key file at `path`.
+    pub fn load(path: &Path, scope: &'static str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read service account key: {:?}", path))?;
+        let key: ServiceAccountKey =
+            serde_json::from_str(&content).context("Invalid service account key")?;
+        Ok(Self {
+            key: EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+                .context("Invalid service account private key")?,
+            client_email: key.client_email,
+            token_uri: key.token_uri,
+            scope,
+            token: Mutex::new(None),
+        })
+    }
+
+    /// Access token, minted again when the current one is missing or about to expire.
+    pub async fn access_token(&self, client: &Client) -> Result<String> {
+        let now = chrono::Utc::now().timestamp();
+        let mut current = self.token.lock().await;
+        if let Some((token, expires_at)) = current.as_ref()
+            && expires_at - REFRESH_MARGIN_SECS > now
+        {
+            return Ok(token.clone());
+        }
+        let claims = ServiceAccountClaims {
+            iss: &self.client_email,
+            scope: self.scope,
+            aud: &self.token_uri,
+            iat: now,
+            exp: now + 3600,
+        };
+        let jwt = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key)
+            .context("Failed to sign service account JWT")?;
+        let response: TokenResponse = client
+            .post(&self.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", &jwt),
+            ])
+            .send()
+            .await
+            .context("HTTP request failed")?
+            .error_for_status()
+            .context("Failed to get a Google access token")?
+            .json()
+            .await
+            .context("Failed to deserialize Google token response")?;
+        *current = Some((response.access_token.clone(), now + response.expires_in));
+        Ok(response.access_token)
+    }
+}
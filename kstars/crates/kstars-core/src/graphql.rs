@@ -0,0 +1,540 @@
+use crate::{
+    IssueCounts, License, Owner, OwnerType, PullRequestCounts, Repo, RepoDetails,
+    error::{Result, bail},
+    filters::SearchSort,
+    forge::SearchPage,
+};
+use anyhow::Context;
+use serde::{
+    Deserialize, Serialize,
+    de::{DeserializeOwned, IgnoredAny},
+};
+use serde_json::json;
+
+/// Search query returning every field `Repo` needs in a single round trip.
+const SEARCH_QUERY: &str = r#"
+query($q: String!, $first: Int!, $after: String) {
+  search(query: $q, type: REPOSITORY, first: $first, after: $after) {
+    repositoryCount
+    pageInfo { hasNextPage endCursor }
+    nodes {
+      ... on Repository {
+        name
+        url
+        stargazerCount
+        forkCount
+        primaryLanguage { name }
+        description
+        issues(states: OPEN) { totalCount }
+        closedIssues: issues(states: CLOSED) { totalCount }
+        pullRequests(states: OPEN) { totalCount }
+        mergedPullRequests: pullRequests(states: MERGED) { totalCount }
+        releases { totalCount }
+        fundingLinks { platform }
+        watchers { totalCount }
+        createdAt
+        pushedAt
+        diskUsage
+        licenseInfo { key name spdxId }
+        repositoryTopics(first: 20) { nodes { topic { name } } }
+        owner { login avatarUrl __typename }
+        isArchived
+        isFork
+        defaultBranchRef { name }
+        mirrorUrl
+      }
+    }
+  }
+}
+"#;
+
+/// Counts the open and closed issues of one repository.
+const ISSUE_COUNTS_QUERY: &str = r#"
+query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    issues(states: OPEN) { totalCount }
+    closedIssues: issues(states: CLOSED) { totalCount }
+  }
+}
+"#;
+
+/// Counts the open and merged pull requests of one repository.
+const PULL_REQUEST_COUNTS_QUERY: &str = r#"
+query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    pullRequests(states: OPEN) { totalCount }
+    mergedPullRequests: pullRequests(states: MERGED) { totalCount }
+  }
+}
+"#;
+
+/// Lists the funding links of one repository, read from its FUNDING.yml.
+const FUNDING_QUERY: &str = r#"
+query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    fundingLinks { platform }
+  }
+}
+"#;
+
+/// Result of a GraphQL request.
+pub enum Outcome<T> {
+    /// What was asked for, e.g. a page of search results whose next cursor is set
+    /// only if more pages exist.
+    Done(T),
+    /// The point budget is exhausted and the request has to be retried later.
+    RateLimited,
+}
+
+#[derive(Serialize)]
+pub struct GraphqlRequest {
+    query: &'static str,
+    variables: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphqlResponse<D> {
+    data: Option<D>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphqlError {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchData {
+    search: SearchConnection,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SearchConnection {
+    #[serde(default)]
+    repository_count: Option<u64>,
+    page_info: PageInfo,
+    nodes: Vec<Option<RepositoryNode>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+/// Data of the queries about one repository, `None` when it is gone.
+#[derive(Deserialize, Debug)]
+struct RepositoryData<N> {
+    repository: Option<N>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct IssueCountsNode {
+    issues: TotalCount,
+    closed_issues: TotalCount,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestCountsNode {
+    pull_requests: TotalCount,
+    merged_pull_requests: TotalCount,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FundingNode {
+    funding_links: Vec<IgnoredAny>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TotalCount {
+    #[serde(rename = "totalCount")]
+    total_count: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct NameNode {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OwnerNode {
+    login: String,
+    #[serde(default)]
+    avatar_url: Option<String>,
+    /// "Organization" or "User".
+    #[serde(rename = "__typename", default)]
+    typename: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LicenseInfo {
+    key: String,
+    name: String,
+    spdx_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TopicConnection {
+    nodes: Vec<TopicNode>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TopicNode {
+    topic: NameNode,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RepositoryNode {
+    name: String,
+    url: String,
+    stargazer_count: u64,
+    fork_count: u64,
+    primary_language: Option<NameNode>,
+    description: Option<String>,
+    issues: TotalCount,
+    /// Left out by requests predating it, like `watchers`.
+    #[serde(default)]
+    closed_issues: Option<TotalCount>,
+    pull_requests: TotalCount,
+    #[serde(default)]
+    merged_pull_requests: Option<TotalCount>,
+    #[serde(default)]
+    releases: Option<TotalCount>,
+    #[serde(default)]
+    funding_links: Option<Vec<IgnoredAny>>,
+    /// Left out by older GitHub Enterprise Server releases.
+    #[serde(default)]
+    watchers: Option<TotalCount>,
+    created_at: String,
+    pushed_at: Option<String>,
+    disk_usage: Option<u64>,
+    license_info: Option<LicenseInfo>,
+    repository_topics: TopicConnection,
+    owner: OwnerNode,
+    is_archived: bool,
+    is_fork: bool,
+    /// Missing for empty repositories.
+    default_branch_ref: Option<NameNode>,
+    mirror_url: Option<String>,
+}
+
+impl From<RepositoryNode> for Repo {
+    fn from(node: RepositoryNode) -> Self {
+        let mut details = RepoDetails {
+            subscribers_count: node.watchers.map(|w| w.total_count),
+            release_count: node.releases.map(|r| r.total_count),
+            sponsorable: node.funding_links.map(|links| !links.is_empty()),
+            ..Default::default()
+        };
+        if let Some(closed) = node.closed_issues {
+            details.set_issue_counts(IssueCounts {
+                open: node.issues.total_count,
+                closed: closed.total_count,
+            });
+        }
+        if let Some(merged) = node.merged_pull_requests {
+            details.pull_requests = Some(PullRequestCounts {
+                open: node.pull_requests.total_count,
+                merged: merged.total_count,
+            });
+        }
+        Repo {
+            name: node.name,
+            html_url: node.url,
+            stargazers_count: node.stargazer_count,
+            forks_count: node.fork_count,
+            // The REST search API reports stars as watchers; keep the column comparable.
+            watchers_count: node.stargazer_count,
+            language: node.primary_language.map(|l| l.name),
+            description: node.description,
+            // REST counts open pull requests as issues too.
+            open_issues_count: node.issues.total_count + node.pull_requests.total_count,
+            pushed_at: node.pushed_at.unwrap_or_else(|| node.created_at.clone()),
+            created_at: node.created_at,
+            size: node.disk_usage.unwrap_or_default(),
+            license: node.license_info.map(|l| License {
+                key: l.key,
+                name: l.name,
+                spdx_id: l.spdx_id,
+            }),
+            topics: node
+                .repository_topics
+                .nodes
+                .into_iter()
+                .map(|t| t.topic.name)
+                .collect(),
+            owner: Some(Owner {
+                login: node.owner.login,
+                avatar_url: node.owner.avatar_url,
+                kind: node.owner.typename.as_deref().and_then(OwnerType::parse),
+            }),
+            archived: node.is_archived,
+            fork: node.is_fork,
+            default_branch: node.default_branch_ref.map(|b| b.name),
+            mirror_url: node.mirror_url,
+            details,
+        }
+    }
+}
+
+/// Builds the JSON body for one page of a search query, ordered by `sort`.
+pub fn build_search_request(
+    query: &str,
+    sort: SearchSort,
+    per_page: u32,
+    after: Option<&str>,
+) -> GraphqlRequest {
+    GraphqlRequest {
+        query: SEARCH_QUERY,
+        variables: json!({
+            "q": format!("{} sort:{}-{}", query, sort.key_name(), sort.order_name()),
+            "first": per_page,
+            "after": after,
+        }),
+    }
+}
+
+/// Builds the JSON body asking for the issue counts of `owner`/`name`.
+pub fn build_issue_counts_request(owner: &str, name: &str) -> GraphqlRequest {
+    repository_request(ISSUE_COUNTS_QUERY, owner, name)
+}
+
+/// Builds the JSON body asking for the funding links of `owner`/`name`.
+pub fn build_funding_request(owner: &str, name: &str) -> GraphqlRequest {
+    repository_request(FUNDING_QUERY, owner, name)
+}
+
+/// Builds the JSON body asking for the pull request counts of `owner`/`name`.
+pub fn build_pull_request_counts_request(owner: &str, name: &str) -> GraphqlRequest {
+    repository_request(PULL_REQUEST_COUNTS_QUERY, owner, name)
+}
+
+fn repository_request(query: &'static str, owner: &str, name: &str) -> GraphqlRequest {
+    GraphqlRequest {
+        query,
+        variables: json!({ "owner": owner, "name": name }),
+    }
+}
+
+/// Reads the data of a GraphQL response to a request for `what`.
+fn parse_data<D: DeserializeOwned>(body: &str, what: &str) -> Result<Outcome<D>> {
+    let parsed: GraphqlResponse<D> =
+        serde_json::from_str(body).context("Failed to deserialize GraphQL response")?;
+
+    // GraphQL reports an exhausted point budget as a 200 with a typed error.
+    if parsed
+        .errors
+        .iter()
+        .any(|e| e.kind.as_deref() == Some("RATE_LIMITED"))
+    {
+        return Ok(Outcome::RateLimited);
+    }
+
+    let Some(data) = parsed.data else {
+        let messages: Vec<&str> = parsed.errors.iter().map(|e| e.message.as_str()).collect();
+        bail!("GraphQL {} returned no data: {}", what, messages.join("; "));
+    };
+    Ok(Outcome::Done(data))
+}
+
+/// Parses a GraphQL issue counts response; `None` when the repository is gone.
+pub fn parse_issue_counts_response(body: &str) -> Result<Outcome<Option<IssueCounts>>> {
+    parse_repository(body, "issue counts", |repo: IssueCountsNode| IssueCounts {
+        open: repo.issues.total_count,
+        closed: repo.closed_issues.total_count,
+    })
+}
+
+/// Parses a GraphQL pull request counts response; `None` when the repository is
+/// gone.
+pub fn parse_pull_request_counts_response(
+    body: &str,
+) -> Result<Outcome<Option<PullRequestCounts>>> {
+    parse_repository(
+        body,
+        "pull request counts",
+        |repo: PullRequestCountsNode| PullRequestCounts {
+            open: repo.pull_requests.total_count,
+            merged: repo.merged_pull_requests.total_count,
+        },
+    )
+}
+
+/// Parses a GraphQL funding links response into whether the repository accepts
+/// sponsorship; `None` when it is gone.
+pub fn parse_funding_response(body: &str) -> Result<Outcome<Option<bool>>> {
+    parse_repository(body, "funding links", |repo: FundingNode| {
+        !repo.funding_links.is_empty()
+    })
+}
+
+/// Reads the repository a query about one repository asked for and maps it.
+fn parse_repository<N: DeserializeOwned, T>(
+    body: &str,
+    what: &str,
+    map: impl FnOnce(N) -> T,
+) -> Result<Outcome<Option<T>>> {
+    Ok(match parse_data::<RepositoryData<N>>(body, what)? {
+        Outcome::Done(data) => Outcome::Done(data.repository.map(map)),
+        Outcome::RateLimited => Outcome::RateLimited,
+    })
+}
+
+/// Parses a GraphQL search response into a page of repositories.
+pub fn parse_search_response(body: &str) -> Result<Outcome<SearchPage>> {
+    let data = match parse_data::<SearchData>(body, "search")? {
+        Outcome::Done(data) => data,
+        Outcome::RateLimited => return Ok(Outcome::RateLimited),
+    };
+
+    let page_info = data.search.page_info;
+    let repos = data
+        .search
+        .nodes
+        .into_iter()
+        .flatten()
+        .map(Repo::from)
+        .collect();
+    Ok(Outcome::Done(SearchPage {
+        repos,
+        next_cursor: page_info.end_cursor.filter(|_| page_info.has_next_page),
+        total_count: data.search.repository_count,
+        incomplete_results: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Outcome, build_search_request, parse_issue_counts_response, parse_search_response,
+    };
+    use crate::filters::{SearchSort, SortKey, SortOrder};
+    use crate::{IssueCounts, OwnerType};
+
+    #[test]
+    fn test_build_search_request_variables() {
+        let request = build_search_request(
+            "language:Rust",
+            SearchSort::default(),
+            100,
+            Some("Y3Vyc29yOjEwMA=="),
+        );
+        assert_eq!(request.variables["q"], "language:Rust sort:stars-desc");
+        assert_eq!(request.variables["first"], 100);
+        assert_eq!(request.variables["after"], "Y3Vyc29yOjEwMA==");
+
+        let by_issues = SearchSort {
+            key: SortKey::HelpWantedIssues,
+            order: SortOrder::Asc,
+        };
+        let first = build_search_request("language:Rust", by_issues, 100, None);
+        assert!(first.variables["after"].is_null());
+        assert_eq!(
+            first.variables["q"],
+            "language:Rust sort:help-wanted-issues-asc"
+        );
+    }
+
+    #[test]
+    fn test_repository_node_maps_into_repo() {
+        let body = r#"{
+          "data": { "search": {
+            "pageInfo": { "hasNextPage": true, "endCursor": "abc" },
+            "nodes": [{
+              "name": "rust",
+              "url": "https://github.com/rust-lang/rust",
+              "stargazerCount": 50000,
+              "forkCount": 10000,
+              "primaryLanguage": { "name": "Rust" },
+              "description": "Empowering everyone",
+              "issues": { "totalCount": 4000 },
+              "pullRequests": { "totalCount": 700 },
+              "mergedPullRequests": { "totalCount": 9000 },
+              "releases": { "totalCount": 120 },
+              "fundingLinks": [{ "platform": "OPEN_COLLECTIVE" }],
+              "watchers": { "totalCount": 1500 },
+              "createdAt": "2010-06-16T20:39:03Z",
+              "pushedAt": "2024-01-01T00:00:00Z",
+              "diskUsage": 1234,
+              "licenseInfo": { "key": "other", "name": "Other", "spdxId": "NOASSERTION" },
+              "repositoryTopics": { "nodes": [{ "topic": { "name": "compiler" } }] },
+              "owner": { "login": "rust-lang", "avatarUrl": "https://avatars.githubusercontent.com/u/5430905", "__typename": "Organization" },
+              "isArchived": false,
+              "isFork": false,
+              "defaultBranchRef": { "name": "master" },
+              "mirrorUrl": null
+            }, null]
+          } }
+        }"#;
+        let Outcome::Done(page) = parse_search_response(body).unwrap() else {
+            panic!("expected a page of results");
+        };
+        assert_eq!(page.next_cursor.as_deref(), Some("abc"));
+        assert_eq!(page.repos.len(), 1);
+
+        let repo = &page.repos[0];
+        assert_eq!(repo.stargazers_count, 50000);
+        assert_eq!(repo.open_issues_count, 4700);
+        assert_eq!(repo.details.subscribers_count, Some(1500));
+        assert_eq!(repo.details.release_count, Some(120));
+        assert_eq!(repo.details.sponsorable, Some(true));
+        let pull_requests = repo.details.pull_requests.unwrap();
+        assert_eq!((pull_requests.open, pull_requests.merged), (700, 9000));
+        assert_eq!(repo.language.as_deref(), Some("Rust"));
+        assert_eq!(repo.license.as_ref().unwrap().key, "other");
+        assert_eq!(repo.topics, vec!["compiler".to_string()]);
+        assert_eq!(repo.owner_login(), Some("rust-lang"));
+        assert_eq!(
+            repo.owner_avatar_url(),
+            Some("https://avatars.githubusercontent.com/u/5430905")
+        );
+        assert_eq!(repo.owner_type(), Some(OwnerType::Organization));
+        assert_eq!(repo.default_branch.as_deref(), Some("master"));
+        assert!(!repo.archived && !repo.fork);
+    }
+
+    #[test]
+    fn test_rate_limited_error_is_detected() {
+        let body = r#"{"data":null,"errors":[{"type":"RATE_LIMITED","message":"API rate limit exceeded"}]}"#;
+        assert!(matches!(
+            parse_search_response(body).unwrap(),
+            Outcome::RateLimited
+        ));
+        assert!(matches!(
+            parse_issue_counts_response(body).unwrap(),
+            Outcome::RateLimited
+        ));
+    }
+
+    #[test]
+    fn test_issue_counts_response() {
+        let body = r#"{"data":{"repository":{"issues":{"totalCount":30},"closedIssues":{"totalCount":90}}}}"#;
+        let Outcome::Done(counts) = parse_issue_counts_response(body).unwrap() else {
+            panic!("expected issue counts");
+        };
+        assert_eq!(
+            counts,
+            Some(IssueCounts {
+                open: 30,
+                closed: 90
+            })
+        );
+        assert_eq!(counts.unwrap().close_rate(), Some(0.75));
+        let gone = r#"{"data":{"repository":null},"errors":[{"type":"NOT_FOUND","message":"Could not resolve"}]}"#;
+        assert!(matches!(
+            parse_issue_counts_response(gone).unwrap(),
+            Outcome::Done(None)
+        ));
+    }
+}
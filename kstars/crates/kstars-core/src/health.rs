@@ -0,0 +1,177 @@
+use crate::error::{Result, bail};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// Days without a commit after which a repository gets no recency credit.
+const STALE_DAYS: f64 = 365.0;
+
+/// Releases a year that earn full release cadence credit.
+const FULL_RELEASES_PER_YEAR: f64 = 12.0;
+
+/// Stars a day that earn full star velocity credit; growth is credited on a log scale.
+const FULL_STARS_PER_DAY: f64 = 50.0;
+
+/// A part of the health score.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthPart {
+    /// How recently the default branch got a commit.
+    Recency,
+    /// Share of the issues that were closed.
+    Issues,
+    /// Releases a year since creation.
+    Releases,
+    /// Stars a day since creation.
+    Velocity,
+}
+
+/// Weights of the parts of the health score, e.g. the `[health]` table of
+/// `kstars.toml`. Only their ratios matter.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthWeights {
+    pub recency: f64,
+    pub issues: f64,
+    pub releases: f64,
+    pub velocity: f64,
+}
+
+impl Default for HealthWeights {
+    fn default() -> Self {
+        Self {
+            recency: 1.0,
+            issues: 1.0,
+            releases: 1.0,
+            velocity: 1.0,
+        }
+    }
+}
+
+impl HealthWeights {
+    pub fn set(&mut self, part: HealthPart, weight: f64) {
+        match part {
+            HealthPart::Recency => self.recency = weight,
+            HealthPart::Issues => self.issues = weight,
+            HealthPart::Releases => self.releases = weight,
+            HealthPart::Velocity => self.velocity = weight,
+        }
+    }
+
+    /// Rejects negative weights and weights that are all zero.
+    pub fn check(&self) -> Result<()> {
+        let weights = [self.recency, self.issues, self.releases, self.velocity];
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            bail!("Health weights must be zero or positive numbers.");
+        }
+        if weights.iter().all(|w| *w == 0.0) {
+            bail!("At least one health weight must be positive.");
+        }
+        Ok(())
+    }
+}
+
+/// Parses a "part=weight" pair of `--health-weights`, e.g. "recency=2".
+pub fn parse_weight(value: &str) -> Result<(HealthPart, f64), String> {
+    let (part, weight) = value.split_once('=').ok_or_else(|| {
+        format!(
+            "expected \"part=weight\", e.g. \"recency=2\", got {:?}",
+            value
+        )
+    })?;
+    let part = HealthPart::from_str(part.trim(), true)
+        .map_err(|_| format!("unknown health part {:?}", part))?;
+    match weight.trim().parse::<f64>() {
+        Ok(weight) if weight.is_finite() && weight >= 0.0 => Ok((part, weight)),
+        _ => Err(format!(
+            "the weight of {:?} must be zero or a positive number",
+            value
+        )),
+    }
+}
+
+/// What the health score of a repository is computed from; parts that are unknown
+/// are left out and the others weigh more.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HealthInputs {
+    pub days_since_commit: Option<i64>,
+    /// Closed issues over all issues, from 0 to 1.
+    pub issue_close_rate: Option<f64>,
+    pub releases_per_year: Option<f64>,
+    pub stars_per_day: Option<f64>,
+}
+
+/// Health score from 0 to 100, `None` when no part with a weight is known.
+pub fn score(inputs: &HealthInputs, weights: &HealthWeights) -> Option<f64> {
+    let parts = [
+        (
+            weights.recency,
+            inputs
+                .days_since_commit
+                .map(|days| 1.0 - days.max(0) as f64 / STALE_DAYS),
+        ),
+        (weights.issues, inputs.issue_close_rate),
+        (
+            weights.releases,
+            inputs
+                .releases_per_year
+                .map(|rate| rate / FULL_RELEASES_PER_YEAR),
+        ),
+        (
+            weights.velocity,
+            inputs
+                .stars_per_day
+                .map(|rate| rate.max(0.0).ln_1p() / FULL_STARS_PER_DAY.ln_1p()),
+        ),
+    ];
+    let (total, weighted) = parts
+        .iter()
+        .filter_map(|&(weight, part)| part.map(|part| (weight, part.clamp(0.0, 1.0))))
+        .fold((0.0, 0.0), |(total, weighted), (weight, part)| {
+            (total + weight, weighted + weight * part)
+        });
+    (total > 0.0).then(|| 100.0 * weighted / total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HealthInputs, HealthPart, HealthWeights, parse_weight, score};
+
+    #[test]
+    fn test_score_weighs_the_known_parts() {
+        let weights = HealthWeights::default();
+        let inputs = HealthInputs {
+            days_since_commit: Some(0),
+            issue_close_rate: Some(0.5),
+            releases_per_year: Some(24.0),
+            stars_per_day: Some(0.0),
+        };
+        assert_eq!(score(&inputs, &weights), Some(62.5));
+        let recent_only = HealthInputs {
+            days_since_commit: Some(730),
+            ..HealthInputs::default()
+        };
+        assert_eq!(score(&recent_only, &weights), Some(0.0));
+        assert_eq!(score(&HealthInputs::default(), &weights), None);
+
+        let mut weights = HealthWeights {
+            recency: 0.0,
+            ..HealthWeights::default()
+        };
+        assert_eq!(score(&recent_only, &weights), None);
+        weights.set(HealthPart::Issues, 3.0);
+        assert_eq!(score(&inputs, &weights), Some(50.0));
+
+        assert_eq!(parse_weight("Recency=2"), Ok((HealthPart::Recency, 2.0)));
+        assert!(parse_weight("stars=2").is_err());
+        assert!(parse_weight("issues=-1").is_err());
+        assert!(
+            HealthWeights {
+                recency: 0.0,
+                issues: 0.0,
+                releases: 0.0,
+                velocity: 0.0
+            }
+            .check()
+            .is_err()
+        );
+    }
+}
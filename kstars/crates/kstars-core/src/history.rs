@@ -0,0 +1,261 @@
+use crate::{
+    Repo,
+    error::{Result, bail},
+};
+use anyhow::Context;
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use std::{fs, path::Path, sync::Mutex};
+use tracing::info;
+
+/// Folder of the history store inside the output folder.
+pub const HISTORY_DIR_NAME: &str = "history";
+
+/// File name of the history database inside its folder.
+pub const HISTORY_FILE_NAME: &str = "history.db";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS history (
+    date TEXT NOT NULL,
+    forge TEXT NOT NULL,
+    language TEXT NOT NULL,
+    url TEXT NOT NULL,
+    name TEXT NOT NULL,
+    stars INTEGER NOT NULL,
+    forks INTEGER NOT NULL,
+    rank INTEGER NOT NULL,
+    PRIMARY KEY (date, forge, language, url)
+);
+CREATE INDEX IF NOT EXISTS history_url ON history(url);
+";
+
+/// Time series of every ranked repository: one point per repository, language and
+/// day. Rows are never deleted; a second run on the same day replaces that day's
+/// points.
+pub struct History {
+    conn: Mutex<Connection>,
+    forge: String,
+    date: NaiveDate,
+}
+
+impl History {
+    /// Opens (or creates) the store in `dir` for the points of `date`.
+    pub fn open(dir: &Path, forge: &str, date: NaiveDate) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        let path = dir.join(HISTORY_FILE_NAME);
+        let conn = Connection::open(&path)
+            .with_context(|| format!("Failed to open history: {:?}", path))?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create the history table")?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            forge: forge.to_string(),
+            date,
+        })
+    }
+
+    /// Appends the ranking of one language in a single transaction.
+    pub fn append(&self, language: &str, repos: &[Repo]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO history (date, forge, language, url, name, stars, forks, rank)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            let date = self.date.to_string();
+            for (i, repo) in repos.iter().enumerate() {
+                insert.execute(params![
+                    date,
+                    self.forge,
+                    language,
+                    repo.html_url,
+                    repo.name,
+                    repo.stargazers_count as i64,
+                    repo.forks_count as i64,
+                    i as i64 + 1,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        info!(
+            "Appended {} points for {} to the history",
+            repos.len(),
+            language
+        );
+        Ok(())
+    }
+}
+
+/// One point of a repository's time series.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct HistoryPoint {
+    pub date: String,
+    pub language: String,
+    pub stars: u64,
+    pub forks: u64,
+    pub rank: u64,
+}
+
+/// Escapes the wildcards of a LIKE pattern, with `\` as the escape character.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Path of a repository URL after its host, e.g. "owner/name".
+fn url_path(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split_once('/')
+        .map_or("", |(_, path)| path)
+        .trim_end_matches('/')
+}
+
+/// Points of `repo`, given as its URL or "owner/name", oldest first.
+pub fn series(path: &Path, repo: &str) -> Result<Vec<HistoryPoint>> {
+    let conn =
+        Connection::open(path).with_context(|| format!("Failed to open history: {:?}", path))?;
+    let repo = repo.trim_matches('/');
+    // LIKE only narrows the rows down; the whole path is compared below, so that
+    // "owner/name" matches neither ".../group/owner/name" nor "name" alone.
+    let mut query = conn.prepare(
+        "SELECT url, date, language, stars, forks, rank FROM history
+         WHERE url = ?1 OR url LIKE '%/' || ?2 ESCAPE '\\'
+         ORDER BY date, language",
+    )?;
+    let rows = query
+        .query_map(params![repo, escape_like(repo)], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                HistoryPoint {
+                    date: row.get(1)?,
+                    language: row.get(2)?,
+                    stars: row.get::<_, i64>(3)? as u64,
+                    forks: row.get::<_, i64>(4)? as u64,
+                    rank: row.get::<_, i64>(5)? as u64,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows
+        .into_iter()
+        .filter(|(url, _)| url == repo || url_path(url) == repo)
+        .map(|(_, point)| point)
+        .collect())
+}
+
+/// Formats a time series can be printed in.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeriesFormat {
+    Csv,
+    Json,
+}
+
+/// Prints the time series of `repo` from the history of `output_dir`.
+pub fn run(output_dir: &Path, repo: &str, format: SeriesFormat) -> Result<()> {
+    let path = output_dir.join(HISTORY_DIR_NAME).join(HISTORY_FILE_NAME);
+    if !path.exists() {
+        bail!(
+            "No history in {:?}; it is written by every fetch.",
+            output_dir
+        );
+    }
+    let points = series(&path, repo)?;
+    if points.is_empty() {
+        bail!("{} is not in the history.", repo);
+    }
+    match format {
+        SeriesFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout());
+            writer.write_record(["Date", "Language", "Stars", "Forks", "Rank"])?;
+            for point in &points {
+                writer.serialize((
+                    &point.date,
+                    &point.language,
+                    point.stars,
+                    point.forks,
+                    point.rank,
+                ))?;
+            }
+            writer.flush()?;
+        }
+        SeriesFormat::Json => println!("{}", serde_json::to_string_pretty(&points)?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HISTORY_FILE_NAME, History, series};
+    use crate::Repo;
+    use chrono::NaiveDate;
+    use tempfile::tempdir;
+
+    fn repo(name: &str, stars: u64) -> Repo {
+        Repo {
+            name: name.to_string(),
+            html_url: format!("https://github.com/example/{}", name),
+            stargazers_count: stars,
+            forks_count: 1,
+            watchers_count: stars,
+            language: Some("Rust".to_string()),
+            created_at: "2020-01-01T00:00:00Z".to_string(),
+            pushed_at: "2024-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_history_appends_one_point_per_day() {
+        let temp_dir = tempdir().unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2025, 6, d).unwrap();
+
+        let first = History::open(temp_dir.path(), "github", day(1)).unwrap();
+        first
+            .append("Rust", &[repo("a", 10), repo("b", 5)])
+            .unwrap();
+        // A rerun on the same day replaces its points.
+        first
+            .append("Rust", &[repo("b", 12), repo("a", 11)])
+            .unwrap();
+        let second = History::open(temp_dir.path(), "github", day(2)).unwrap();
+        second.append("Rust", &[repo("a", 20)]).unwrap();
+
+        let points = series(&temp_dir.path().join(HISTORY_FILE_NAME), "example/a").unwrap();
+        let stars: Vec<(&str, u64, u64)> = points
+            .iter()
+            .map(|p| (p.date.as_str(), p.stars, p.rank))
+            .collect();
+        assert_eq!(stars, [("2025-06-01", 11, 2), ("2025-06-02", 20, 1)]);
+    }
+
+    #[test]
+    fn test_history_series_matches_the_whole_repository_path() {
+        let temp_dir = tempdir().unwrap();
+        let day = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let mut nested = repo("a_b", 7);
+        nested.html_url = "https://gitlab.com/group/example/a_b".to_string();
+        let history = History::open(temp_dir.path(), "github", day).unwrap();
+        history
+            .append("Rust", &[repo("a_b", 10), repo("axb", 5), nested])
+            .unwrap();
+
+        let path = temp_dir.path().join(HISTORY_FILE_NAME);
+        let stars = |repo| -> Vec<u64> {
+            series(&path, repo)
+                .unwrap()
+                .iter()
+                .map(|p| p.stars)
+                .collect()
+        };
+        // "_" is not a wildcard, and subgroups are not matched by their tail.
+        assert_eq!(stars("example/a_b"), [10]);
+        assert_eq!(stars("https://github.com/example/axb/"), [5]);
+        assert_eq!(stars("group/example/a_b"), [7]);
+        assert!(stars("a_b").is_empty());
+    }
+}
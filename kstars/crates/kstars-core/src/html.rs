@@ -0,0 +1,251 @@
+use crate::{
+    delimited,
+    error::Result,
+    manifest::Manifest,
+    process::{self, DEFAULT_PREVIEW_SIZE, ISO_SUFFIX, OVERALL_TOP_STEM},
+};
+use anyhow::Context;
+use csv::StringRecord;
+use std::{fmt::Write, fs, path::Path};
+use tracing::info;
+
+/// Columns sorted as numbers rather than text.
+const NUMERIC_COLUMNS: [&str; 13] = [
+    "Ranking",
+    "Stars",
+    "Forks",
+    "Watchers",
+    "Open Issues",
+    "Size (KB)",
+    "Commits (52w)",
+    "Stars/Day",
+    "Age (Years)",
+    "Days Since Last Commit",
+    "Rank Δ",
+    "Stars Δ (7d)",
+    "Stars Gained",
+];
+
+/// Columns that are not shown; dates are sorted by their " (ISO)" column instead.
+const HIDDEN_COLUMNS: [&str; 2] = ["Owner Avatar", "README Excerpt"];
+
+const STYLE: &str = "\
+body{font-family:-apple-system,BlinkMacSystemFont,\"Segoe UI\",Roboto,Helvetica,Arial,sans-serif;\
+margin:0 auto;padding:1rem 2rem;max-width:1400px;color:#212529}\
+a{color:#007bff;text-decoration:none}a:hover{text-decoration:underline}\
+table{border-collapse:collapse;width:100%;margin-bottom:2rem;font-size:.9rem}\
+th,td{border-bottom:1px solid #e9ecef;padding:.4rem .6rem;text-align:left;vertical-align:top}\
+th{background:#f8f9fa;cursor:pointer;position:sticky;top:0;white-space:nowrap}\
+th[data-order=asc]::after{content:\" ▲\"}th[data-order=desc]::after{content:\" ▼\"}\
+tr:hover td{background:#f1f3f5}nav a{margin-right:1rem;line-height:2}";
+
+/// Sorts a table by the column whose header is clicked, toggling the order.
+const SORT_SCRIPT: &str = r#"document.querySelectorAll("table th").forEach((th) => {
+  th.addEventListener("click", () => {
+    const table = th.closest("table");
+    const column = th.cellIndex;
+    const ascending = th.dataset.order !== "asc";
+    table.querySelectorAll("th").forEach((other) => delete other.dataset.order);
+    th.dataset.order = ascending ? "asc" : "desc";
+    const value = (row) => row.cells[column].dataset.value ?? row.cells[column].textContent;
+    const rows = Array.from(table.tBodies[0].rows).sort((a, b) => {
+      const order = th.dataset.type === "numeric"
+        ? (parseFloat(value(a)) || 0) - (parseFloat(value(b)) || 0)
+        : value(a).localeCompare(value(b));
+      return ascending ? order : -order;
+    });
+    table.tBodies[0].append(...rows);
+  });
+});"#;
+
+/// Escapes text for HTML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A complete page with the stylesheet and the sorting script inlined.
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n{body}\
+         <script>\n{SORT_SCRIPT}\n</script>\n</body>\n</html>\n",
+        title = escape(title),
+    )
+}
+
+/// A sortable table of the first `max_rows` rows, or all of them. Web repository
+/// URLs become links and dates sort by their " (ISO)" column.
+fn table(headers: &StringRecord, rows: &[StringRecord], max_rows: Option<usize>) -> String {
+    let shown: Vec<usize> = (0..headers.len())
+        .filter(|&i| !HIDDEN_COLUMNS.contains(&&headers[i]) && !headers[i].ends_with(ISO_SUFFIX))
+        .collect();
+    let iso_column = |i: usize| {
+        headers
+            .iter()
+            .position(|h| h == format!("{}{}", &headers[i], ISO_SUFFIX))
+    };
+    let url_column = headers.iter().position(|h| h == "Repo URL");
+
+    let mut html = String::from("<table>\n<thead><tr>");
+    for &i in &shown {
+        let numeric = if NUMERIC_COLUMNS.contains(&&headers[i]) {
+            " data-type=\"numeric\""
+        } else {
+            ""
+        };
+        let _ = write!(html, "<th{}>{}</th>", numeric, escape(&headers[i]));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for row in rows.iter().take(max_rows.unwrap_or(usize::MAX)) {
+        html.push_str("<tr>");
+        for &i in &shown {
+            let value = row.get(i).unwrap_or_default();
+            match iso_column(i).and_then(|iso| row.get(iso)) {
+                Some(iso) if !iso.is_empty() => {
+                    let _ = write!(html, "<td data-value=\"{}\">", escape(iso));
+                }
+                _ => html.push_str("<td>"),
+            }
+            if Some(i) == url_column && value.starts_with("http") {
+                let _ = write!(
+                    html,
+                    "<a href=\"{0}\">{1}</a>",
+                    escape(value),
+                    escape(value.split_once("://").map_or(value, |(_, rest)| rest))
+                );
+            } else {
+                html.push_str(&escape(value));
+            }
+            html.push_str("</td>");
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+/// Renders the processed rankings of `input_dir` as a self-contained static site in
+/// `output_dir`: a page per ranking with its full table and an index with the top
+/// rows of each. Returns the number of pages written.
+pub fn render_site(input_dir: &Path, output_dir: &Path, delimiter: u8) -> Result<usize> {
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create directory: {:?}", output_dir))?;
+    let display_names = Manifest::display_names(input_dir);
+
+    let mut nav = String::from("<nav>");
+    let mut sections = String::new();
+    let mut pages = 0;
+    for path in process::rankings(input_dir, delimiter)? {
+        let stem = path
+            .file_stem()
+            .expect("result files have a name")
+            .to_string_lossy()
+            .into_owned();
+        let name = match display_names.get(&stem) {
+            Some(name) => name.clone(),
+            None if stem == OVERALL_TOP_STEM => "Overall".to_string(),
+            None => stem.clone(),
+        };
+        let mut reader = delimited::reader(&path, delimiter)?;
+        let headers = reader.headers()?.clone();
+        let rows = reader
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Failed to read a row of {:?}", path))?;
+
+        let file_name = format!("{}.html", stem);
+        let body = format!(
+            "<p><a href=\"index.html\">← All languages</a></p>\n<h1>Top {} repositories: {}</h1>\n{}",
+            rows.len(),
+            escape(&name),
+            table(&headers, &rows, None)
+        );
+        let page_path = output_dir.join(&file_name);
+        fs::write(&page_path, page(&format!("kstars: {}", name), &body))
+            .with_context(|| format!("Failed to write {:?}", page_path))?;
+        pages += 1;
+
+        let link = format!("<a href=\"{}\">{}</a>", escape(&file_name), escape(&name));
+        nav.push_str(&link);
+        let _ = write!(
+            sections,
+            "<h2 id=\"{}\">{}</h2>\n{}<p><a href=\"{}\">View all {} →</a></p>\n",
+            escape(&stem),
+            escape(&name),
+            table(&headers, &rows, Some(DEFAULT_PREVIEW_SIZE)),
+            escape(&file_name),
+            rows.len()
+        );
+    }
+    nav.push_str("</nav>\n");
+
+    let index = output_dir.join("index.html");
+    fs::write(
+        &index,
+        page(
+            "kstars: most starred repositories by language",
+            &format!(
+                "<h1>Most starred repositories by language</h1>\n{}{}",
+                nav, sections
+            ),
+        ),
+    )
+    .with_context(|| format!("Failed to write {:?}", index))?;
+    pages += 1;
+    info!("Rendered {} page(s) into {:?}", pages, output_dir);
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_site;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_render_site_writes_a_page_per_ranking() {
+        let temp_dir = tempdir().unwrap();
+        let processed = temp_dir.path().join("processed");
+        let site = temp_dir.path().join("site");
+        fs::create_dir(&processed).unwrap();
+        let ranking = "Ranking,Project Name,Description,Created At,Repo URL,Owner Avatar,Created At (ISO)\n\
+                       1,fmt,A <fast> formatter,16/10/2015,https://github.com/fmtlib/fmt,\
+                       https://avatars.example/1,2015-10-16T08:00:00Z\n";
+        fs::write(processed.join("CPP.csv"), ranking).unwrap();
+        fs::write(processed.join("top10_CPP.csv"), ranking).unwrap();
+        fs::write(
+            processed.join("manifest.json"),
+            r#"{"schema_version": 2, "snapshot_date": "2025-06-01", "preview_sizes": [10],
+                "languages": [{"name": "CPP", "display_name": "C++", "trending": false,
+                               "rows": 1, "columns": [], "file": "CPP.csv", "previews": {}}],
+                "overall_top": null}"#,
+        )
+        .unwrap();
+
+        assert_eq!(render_site(&processed, &site, b',').unwrap(), 2);
+
+        let index = fs::read_to_string(site.join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"CPP.html\">C++</a>"));
+        let page = fs::read_to_string(site.join("CPP.html")).unwrap();
+        assert!(page.contains("<th data-type=\"numeric\">Ranking</th>"));
+        assert!(page.contains("<td>A &lt;fast&gt; formatter</td>"));
+        assert!(page.contains("<td data-value=\"2015-10-16T08:00:00Z\">16/10/2015</td>"));
+        assert!(
+            page.contains("<a href=\"https://github.com/fmtlib/fmt\">github.com/fmtlib/fmt</a>")
+        );
+        assert!(!page.contains("Owner Avatar") && !page.contains("(ISO)"));
+        assert!(!site.join("top10_CPP.html").exists());
+    }
+}
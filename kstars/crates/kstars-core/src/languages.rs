@@ -0,0 +1,371 @@
+use crate::error::{Result, bail};
+use anyhow::Context;
+use std::{fs, path::Path};
+use tracing::{info, warn};
+
+/// Languages fetched when none are given, as (API name, display name).
+pub const DEFAULT_LANGUAGES: [(&str, &str); 34] = [
+    ("ActionScript", "ActionScript"),
+    ("C", "C"),
+    ("CSharp", "CSharp"),
+    ("CPP", "CPP"),
+    ("Clojure", "Clojure"),
+    ("CoffeeScript", "CoffeeScript"),
+    ("CSS", "CSS"),
+    ("Dart", "Dart"),
+    ("DM", "DM"),
+    ("Elixir", "Elixir"),
+    ("Go", "Go"),
+    ("Groovy", "Groovy"),
+    ("Haskell", "Haskell"),
+    ("HTML", "HTML"),
+    ("Java", "Java"),
+    ("JavaScript", "JavaScript"),
+    ("Julia", "Julia"),
+    ("Kotlin", "Kotlin"),
+    ("Lua", "Lua"),
+    ("MATLAB", "MATLAB"),
+    ("Objective-C", "Objective-C"),
+    ("Perl", "Perl"),
+    ("PHP", "PHP"),
+    ("PowerShell", "PowerShell"),
+    ("Python", "Python"),
+    ("R", "R"),
+    ("Ruby", "Ruby"),
+    ("Rust", "Rust"),
+    ("Scala", "Scala"),
+    ("Shell", "Shell"),
+    ("Swift", "Swift"),
+    ("TeX", "TeX"),
+    ("TypeScript", "TypeScript"),
+    ("Vim-script", "Vim-script"),
+];
+
+/// Other GitHub languages, so that only names close to a known one are rejected.
+const OTHER_LANGUAGES: &[&str] = &[
+    "Ada",
+    "Agda",
+    "Assembly",
+    "Astro",
+    "Awk",
+    "Batchfile",
+    "C++",
+    "C#",
+    "CMake",
+    "COBOL",
+    "Common Lisp",
+    "Coq",
+    "Crystal",
+    "CUDA",
+    "D",
+    "Dockerfile",
+    "Elm",
+    "Emacs Lisp",
+    "Erlang",
+    "F#",
+    "Fortran",
+    "GDScript",
+    "Gleam",
+    "GLSL",
+    "Hack",
+    "Haxe",
+    "HCL",
+    "Idris",
+    "Jupyter Notebook",
+    "Lean",
+    "Less",
+    "Makefile",
+    "Nim",
+    "Nix",
+    "Objective-C++",
+    "OCaml",
+    "Odin",
+    "Pascal",
+    "PLpgSQL",
+    "Prolog",
+    "PureScript",
+    "QML",
+    "Racket",
+    "Raku",
+    "ReScript",
+    "Scheme",
+    "SCSS",
+    "Smalltalk",
+    "Solidity",
+    "SQL",
+    "Svelte",
+    "SystemVerilog",
+    "Tcl",
+    "V",
+    "Vala",
+    "Verilog",
+    "VHDL",
+    "Visual Basic .NET",
+    "Vue",
+    "WebAssembly",
+    "Zig",
+];
+
+/// Mapping of a language’s API name to its display name.
+#[derive(Debug, Clone)]
+pub struct LanguageMapping {
+    pub api_name: String,
+    pub display_name: String,
+    /// Records to fetch for this language instead of the count of the run.
+    pub records: Option<u32>,
+}
+
+/// Parses language strings provided from the CLI into LanguageMapping instances.
+/// Each is "api_name[:display_name][=records]", e.g. "CPP:C++=200".
+pub fn parse_languages(args: Option<Vec<String>>) -> Result<Vec<LanguageMapping>> {
+    let mut mappings = Vec::new();
+    if let Some(lang_list) = args {
+        for lang in lang_list {
+            let (name, records) = match lang.rsplit_once('=') {
+                Some((name, records)) => match records.trim().parse::<u32>() {
+                    Ok(records) if records > 0 => (name, Some(records)),
+                    _ => bail!(
+                        "Invalid record count in language {:?}: expected a positive number.",
+                        lang
+                    ),
+                },
+                None => (lang.as_str(), None),
+            };
+            let parts: Vec<&str> = name.split(':').collect();
+            if parts.len() == 2 {
+                mappings.push(LanguageMapping {
+                    api_name: parts[0].to_string(),
+                    display_name: parts[1].to_string(),
+                    records,
+                });
+            } else {
+                mappings.push(LanguageMapping {
+                    api_name: name.to_string(),
+                    display_name: name.to_string(),
+                    records,
+                });
+            }
+        }
+    } else {
+        for (api, display) in DEFAULT_LANGUAGES {
+            mappings.push(LanguageMapping {
+                api_name: api.to_string(),
+                display_name: display.to_string(),
+                records: None,
+            });
+        }
+    }
+    info!("Parsed {} languages.", mappings.len());
+    Ok(mappings)
+}
+
+/// Drops the languages named in `excluded`, by API or display name in any case.
+/// Names matching none of them get a warning.
+pub fn exclude_languages(
+    mut languages: Vec<LanguageMapping>,
+    excluded: &[String],
+) -> Vec<LanguageMapping> {
+    let matches = |mapping: &LanguageMapping, name: &str| {
+        mapping.api_name.eq_ignore_ascii_case(name)
+            || mapping.display_name.eq_ignore_ascii_case(name)
+    };
+    for name in excluded {
+        if !languages.iter().any(|mapping| matches(mapping, name)) {
+            warn!(
+                "Excluded language \"{}\" is not in the language list.",
+                name
+            );
+        }
+    }
+    languages.retain(|mapping| !excluded.iter().any(|name| matches(mapping, name)));
+    languages
+}
+
+/// Reads the entries of `--languages` from a file, one per line. Blank lines and
+/// lines starting with "#" are skipped; "#" elsewhere is part of the entry, as in
+/// "CSharp:C#".
+pub fn read_languages_file(path: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read languages from file: {:?}", path))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Edit distance between two names, ignoring case.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// API name of the known language closest to `name`, if it is close enough to be a
+/// typo of it: the same name in another case, or within a third of its length in
+/// edits. Display names of the default languages count too.
+fn suggestion(name: &str) -> Option<&'static str> {
+    let candidates = DEFAULT_LANGUAGES
+        .iter()
+        .flat_map(|&(api, display)| [(api, api), (display, api)])
+        .chain(OTHER_LANGUAGES.iter().map(|&other| (other, other)));
+    candidates
+        .map(|(known, api)| (distance(name, known), api))
+        .filter(|&(edits, _)| edits <= name.chars().count() / 3)
+        .min_by_key(|&(edits, _)| edits)
+        .map(|(_, api)| api)
+}
+
+/// Fails on API names that look like typos of a known language, suggesting the
+/// closest one, so a run doesn't fetch nothing for them. Other unknown names only
+/// get a warning, since GitHub knows more languages than kstars does.
+pub fn check_languages(languages: &[LanguageMapping]) -> Result<()> {
+    let is_known = |name: &str| {
+        DEFAULT_LANGUAGES.iter().any(|&(api, _)| api == name) || OTHER_LANGUAGES.contains(&name)
+    };
+    let mut problems = Vec::new();
+    for mapping in languages {
+        let name = mapping.api_name.as_str();
+        if is_known(name) {
+            continue;
+        }
+        match suggestion(name) {
+            Some(known) => problems.push(format!("\"{}\" (did you mean \"{}\"?)", name, known)),
+            None => warn!(
+                "\"{}\" is not a known language; the forge may have no repositories for it.",
+                name
+            ),
+        }
+    }
+    if !problems.is_empty() {
+        bail!(
+            "Unknown language(s): {}. Pass --skip-language-check to fetch them anyway.",
+            problems.join(", ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        LanguageMapping, check_languages, exclude_languages, parse_languages, read_languages_file,
+        suggestion,
+    };
+
+    #[test]
+    fn test_parse_languages_with_custom_list() {
+        let languages = vec![
+            "CSharp:C#".to_string(),
+            "CPP:C++".to_string(),
+            "Python".to_string(),
+        ];
+
+        let mappings = parse_languages(Some(languages)).unwrap();
+
+        assert_eq!(mappings.len(), 3);
+        assert_eq!(mappings[0].api_name, "CSharp");
+        assert_eq!(mappings[0].display_name, "C#");
+        assert_eq!(mappings[1].api_name, "CPP");
+        assert_eq!(mappings[1].display_name, "C++");
+        assert_eq!(mappings[2].api_name, "Python");
+        assert_eq!(mappings[2].display_name, "Python");
+    }
+
+    #[test]
+    fn test_parse_languages_with_record_overrides() {
+        let languages = vec![
+            "CPP:C++=200".to_string(),
+            "Python".to_string(),
+            "Rust=500".to_string(),
+        ];
+
+        let mappings = parse_languages(Some(languages)).unwrap();
+
+        assert_eq!(mappings[0].api_name, "CPP");
+        assert_eq!(mappings[0].display_name, "C++");
+        assert_eq!(mappings[2].api_name, "Rust");
+        assert_eq!(mappings[2].display_name, "Rust");
+        let records: Vec<_> = mappings.iter().map(|m| m.records).collect();
+        assert_eq!(records, [Some(200), None, Some(500)]);
+        assert!(parse_languages(Some(vec!["Rust=lots".to_string()])).is_err());
+    }
+
+    #[test]
+    fn test_parse_languages_with_default_list() {
+        let mappings = parse_languages(None).unwrap();
+
+        // Check a few key languages from the default list
+        assert!(mappings.len() > 10); // Should have many default languages
+
+        // Find a few specific languages
+        let rust = mappings.iter().find(|m| m.api_name == "Rust").unwrap();
+        let csharp = mappings.iter().find(|m| m.api_name == "CSharp").unwrap();
+
+        assert_eq!(rust.display_name, "Rust");
+        assert_eq!(csharp.display_name, "CSharp");
+    }
+
+    #[test]
+    fn test_parse_languages_excludes_by_api_or_display_name() {
+        let mappings = parse_languages(Some(vec![
+            "HTML".to_string(),
+            "CSharp:C#".to_string(),
+            "Rust".to_string(),
+        ]))
+        .unwrap();
+
+        // Names match either name, ignoring case; unknown names are skipped.
+        let excluded = ["html".to_string(), "c#".to_string(), "Cobol".to_string()];
+        let remaining = exclude_languages(mappings.clone(), &excluded);
+        assert_eq!(remaining.len(), 1);
+        assert!(
+            !remaining
+                .iter()
+                .any(|m| m.api_name == "HTML" || m.api_name == "CSharp")
+        );
+    }
+
+    #[test]
+    fn test_check_languages_suggests_close_names() {
+        assert_eq!(suggestion("Javascript"), Some("JavaScript"));
+        assert_eq!(suggestion("C+?+"), Some("C++"));
+        assert_eq!(suggestion("Pyhton"), Some("Python"));
+        assert_eq!(suggestion("Brainfuck"), None);
+
+        let mapping = |name: &str| LanguageMapping {
+            api_name: name.to_string(),
+            display_name: name.to_string(),
+            records: None,
+        };
+        assert!(check_languages(&[mapping("Rust"), mapping("Zig"), mapping("Brainfuck")]).is_ok());
+        let error = check_languages(&[mapping("Rust"), mapping("Javascript")]).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("\"Javascript\" (did you mean \"JavaScript\"?)")
+        );
+    }
+
+    #[test]
+    fn test_read_languages_file_skips_comments() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("langs.txt");
+        std::fs::write(&path, "# Curated set\nRust=500\n\n  CSharp:C#  \n").unwrap();
+
+        assert_eq!(
+            read_languages_file(&path).unwrap(),
+            ["Rust=500", "CSharp:C#"]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Default `--filename-template`: one file per language in the output folder.
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{language}";
+
+/// Placeholders a filename template may use.
+const PLACEHOLDERS: [&str; 4] = ["date", "provider", "language", "api_name"];
+
+/// Values filled into a [`FilenameTemplate`] for one language.
+pub struct FileNameValues<'a> {
+    /// Date of the run, "YYYY-MM-DD".
+    pub date: &'a str,
+    pub provider: &'a str,
+    /// Display name of the language, e.g. "C++".
+    pub language: &'a str,
+    /// Name of the language in the forge's API, e.g. "CPP".
+    pub api_name: &'a str,
+}
+
+/// Path of the result files of a language below the output folder, e.g.
+/// "{date}/{provider}/{language}". A placeholder followed by ":lower" is
+/// lowercased, e.g. "{language:lower}". The extension comes from each format, so
+/// one given in the template is dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenameTemplate {
+    template: String,
+}
+
+impl Default for FilenameTemplate {
+    fn default() -> Self {
+        Self {
+            template: DEFAULT_FILENAME_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl FilenameTemplate {
+    /// Checks a `--filename-template`: known placeholders, a relative path inside
+    /// the output folder, and one file per language.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut names = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find(['{', '}']) {
+            if rest[start..].starts_with('}') {
+                return Err(format!("unmatched \"}}\" in {:?}", template));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("unmatched \"{{\" in {:?}", template))?;
+            let placeholder = &rest[start + 1..start + end];
+            let name = placeholder.strip_suffix(":lower").unwrap_or(placeholder);
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(format!(
+                    "unknown placeholder {{{}}}; expected one of {}",
+                    placeholder,
+                    PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")
+                ));
+            }
+            names.push(name);
+            rest = &rest[start + end + 1..];
+        }
+        if !names
+            .iter()
+            .any(|&name| name == "language" || name == "api_name")
+        {
+            return Err(format!(
+                "{:?} must contain {{language}} or {{api_name}}, or every language writes the same file",
+                template
+            ));
+        }
+        let path = Path::new(template);
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+            || template.ends_with(['/', '\\'])
+        {
+            return Err(format!(
+                "{:?} must be a relative path inside the output folder",
+                template
+            ));
+        }
+
+        // Drop an extension written after the last placeholder.
+        let tail = template.rfind('}').map_or(0, |i| i + 1);
+        let stem = match template[tail..].rfind('.') {
+            Some(dot) if !template[tail + dot..].contains(['/', '\\']) => &template[..tail + dot],
+            _ => template,
+        };
+        Ok(Self {
+            template: stem.to_string(),
+        })
+    }
+
+    /// Path of the files of a language without their extension, relative to the
+    /// output folder. Values are made safe for file names first.
+    pub fn render(&self, values: &FileNameValues) -> PathBuf {
+        let mut rendered = self.template.clone();
+        for (name, value) in [
+            ("date", values.date),
+            ("provider", values.provider),
+            ("language", values.language),
+            ("api_name", values.api_name),
+        ] {
+            let value = safe_file_name(value);
+            rendered = rendered
+                .replace(&format!("{{{}:lower}}", name), &value.to_lowercase())
+                .replace(&format!("{{{}}}", name), &value);
+        }
+        PathBuf::from(rendered)
+    }
+}
+
+/// Builds a file name from a language's display name.
+fn safe_file_name(display_name: &str) -> String {
+    let safe_name: String = display_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || ['_', '-', '.', '+', '#', ' '].contains(&c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    safe_name.replace(' ', "_") // Replace spaces for good measure
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileNameValues, FilenameTemplate};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_filename_template_renders_and_validates() {
+        let values = FileNameValues {
+            date: "2025-06-01",
+            provider: "github",
+            language: "C++",
+            api_name: "CPP",
+        };
+        let template = FilenameTemplate::parse("{date}/{provider}/{language:lower}.csv").unwrap();
+        assert_eq!(
+            template.render(&values),
+            PathBuf::from("2025-06-01/github/c++")
+        );
+        assert_eq!(
+            FilenameTemplate::default().render(&values),
+            PathBuf::from("C++")
+        );
+        assert_eq!(
+            FilenameTemplate::parse("v1.2/{api_name}")
+                .unwrap()
+                .render(&values),
+            PathBuf::from("v1.2/CPP")
+        );
+
+        assert!(FilenameTemplate::parse("{date}.csv").is_err());
+        assert!(FilenameTemplate::parse("{lang}.csv").is_err());
+        assert!(FilenameTemplate::parse("{language").is_err());
+        assert!(FilenameTemplate::parse("../{language}").is_err());
+        assert!(FilenameTemplate::parse("/tmp/{language}").is_err());
+    }
+}
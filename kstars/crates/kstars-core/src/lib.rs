@@ -0,0 +1,396 @@
+//! Ranks the most starred repositories of each language on GitHub, GitLab or
+//! Bitbucket and writes them as CSV, JSON, Parquet, Markdown or SQLite datasets.
+//!
+//! The `kstars` command line tool is a thin wrapper around this crate: build a
+//! forge client, describe the run with [`FetchOptions`] and hand both to a
+//! [`Fetcher`].
+//!
+//! Fallible functions return a [`KstarsError`]; [`KstarsError::kind`] tells rate
+//! limits, rejected credentials and unreadable files apart from other failures.
+
+pub mod alerts;
+pub mod atomic;
+pub mod auth;
+pub mod badges;
+#[cfg(feature = "bigquery")]
+mod bigquery_sink;
+pub mod bitbucket;
+pub mod cache;
+pub mod columns;
+pub mod database;
+pub mod delimited;
+pub mod delta;
+pub mod diff;
+pub mod enrich;
+pub mod error;
+mod fetcher;
+pub mod filters;
+pub mod forge;
+pub mod git_publish;
+pub mod github;
+pub mod github_app;
+pub mod gitlab;
+#[cfg(any(feature = "sheets", feature = "bigquery"))]
+mod google_auth;
+mod graphql;
+pub mod health;
+pub mod history;
+pub mod html;
+pub mod languages;
+pub mod layout;
+pub mod linguist;
+pub mod lock;
+pub mod manifest;
+pub mod markdown;
+pub mod merge;
+pub mod metrics;
+pub mod notify;
+pub mod owners;
+mod parquet_writer;
+#[cfg(feature = "postgres")]
+mod postgres_sink;
+pub mod process;
+pub mod progress;
+pub mod publish;
+pub mod quota;
+pub mod rate_limit;
+pub mod report;
+pub mod run_state;
+pub mod sanitize;
+pub mod serve;
+#[cfg(feature = "sheets")]
+mod sheets_sink;
+pub mod shutdown;
+pub mod sink;
+pub mod snapshot;
+pub mod token_pool;
+pub mod transport;
+pub mod validate;
+
+#[cfg(feature = "bigquery")]
+pub use bigquery_sink::BigQueryTable;
+pub use error::{KstarsError, Result};
+pub use fetcher::{FetchOptions, Fetcher};
+pub use languages::{LanguageMapping, parse_languages};
+pub use sink::OutputSink;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Columns of the per-language result files.
+pub const CSV_HEADER: [&str; 20] = [
+    "Ranking",
+    "Project Name",
+    "Stars",
+    "Forks",
+    "Watchers",
+    "Open Issues",
+    "Created At",
+    "Last Commit",
+    "Size (KB)",
+    "Description",
+    "Language",
+    "Repo URL",
+    "License",
+    "Topics",
+    "Owner",
+    "Owner Avatar",
+    "Owner Type",
+    "Archived",
+    "Fork",
+    "Default Branch",
+];
+
+/// File formats the per-language results can be written in.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// One row per repository with the columns of `CSV_HEADER`.
+    Csv,
+    /// An array of repository records.
+    Json,
+    /// One repository record per line, written as results come in.
+    Jsonl,
+    /// Columnar file with typed columns for analytics tools.
+    Parquet,
+    /// README-ready table with linked names and shortened descriptions.
+    Markdown,
+    /// Rows of the `repos` table of one `kstars.db` shared by all languages.
+    Sqlite,
+}
+
+impl OutputFormat {
+    /// Whether repositories are written while they are fetched rather than once the
+    /// final ranking is known.
+    pub fn is_streamed(self) -> bool {
+        self == OutputFormat::Jsonl
+    }
+
+    /// File extension; delimited files are ".tsv" when tab-separated.
+    pub fn extension(self, delimiter: u8) -> &'static str {
+        match self {
+            OutputFormat::Csv => delimited::extension(delimiter),
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Sqlite => "db",
+        }
+    }
+}
+
+/// Structure for a GitHub repository (partial data).
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Repo {
+    pub name: String,
+    pub html_url: String,
+    pub stargazers_count: u64,
+    pub forks_count: u64,
+    pub watchers_count: u64,
+    pub language: Option<String>,
+    pub description: Option<String>,
+    pub open_issues_count: u64,
+    pub created_at: String,
+    pub pushed_at: String,
+    pub size: u64,
+    #[serde(default)]
+    pub license: Option<License>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub owner: Option<Owner>,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub fork: bool,
+    #[serde(default)]
+    pub default_branch: Option<String>,
+    /// Upstream URL of mirrored repositories.
+    #[serde(default)]
+    pub mirror_url: Option<String>,
+    /// Filled in by the enrichment stage, after the search.
+    #[serde(default)]
+    pub details: RepoDetails,
+}
+
+impl Repo {
+    /// Login of the user or organization owning the repository.
+    pub fn owner_login(&self) -> Option<&str> {
+        self.owner.as_ref().map(|o| o.login.as_str())
+    }
+
+    /// Avatar URL of the user or organization owning the repository.
+    pub fn owner_avatar_url(&self) -> Option<&str> {
+        self.owner.as_ref().and_then(|o| o.avatar_url.as_deref())
+    }
+
+    /// Whether an organization or a user owns the repository, if the forge said.
+    pub fn owner_type(&self) -> Option<OwnerType> {
+        self.owner.as_ref().and_then(|o| o.kind)
+    }
+}
+
+/// License information attached to a repository.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct License {
+    pub key: String,
+    pub name: String,
+    pub spdx_id: Option<String>,
+}
+
+impl License {
+    /// SPDX identifier when the forge recognized the license, its name otherwise.
+    pub fn label(&self) -> &str {
+        match self.spdx_id.as_deref() {
+            Some(id) if id != "NOASSERTION" => id,
+            _ => &self.name,
+        }
+    }
+
+    /// Replaces the identifier with its SPDX spelling, looked up by `key` when the
+    /// forge gave none or "NOASSERTION". Licenses SPDX does not know get none.
+    pub fn normalize(&mut self) {
+        if self
+            .spdx_id
+            .as_deref()
+            .is_some_and(|id| id != "NOASSERTION" && !id.is_empty())
+        {
+            return;
+        }
+        self.spdx_id = SPDX_IDS
+            .iter()
+            .find(|id| id.eq_ignore_ascii_case(&self.key))
+            .map(|id| id.to_string());
+    }
+
+    /// Whether the license is `id`, given as an SPDX identifier or a GitHub key.
+    pub fn is(&self, id: &str) -> bool {
+        self.key.eq_ignore_ascii_case(id)
+            || self
+                .spdx_id
+                .as_deref()
+                .is_some_and(|spdx| spdx.eq_ignore_ascii_case(id))
+    }
+}
+
+/// SPDX identifiers of the licenses GitHub detects; their keys are the same in
+/// lower case.
+const SPDX_IDS: &[&str] = &[
+    "0BSD",
+    "AFL-3.0",
+    "AGPL-3.0",
+    "Apache-2.0",
+    "Artistic-2.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "BSD-3-Clause-Clear",
+    "BSD-4-Clause",
+    "BSL-1.0",
+    "CC-BY-4.0",
+    "CC-BY-SA-4.0",
+    "CC0-1.0",
+    "ECL-2.0",
+    "EPL-1.0",
+    "EPL-2.0",
+    "EUPL-1.1",
+    "EUPL-1.2",
+    "GPL-2.0",
+    "GPL-3.0",
+    "ISC",
+    "LGPL-2.1",
+    "LGPL-3.0",
+    "LPPL-1.3c",
+    "MIT",
+    "MIT-0",
+    "MPL-2.0",
+    "MS-PL",
+    "MS-RL",
+    "MulanPSL-2.0",
+    "NCSA",
+    "ODbL-1.0",
+    "OFL-1.1",
+    "OSL-3.0",
+    "PostgreSQL",
+    "Unlicense",
+    "UPL-1.0",
+    "Vim",
+    "WTFPL",
+    "Zlib",
+];
+
+/// Data gathered by the optional enrichment stage, one request per repository.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct RepoDetails {
+    pub latest_release: Option<Release>,
+    /// Releases published so far, drafts left out.
+    pub release_count: Option<u64>,
+    /// Commits to the default branch over the last 52 weeks.
+    pub commits_last_year: Option<u64>,
+    /// First paragraph of the README, shown when the description is empty.
+    pub readme_excerpt: Option<String>,
+    /// Accounts watching the repository for notifications. GitHub's search reports
+    /// stars as `watchers_count`; this is the real number.
+    pub subscribers_count: Option<u64>,
+    /// Issues closed so far, pull requests left out.
+    pub closed_issues: Option<u64>,
+    /// Share of the issues that were closed, from 0 to 1.
+    pub issue_close_rate: Option<f64>,
+    pub pull_requests: Option<PullRequestCounts>,
+    /// Whether the repository lists ways to sponsor it in a FUNDING.yml.
+    pub sponsorable: Option<bool>,
+    pub hygiene: Option<Hygiene>,
+}
+
+impl RepoDetails {
+    /// Records the closed issues and the close rate they give.
+    pub fn set_issue_counts(&mut self, counts: IssueCounts) {
+        self.closed_issues = Some(counts.closed);
+        self.issue_close_rate = counts.close_rate();
+    }
+}
+
+/// Files a well-kept repository has.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hygiene {
+    /// A SECURITY.md telling how to report vulnerabilities.
+    pub security_policy: bool,
+    /// GitHub Actions workflows in `.github/workflows`.
+    pub ci: bool,
+}
+
+/// Open and merged pull requests of a repository. Mirrored dumps have neither.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PullRequestCounts {
+    pub open: u64,
+    pub merged: u64,
+}
+
+/// Open and closed issues of a repository, pull requests left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IssueCounts {
+    pub open: u64,
+    pub closed: u64,
+}
+
+impl IssueCounts {
+    /// Share of the issues that were closed; `None` for repositories without any.
+    pub fn close_rate(self) -> Option<f64> {
+        let total = self.open + self.closed;
+        (total > 0).then(|| self.closed as f64 / total as f64)
+    }
+}
+
+/// A published release of a repository.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    pub published_at: Option<String>,
+}
+
+/// Account owning a repository.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Owner {
+    pub login: String,
+    /// Profile picture of the account.
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    #[serde(default, rename = "type", deserialize_with = "deserialize_owner_type")]
+    pub kind: Option<OwnerType>,
+}
+
+/// Kind of account owning a repository.
+#[derive(ValueEnum, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum OwnerType {
+    Organization,
+    User,
+}
+
+impl OwnerType {
+    /// Name of the type, as written to the results.
+    pub fn name(self) -> &'static str {
+        match self {
+            OwnerType::Organization => "organization",
+            OwnerType::User => "user",
+        }
+    }
+
+    /// Reads the account types of GitHub ("Organization", "User") and of GitLab
+    /// namespaces ("group", "user"), in any case.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "organization" | "group" => Some(OwnerType::Organization),
+            "user" => Some(OwnerType::User),
+            _ => None,
+        }
+    }
+}
+
+/// Owner types other than organizations and users, such as bots, are left unknown.
+fn deserialize_owner_type<'de, D>(deserializer: D) -> Result<Option<OwnerType>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.as_deref().and_then(OwnerType::parse))
+}
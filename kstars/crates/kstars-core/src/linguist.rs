@@ -0,0 +1,130 @@
+/// Colors GitHub gives languages, from the `languages.yml` of Linguist. Languages
+/// without a color there are left out.
+const COLORS: &[(&str, &str)] = &[
+    ("ActionScript", "#882B0F"),
+    ("Ada", "#02f88c"),
+    ("Agda", "#315665"),
+    ("Assembly", "#6E4C13"),
+    ("Astro", "#ff5a03"),
+    ("Awk", "#c30e9b"),
+    ("Batchfile", "#C1F12E"),
+    ("C", "#555555"),
+    ("C#", "#178600"),
+    ("C++", "#f34b7d"),
+    ("Clojure", "#db5855"),
+    ("CMake", "#DA3434"),
+    ("COBOL", "#005ca5"),
+    ("CoffeeScript", "#244776"),
+    ("Common Lisp", "#3fb68b"),
+    ("Coq", "#d0b68c"),
+    ("Crystal", "#000100"),
+    ("CSS", "#663399"),
+    ("CUDA", "#3A4E3A"),
+    ("D", "#ba595e"),
+    ("Dart", "#00B4AB"),
+    ("DM", "#447265"),
+    ("Dockerfile", "#384d54"),
+    ("Elixir", "#6e4a7e"),
+    ("Elm", "#60B5CC"),
+    ("Emacs Lisp", "#c065db"),
+    ("Erlang", "#B83998"),
+    ("F#", "#b845fc"),
+    ("Fortran", "#4d41b1"),
+    ("GDScript", "#355570"),
+    ("Gleam", "#ffaff3"),
+    ("GLSL", "#5686a5"),
+    ("Go", "#00ADD8"),
+    ("Groovy", "#4298b8"),
+    ("Hack", "#878787"),
+    ("Haskell", "#5e5086"),
+    ("Haxe", "#df7900"),
+    ("HCL", "#844FBA"),
+    ("HTML", "#e34c26"),
+    ("Idris", "#b30000"),
+    ("Java", "#b07219"),
+    ("JavaScript", "#f1e05a"),
+    ("Julia", "#a270ba"),
+    ("Jupyter Notebook", "#DA5B0B"),
+    ("Kotlin", "#A97BFF"),
+    ("Lean", "#000000"),
+    ("Less", "#1d365d"),
+    ("Lua", "#000080"),
+    ("Makefile", "#427819"),
+    ("MATLAB", "#e16737"),
+    ("Nim", "#ffc200"),
+    ("Nix", "#7e7eff"),
+    ("Objective-C", "#438eff"),
+    ("Objective-C++", "#6866fb"),
+    ("OCaml", "#ef7a08"),
+    ("Odin", "#60AFFE"),
+    ("Pascal", "#E3F171"),
+    ("Perl", "#0298c3"),
+    ("PHP", "#4F5D95"),
+    ("PLpgSQL", "#336790"),
+    ("PowerShell", "#012456"),
+    ("Prolog", "#74283c"),
+    ("PureScript", "#1D222D"),
+    ("Python", "#3572A5"),
+    ("QML", "#44a51c"),
+    ("R", "#198CE7"),
+    ("Racket", "#3c5caa"),
+    ("Raku", "#0000fb"),
+    ("ReScript", "#ed5051"),
+    ("Ruby", "#701516"),
+    ("Rust", "#dea584"),
+    ("Scala", "#c22d40"),
+    ("Scheme", "#1e4aec"),
+    ("SCSS", "#c6538c"),
+    ("Shell", "#89e051"),
+    ("Smalltalk", "#596706"),
+    ("Solidity", "#AA6746"),
+    ("Svelte", "#ff3e00"),
+    ("Swift", "#F05138"),
+    ("SystemVerilog", "#DAE1C2"),
+    ("Tcl", "#e4cc98"),
+    ("TeX", "#3D6117"),
+    ("TypeScript", "#3178c6"),
+    ("V", "#4f87c4"),
+    ("Vala", "#a56de2"),
+    ("Verilog", "#b2b7f8"),
+    ("VHDL", "#adb2cb"),
+    ("Vim Script", "#199f4b"),
+    ("Visual Basic .NET", "#945db7"),
+    ("Vue", "#41b883"),
+    ("WebAssembly", "#04133b"),
+    ("Zig", "#ec915c"),
+];
+
+/// Linguist names of the languages kstars knows under another API name.
+fn linguist_name(language: &str) -> &str {
+    match language {
+        "CSharp" => "C#",
+        "CPP" => "C++",
+        _ => language,
+    }
+}
+
+/// Color GitHub shows `language` with, e.g. "#dea584" for Rust. Names are matched
+/// ignoring case, with "-" standing for a space as in "Vim-script".
+pub fn color(language: &str) -> Option<&'static str> {
+    let name = linguist_name(language).replace('-', " ");
+    COLORS
+        .iter()
+        .find(|(known, _)| known.replace('-', " ").eq_ignore_ascii_case(&name))
+        .map(|&(_, color)| color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::color;
+
+    #[test]
+    fn test_color_matches_api_and_linguist_names() {
+        assert_eq!(color("Rust"), Some("#dea584"));
+        assert_eq!(color("CPP"), color("C++"));
+        assert_eq!(color("Vim-script"), Some("#199f4b"));
+        assert_eq!(color("objective-c"), Some("#438eff"));
+        assert_eq!(color("Objective-C++"), Some("#6866fb"));
+        assert_eq!(color("Brainfuck Dialect"), None);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{
+    error::{KstarsError, Result},
+    shutdown::Shutdown,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+use tracing::{info, warn};
+
+/// Lock file of an output folder, next to its caches and results.
+pub const LOCK_FILE_NAME: &str = ".kstars.lock";
+
+/// Age after which a lock is stale even if its process can't be checked, e.g. on
+/// another machine sharing the folder.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Interval between two attempts to take a lock held by another run.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Advisory lock of an output folder, so that two runs don't write the same caches
+/// and result files. Released when dropped.
+#[derive(Debug)]
+pub struct OutputLock {
+    path: PathBuf,
+}
+
+impl OutputLock {
+    /// Takes the lock of `dir`, replacing a stale one left by a run that died.
+    /// Fails with [`KstarsError::Locked`] while another run holds it.
+    pub fn acquire(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {:?}", dir))?;
+        let path = dir.join(LOCK_FILE_NAME);
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    write!(file, "{}\n{}\n", process::id(), Utc::now().to_rfc3339())
+                        .with_context(|| format!("Failed to write lock file: {:?}", path))?;
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let holder = fs::read_to_string(&path).unwrap_or_default();
+                    if !is_stale(&holder, Utc::now()) {
+                        return Err(KstarsError::Locked {
+                            path,
+                            holder: describe(&holder),
+                        });
+                    }
+                    warn!("Removing stale lock {:?} ({}).", path, describe(&holder));
+                    match fs::remove_file(&path) {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == ErrorKind::NotFound => {}
+                        Err(e) => {
+                            return Err(anyhow::Error::new(e)
+                                .context(format!("Failed to remove stale lock {:?}", path))
+                                .into());
+                        }
+                    }
+                }
+                Err(e) => {
+                    return Err(anyhow::Error::new(e)
+                        .context(format!("Failed to create lock file: {:?}", path))
+                        .into());
+                }
+            }
+        }
+    }
+
+    /// Like [`OutputLock::acquire`], but waits for the run holding the lock to
+    /// finish. Returns `None` if `shutdown` is triggered in the meantime.
+    pub async fn acquire_waiting(dir: &Path, shutdown: &Shutdown) -> Result<Option<Self>> {
+        let mut announced = false;
+        loop {
+            match Self::acquire(dir) {
+                Err(KstarsError::Locked { path, holder }) => {
+                    if !announced {
+                        info!("Waiting for {} to release {:?}...", holder, path);
+                        announced = true;
+                    }
+                }
+                result => return result.map(Some),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(RETRY_INTERVAL) => {}
+                _ = shutdown.triggered() => return Ok(None),
+            }
+        }
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to remove lock {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Whether the lock with content `holder` was left behind: its process is gone,
+/// as far as this machine can tell, or it is older than [`STALE_AFTER`].
+fn is_stale(holder: &str, now: DateTime<Utc>) -> bool {
+    let mut lines = holder.lines();
+    let (Some(pid), Some(started)) = (lines.next(), lines.next()) else {
+        // Still being written, or not a kstars lock; wait for it either way.
+        return false;
+    };
+    if let Ok(started) = DateTime::parse_from_rfc3339(started)
+        && (now - started.with_timezone(&Utc))
+            .to_std()
+            .is_ok_and(|age| age > STALE_AFTER)
+    {
+        return true;
+    }
+    // Running processes are listed in /proc on Linux; elsewhere only age counts.
+    let proc = Path::new("/proc");
+    proc.is_dir() && !proc.join(pid.trim()).exists()
+}
+
+/// Names the run holding a lock for log messages, e.g. "process 42 (since …)".
+fn describe(holder: &str) -> String {
+    let mut lines = holder.lines();
+    match (lines.next(), lines.next()) {
+        (Some(pid), Some(started)) => format!("process {} (since {})", pid, started),
+        _ => "another run".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LOCK_FILE_NAME, OutputLock};
+    use crate::error::KstarsError;
+    use std::{fs, process};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_excludes_other_runs_until_released() {
+        let dir = tempdir().unwrap();
+        let lock = OutputLock::acquire(dir.path()).unwrap();
+
+        let error = OutputLock::acquire(dir.path()).unwrap_err();
+        assert!(matches!(error, KstarsError::Locked { .. }));
+        assert!(
+            error
+                .to_string()
+                .contains(&format!("process {}", process::id()))
+        );
+
+        drop(lock);
+        assert!(!dir.path().join(LOCK_FILE_NAME).exists());
+        let _lock = OutputLock::acquire(dir.path()).unwrap();
+
+        // A lock left by a run that died long ago is taken over.
+        let stale = tempdir().unwrap();
+        fs::write(
+            stale.path().join(LOCK_FILE_NAME),
+            "42\n2000-01-01T00:00:00+00:00\n",
+        )
+        .unwrap();
+        let _lock = OutputLock::acquire(stale.path()).unwrap();
+    }
+}
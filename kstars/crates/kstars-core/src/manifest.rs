@@ -0,0 +1,109 @@
+use crate::{
+    atomic,
+    error::{KstarsError, Result},
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+use tracing::info;
+
+/// Name of the manifest written next to the processed rankings.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Version of the layout of the processed files and of the manifest. Bumped when
+/// consumers have to change the way they read them:
+///
+/// 1. First manifest.
+/// 2. Dates are also kept in " (ISO)" columns and every ranking lists its columns.
+/// 3. "Watchers" counts the accounts watching a repository; it is left out rather
+///    than repeat the stars.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// One processed ranking.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct DatasetEntry {
+    /// File stem of the ranking, e.g. "CPP"; the website links it as `?lang=`.
+    pub name: String,
+    pub display_name: String,
+    /// Whether the ranking only holds repositories created recently.
+    pub trending: bool,
+    /// Linguist color of the language of the ranking, e.g. "#dea584" for Rust.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    pub rows: usize,
+    /// Header of the ranking, for consumers to check the columns they read.
+    pub columns: Vec<String>,
+    /// Path of the ranking, relative to the manifest.
+    pub file: String,
+    /// Preview files by their number of rows.
+    pub previews: BTreeMap<usize, String>,
+}
+
+/// Contents of `manifest.json`, which tells the website and downstream users what
+/// the dataset holds.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Manifest {
+    pub schema_version: u32,
+    /// Day the rankings were fetched, e.g. "2025-06-01".
+    pub snapshot_date: String,
+    /// Rows of the previews written for every ranking, e.g. `top10_` and `top50_`
+    /// files for `[10, 50]`.
+    pub preview_sizes: Vec<usize>,
+    pub languages: Vec<DatasetEntry>,
+    /// Path of the ranking merging every language, if it was written.
+    pub overall_top: Option<String>,
+    /// Path of the leaderboard of the owners of the ranked repositories, if written.
+    #[serde(default)]
+    pub owners: Option<String>,
+    /// Linguist colors of the languages in the "Language" column of the rankings.
+    #[serde(default)]
+    pub language_colors: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Reads the manifest of `dir`, which must not be newer than [`SCHEMA_VERSION`].
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE_NAME);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read manifest: {:?}", path))?;
+        let manifest: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid manifest: {:?}", path))?;
+        if manifest.schema_version > SCHEMA_VERSION {
+            return Err(KstarsError::SchemaMismatch {
+                path,
+                reason: format!(
+                    "schema version {} is newer than the supported {}",
+                    manifest.schema_version, SCHEMA_VERSION
+                ),
+            });
+        }
+        Ok(manifest)
+    }
+
+    /// Display names of the rankings of `dir` by file stem; empty without a manifest.
+    pub fn display_names(dir: &Path) -> HashMap<String, String> {
+        Self::read(dir)
+            .map(|manifest| {
+                manifest
+                    .languages
+                    .into_iter()
+                    .map(|entry| (entry.name, entry.display_name))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Writes the manifest to `output_dir`.
+    pub fn write(&self, output_dir: &Path) -> Result<()> {
+        let path = output_dir.join(MANIFEST_FILE_NAME);
+        let content = serde_json::to_string_pretty(self)?;
+        atomic::write(&path, content)
+            .with_context(|| format!("Failed to write manifest: {:?}", path))?;
+        info!("Manifest written to {:?}", path);
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{
+    Repo,
+    atomic::AtomicFile,
+    delimited,
+    error::Result,
+    manifest::Manifest,
+    process::{self, OVERALL_TOP_STEM},
+    sink::OutputSink,
+};
+use anyhow::Context;
+use chrono::Local;
+use csv::StringRecord;
+use std::{fmt::Write as _, fs, io::Write, path::Path};
+use tracing::info;
+
+/// Folder of the generated list holding a page per language.
+pub const DOCS_DIR_NAME: &str = "docs";
+
+/// Page of a language when no template is given. `{language}`, `{count}`, `{date}`
+/// and `{table}` are filled in.
+pub const DEFAULT_PAGE_TEMPLATE: &str = "# Top {count} {language} repositories
+
+The most starred {language} repositories on GitHub as of {date}.
+[Back to all languages](../README.md)
+
+{table}";
+
+/// Index of the languages when no template is given. `{date}` and `{languages}`,
+/// a bulleted list of links to the pages, are filled in.
+pub const DEFAULT_INDEX_TEMPLATE: &str = "# Most starred repositories per language
+
+The most starred repositories on GitHub for each language as of {date}.
+
+{languages}";
+
+/// Descriptions longer than this many characters are cut short.
+const MAX_DESCRIPTION_CHARS: usize = 100;
+
+/// Makes free text safe for a table cell: pipes are escaped, line breaks flattened,
+/// and long text truncated with an ellipsis.
+fn table_cell(text: &str, max_chars: usize) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let truncated = match flat.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", flat[..end].trim_end()),
+        None => flat,
+    };
+    truncated.replace('|', "\\|")
+}
+
+/// Header and alignment rows of the table.
+const TABLE_HEADER: &str = "| Ranking | Project Name | Stars | Forks | Open Issues | Last Commit | Description |\n\
+                            |--------:|--------------|------:|------:|------------:|-------------|-------------|\n";
+
+/// Renders one table row with a linked name.
+fn render_row(ranking: usize, repo: &Repo) -> String {
+    let last_commit = repo.pushed_at.get(..10).unwrap_or(&repo.pushed_at);
+    format!(
+        "| {} | [{}]({}) | {} | {} | {} | {} | {} |\n",
+        ranking,
+        table_cell(&repo.name, usize::MAX).replace(['[', ']'], ""),
+        repo.html_url,
+        repo.stargazers_count,
+        repo.forks_count,
+        repo.open_issues_count,
+        last_commit,
+        table_cell(
+            repo.description.as_deref().unwrap_or_default(),
+            MAX_DESCRIPTION_CHARS
+        ),
+    )
+}
+
+/// Markdown file holding a single table of the ranking.
+pub struct MarkdownSink {
+    /// Taken by `finish`.
+    file: Option<AtomicFile>,
+}
+
+impl MarkdownSink {
+    pub fn create(path: &Path) -> Result<Self> {
+        info!("Writing repositories to Markdown: {:?}", path);
+        Ok(Self {
+            file: Some(AtomicFile::create(path)?),
+        })
+    }
+
+    fn file(&mut self) -> &mut AtomicFile {
+        self.file.as_mut().expect("rows are written before finish")
+    }
+}
+
+impl OutputSink for MarkdownSink {
+    fn write_header(&mut self) -> Result<()> {
+        self.file().write_all(TABLE_HEADER.as_bytes())?;
+        Ok(())
+    }
+
+    fn write_repo(&mut self, ranking: usize, repo: &Repo) -> Result<()> {
+        self.file()
+            .write_all(render_row(ranking, repo).as_bytes())?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(file) = self.file.take() {
+            file.commit()?;
+        }
+        info!("Markdown file written successfully.");
+        Ok(())
+    }
+}
+
+/// Templates of the pages written by [`render_docs`].
+#[derive(Debug, Clone)]
+pub struct DocTemplates {
+    pub page: String,
+    pub index: String,
+}
+
+impl Default for DocTemplates {
+    fn default() -> Self {
+        Self {
+            page: DEFAULT_PAGE_TEMPLATE.to_string(),
+            index: DEFAULT_INDEX_TEMPLATE.to_string(),
+        }
+    }
+}
+
+/// Renders a processed ranking as a table in the layout of [`TABLE_HEADER`].
+fn render_table(headers: &StringRecord, records: &[StringRecord]) -> String {
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let columns = [
+        "Project Name",
+        "Stars",
+        "Forks",
+        "Open Issues",
+        "Last Commit",
+        "Description",
+    ]
+    .map(column);
+    let url_column = column("Repo URL");
+    let mut table = TABLE_HEADER.to_string();
+    for (i, record) in records.iter().enumerate() {
+        let [name, stars, forks, issues, last_commit, description] =
+            columns.map(|column| column.and_then(|c| record.get(c)).unwrap_or_default());
+        let name = table_cell(name, usize::MAX).replace(['[', ']'], "");
+        let name = match url_column.and_then(|c| record.get(c)) {
+            Some(url) if !url.is_empty() => format!("[{}]({})", name, url),
+            _ => name,
+        };
+        let _ = writeln!(
+            table,
+            "| {} | {} | {} | {} | {} | {} | {} |",
+            i + 1,
+            name,
+            stars,
+            forks,
+            issues,
+            last_commit,
+            table_cell(description, MAX_DESCRIPTION_CHARS)
+        );
+    }
+    table
+}
+
+/// Writes the processed rankings of `input_dir` as an "awesome list" in
+/// `output_dir`: a page per language in `docs/` and a README.md index linking
+/// them, both rendered from `templates`. Returns the number of pages written.
+pub fn render_docs(
+    input_dir: &Path,
+    output_dir: &Path,
+    templates: &DocTemplates,
+    delimiter: u8,
+) -> Result<usize> {
+    let docs_dir = output_dir.join(DOCS_DIR_NAME);
+    fs::create_dir_all(&docs_dir)
+        .with_context(|| format!("Failed to create directory: {:?}", docs_dir))?;
+    let date = Manifest::read(input_dir)
+        .map(|manifest| manifest.snapshot_date)
+        .unwrap_or_else(|_| Local::now().date_naive().to_string());
+    let display_names = Manifest::display_names(input_dir);
+
+    let mut languages = String::new();
+    let mut pages = 0;
+    for path in process::rankings(input_dir, delimiter)? {
+        let stem = path
+            .file_stem()
+            .expect("result files have a name")
+            .to_string_lossy()
+            .into_owned();
+        let name = match display_names.get(&stem) {
+            Some(name) => name.clone(),
+            None if stem == OVERALL_TOP_STEM => "Overall".to_string(),
+            None => stem.clone(),
+        };
+        let mut reader = delimited::reader(&path, delimiter)?;
+        let headers = reader.headers()?.clone();
+        let records = reader
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Failed to read a row of {:?}", path))?;
+
+        let page = templates
+            .page
+            .replace("{language}", &name)
+            .replace("{count}", &records.len().to_string())
+            .replace("{date}", &date)
+            .replace("{table}", &render_table(&headers, &records));
+        let page_path = docs_dir.join(format!("{}.md", stem));
+        fs::write(&page_path, page).with_context(|| format!("Failed to write {:?}", page_path))?;
+        pages += 1;
+        let _ = writeln!(
+            languages,
+            "- [{}]({}/{}.md) ({} repositories)",
+            name,
+            DOCS_DIR_NAME,
+            stem,
+            records.len()
+        );
+    }
+
+    let index = templates
+        .index
+        .replace("{date}", &date)
+        .replace("{languages}", &languages);
+    let index_path = output_dir.join("README.md");
+    fs::write(&index_path, index).with_context(|| format!("Failed to write {:?}", index_path))?;
+    info!(
+        "Wrote {} language page(s) and their index to {:?}",
+        pages, output_dir
+    );
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocTemplates, TABLE_HEADER, render_docs, render_row, table_cell};
+    use crate::Repo;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_table_cell_escapes_and_truncates() {
+        assert_eq!(table_cell("a | b\nc", 100), "a \\| b c");
+        assert_eq!(table_cell("abcdef", 3), "abc…");
+        assert_eq!(table_cell("abc", 3), "abc");
+    }
+
+    #[test]
+    fn test_render_row() {
+        let repo = Repo {
+            name: "rust".to_string(),
+            html_url: "https://github.com/rust-lang/rust".to_string(),
+            stargazers_count: 50000,
+            forks_count: 10000,
+            watchers_count: 50000,
+            language: Some("Rust".to_string()),
+            description: Some("Empowering everyone".to_string()),
+            open_issues_count: 5000,
+            created_at: "2010-06-16T20:39:03Z".to_string(),
+            pushed_at: "2024-05-01T10:00:00Z".to_string(),
+            size: 100000,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_row(1, &repo),
+            "| 1 | [rust](https://github.com/rust-lang/rust) | 50000 | 10000 | 5000 | 2024-05-01 | Empowering everyone |\n"
+        );
+        assert_eq!(TABLE_HEADER.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_render_docs_writes_pages_and_index() {
+        let temp_dir = tempdir().unwrap();
+        let processed = temp_dir.path().join("processed");
+        let list = temp_dir.path().join("list");
+        fs::create_dir(&processed).unwrap();
+        let ranking = "Ranking,Project Name,Stars,Forks,Open Issues,Last Commit,Description,Repo URL\n\
+                       1,fmt,300,20,5,26/02/2026,A | formatter,https://github.com/fmtlib/fmt\n";
+        fs::write(processed.join("CPP.csv"), ranking).unwrap();
+        fs::write(processed.join("top10_CPP.csv"), ranking).unwrap();
+        fs::write(
+            processed.join("manifest.json"),
+            r#"{"schema_version": 2, "snapshot_date": "2025-06-01", "preview_sizes": [10],
+                "languages": [{"name": "CPP", "display_name": "C++", "trending": false,
+                               "rows": 1, "columns": [], "file": "CPP.csv", "previews": {}}],
+                "overall_top": null}"#,
+        )
+        .unwrap();
+        let templates = DocTemplates {
+            page: "## {language} ({count}, {date})\n{table}".to_string(),
+            ..DocTemplates::default()
+        };
+
+        assert_eq!(render_docs(&processed, &list, &templates, b',').unwrap(), 1);
+
+        assert_eq!(
+            fs::read_to_string(list.join("docs/CPP.md")).unwrap(),
+            format!(
+                "## C++ (1, 2025-06-01)\n{}\
+                 | 1 | [fmt](https://github.com/fmtlib/fmt) | 300 | 20 | 5 | 26/02/2026 | A \\| formatter |\n",
+                TABLE_HEADER
+            )
+        );
+        let index = fs::read_to_string(list.join("README.md")).unwrap();
+        assert!(index.contains("as of 2025-06-01"));
+        assert!(index.contains("- [C++](docs/CPP.md) (1 repositories)\n"));
+        assert!(!list.join("docs/top10_CPP.md").exists());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{
+    Hygiene, License, Owner, OwnerType, PullRequestCounts, Release, Repo,
+    columns::Column,
+    delimited,
+    error::{KstarsError, Result},
+};
+use anyhow::Context;
+use std::{collections::HashSet, path::Path};
+
+/// Repository with nothing but its URL, filled in column by column.
+fn blank_repo(html_url: &str) -> Repo {
+    Repo {
+        html_url: html_url.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Owner of `repo`, created when its first column is read.
+fn owner(repo: &mut Repo) -> &mut Owner {
+    repo.owner.get_or_insert_with(|| Owner {
+        login: String::new(),
+        avatar_url: None,
+        kind: None,
+    })
+}
+
+/// Sets the field of `repo` written to `column`, undoing [`Column::value`].
+fn set_field(repo: &mut Repo, column: Column, value: &str) {
+    let text = || (!value.is_empty()).then(|| value.to_string());
+    let count = || value.parse().unwrap_or_default();
+    match column {
+        Column::Ranking | Column::RepoUrl => {}
+        Column::ProjectName => repo.name = value.to_string(),
+        Column::Stars => repo.stargazers_count = count(),
+        Column::Forks => repo.forks_count = count(),
+        Column::Watchers => repo.watchers_count = count(),
+        Column::OpenIssues => repo.open_issues_count = count(),
+        Column::CreatedAt => repo.created_at = value.to_string(),
+        Column::LastCommit => repo.pushed_at = value.to_string(),
+        Column::SizeKb => repo.size = count(),
+        Column::Description => repo.description = text(),
+        Column::Language => repo.language = text(),
+        Column::License => {
+            repo.license = text().map(|label| License {
+                key: label.to_lowercase(),
+                name: label.clone(),
+                spdx_id: Some(label),
+            })
+        }
+        Column::Topics => {
+            repo.topics = value
+                .split(';')
+                .filter(|t| !t.is_empty())
+                .map(String::from)
+                .collect()
+        }
+        Column::Owner => {
+            if let Some(login) = text() {
+                owner(repo).login = login;
+            }
+        }
+        Column::OwnerAvatar => {
+            if let Some(avatar) = text() {
+                owner(repo).avatar_url = Some(avatar);
+            }
+        }
+        Column::OwnerType => {
+            if let Some(kind) = OwnerType::parse(value) {
+                owner(repo).kind = Some(kind);
+            }
+        }
+        Column::Archived => repo.archived = value == "true",
+        Column::Fork => repo.fork = value == "true",
+        Column::DefaultBranch => repo.default_branch = text(),
+        Column::LatestRelease => {
+            if let Some(tag_name) = text() {
+                repo.details
+                    .latest_release
+                    .get_or_insert_with(|| Release {
+                        tag_name: String::new(),
+                        published_at: None,
+                    })
+                    .tag_name = tag_name;
+            }
+        }
+        Column::ReleaseDate => {
+            if let Some(release) = &mut repo.details.latest_release {
+                release.published_at = text();
+            }
+        }
+        Column::ReleaseCount => repo.details.release_count = value.parse().ok(),
+        Column::Commits52w => repo.details.commits_last_year = value.parse().ok(),
+        Column::ReadmeExcerpt => repo.details.readme_excerpt = text(),
+        Column::Subscribers => repo.details.subscribers_count = value.parse().ok(),
+        Column::ClosedIssues => repo.details.closed_issues = value.parse().ok(),
+        Column::IssueCloseRate => repo.details.issue_close_rate = value.parse().ok(),
+        Column::Sponsorable => repo.details.sponsorable = text().map(|s| s == "true"),
+        Column::SecurityPolicy | Column::Ci => {
+            if let Some(value) = text() {
+                let hygiene = repo.details.hygiene.get_or_insert(Hygiene {
+                    security_policy: false,
+                    ci: false,
+                });
+                match column {
+                    Column::SecurityPolicy => hygiene.security_policy = value == "true",
+                    _ => hygiene.ci = value == "true",
+                }
+            }
+        }
+        Column::OpenPullRequests | Column::MergedPullRequests => {
+            if let Ok(count) = value.parse() {
+                let counts = repo
+                    .details
+                    .pull_requests
+                    .get_or_insert(PullRequestCounts { open: 0, merged: 0 });
+                match column {
+                    Column::OpenPullRequests => counts.open = count,
+                    _ => counts.merged = count,
+                }
+            }
+        }
+    }
+}
+
+/// Reads the repositories of a delimited result file written by a fetch, in
+/// ranking order. Columns left out with `--columns` stay empty.
+pub fn read_repos(path: &Path, delimiter: u8) -> Result<Vec<Repo>> {
+    let mut reader = delimited::reader(path, delimiter)?;
+    let columns: Vec<Option<Column>> = reader
+        .headers()?
+        .iter()
+        .map(|header| Column::ALL.into_iter().find(|c| c.header() == header))
+        .collect();
+    let url_column = columns
+        .iter()
+        .position(|&c| c == Some(Column::RepoUrl))
+        .ok_or_else(|| KstarsError::SchemaMismatch {
+            path: path.to_path_buf(),
+            reason: "no \"Repo URL\" column".to_string(),
+        })?;
+
+    let mut repos = Vec::new();
+    for record in reader.records() {
+        let record = record.with_context(|| format!("Failed to read a row of {:?}", path))?;
+        let mut repo = blank_repo(record.get(url_column).unwrap_or_default());
+        // Release dates belong to the release, which has to be read first.
+        let mut cells: Vec<_> = columns.iter().zip(record.iter()).collect();
+        cells.sort_by_key(|(column, _)| **column == Some(Column::ReleaseDate));
+        for (column, value) in cells {
+            if let Some(column) = column {
+                set_field(&mut repo, *column, value);
+            }
+        }
+        repos.push(repo);
+    }
+    Ok(repos)
+}
+
+/// Combines previously written repositories with freshly fetched ones by URL. A
+/// repository in both keeps the fetched, fresher metrics; the result still has to
+/// be ranked.
+pub fn merge_repos(existing: Vec<Repo>, fetched: Vec<Repo>) -> Vec<Repo> {
+    let fetched_urls: HashSet<String> = fetched.iter().map(|r| r.html_url.clone()).collect();
+    let mut merged = fetched;
+    merged.extend(
+        existing
+            .into_iter()
+            .filter(|repo| !fetched_urls.contains(&repo.html_url)),
+    );
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_repos, read_repos};
+    use crate::{columns, sink};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_merge_keeps_fetched_metrics_and_old_repos() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Rust.csv");
+        fs::write(
+            &path,
+            "Ranking,Project Name,Stars,Repo URL,Topics,Owner,Latest Release,Release Date\n\
+             1,serde,900,https://github.com/serde-rs/serde,json;serde,serde-rs,v1.0.0,2025-01-01\n\
+             2,tokio,800,https://github.com/tokio-rs/tokio,,tokio-rs,,\n",
+        )
+        .unwrap();
+
+        let existing = read_repos(&path, b',').unwrap();
+        assert_eq!(existing[0].topics, ["json", "serde"]);
+        assert_eq!(existing[0].owner_login(), Some("serde-rs"));
+        let release = existing[0].details.latest_release.as_ref().unwrap();
+        assert_eq!(release.published_at.as_deref(), Some("2025-01-01"));
+        assert!(existing[1].details.latest_release.is_none());
+
+        let mut fresh = existing[1].clone();
+        fresh.stargazers_count = 1000;
+        let merged = merge_repos(existing, vec![fresh]);
+        let stars: Vec<_> = merged
+            .iter()
+            .map(|r| (r.name.as_str(), r.stargazers_count))
+            .collect();
+        assert_eq!(stars, [("tokio", 1000), ("serde", 900)]);
+
+        // What was read writes back the same cells.
+        let mut csv = sink::CsvSink::create(&path, b',', &columns::default_columns(&[])).unwrap();
+        sink::write_all(&mut csv, &merged).unwrap();
+        assert_eq!(
+            read_repos(&path, b',').unwrap()[1].topics,
+            ["json", "serde"]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    error::Result,
+    rate_limit::PauseStats,
+    report::{LanguageReport, LanguageStatus},
+    serve::respond,
+};
+use anyhow::Context;
+use reqwest::Client;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{io::AsyncReadExt, net::TcpListener};
+use tracing::{info, warn};
+
+/// Content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Job name the metrics are pushed under to a Pushgateway.
+const PUSHGATEWAY_JOB: &str = "kstars";
+
+/// Upper bounds, in seconds, of the buckets of the language duration histogram.
+const DURATION_BUCKETS: [f64; 8] = [10.0, 30.0, 60.0, 300.0, 600.0, 1800.0, 3600.0, 7200.0];
+
+#[derive(Default)]
+struct Counts {
+    api_calls: u64,
+    cache_hits: u64,
+    repos_fetched: u64,
+    /// Languages finished, by status name.
+    languages: BTreeMap<&'static str, u64>,
+    /// Languages whose duration fell in each bucket; the last one counts `+Inf`.
+    duration_buckets: [u64; DURATION_BUCKETS.len() + 1],
+    duration_sum: f64,
+    duration_count: u64,
+}
+
+/// Counters of fetch runs for monitoring, updated as each language finishes.
+/// Scheduled runs keep adding to the same counters.
+pub struct Metrics {
+    forge: String,
+    counts: Mutex<Counts>,
+    /// Rate limits hit by the runs, counted by their limiters.
+    pauses: Arc<PauseStats>,
+}
+
+impl Metrics {
+    pub fn new(forge: &str) -> Self {
+        Self {
+            forge: forge.to_string(),
+            counts: Mutex::new(Counts::default()),
+            pauses: Arc::default(),
+        }
+    }
+
+    /// Stats to hand to the rate limiters of the runs.
+    pub fn pause_stats(&self) -> Arc<PauseStats> {
+        Arc::clone(&self.pauses)
+    }
+
+    /// Adds the outcome of one language.
+    pub fn record(&self, report: &LanguageReport) {
+        let mut counts = self.counts.lock().expect("metrics lock is never poisoned");
+        counts.api_calls += report.api_calls as u64;
+        counts.cache_hits += report.cache_hits as u64;
+        counts.repos_fetched += report.records as u64;
+        *counts.languages.entry(report.status.name()).or_default() += 1;
+        if report.status == LanguageStatus::Skipped {
+            return;
+        }
+        let bucket = DURATION_BUCKETS
+            .iter()
+            .position(|&bound| report.duration_secs <= bound)
+            .unwrap_or(DURATION_BUCKETS.len());
+        counts.duration_buckets[bucket] += 1;
+        counts.duration_sum += report.duration_secs;
+        counts.duration_count += 1;
+    }
+
+    /// The metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let counts = self.counts.lock().expect("metrics lock is never poisoned");
+        let forge = format!("forge=\"{}\"", self.forge);
+        let mut out = String::new();
+        let mut counter = |name: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{}{{{}}} {}", name, forge, value);
+        };
+        counter(
+            "kstars_api_calls_total",
+            "Requests sent to the forge by finished languages.",
+            counts.api_calls,
+        );
+        counter(
+            "kstars_cache_hits_total",
+            "Search pages served from the page cache.",
+            counts.cache_hits,
+        );
+        counter(
+            "kstars_repos_fetched_total",
+            "Repositories in the written rankings.",
+            counts.repos_fetched,
+        );
+        counter(
+            "kstars_rate_limit_pauses_total",
+            "Rate limits hit by the run.",
+            self.pauses.pauses(),
+        );
+        counter(
+            "kstars_rate_limit_pause_seconds_total",
+            "Seconds the rate limits paused every request for.",
+            self.pauses.paused_secs(),
+        );
+
+        let name = "kstars_languages_total";
+        let _ = writeln!(out, "# HELP {} Languages finished, by status.", name);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        for status in [
+            LanguageStatus::Completed,
+            LanguageStatus::Failed,
+            LanguageStatus::Interrupted,
+            LanguageStatus::Skipped,
+        ] {
+            let value = counts.languages.get(status.name()).copied().unwrap_or(0);
+            let _ = writeln!(
+                out,
+                "{}{{{},status=\"{}\"}} {}",
+                name,
+                forge,
+                status.name(),
+                value
+            );
+        }
+
+        let name = "kstars_language_duration_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {} Time taken to fetch and write a language.",
+            name
+        );
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (i, count) in counts.duration_buckets.iter().enumerate() {
+            cumulative += count;
+            let bound = DURATION_BUCKETS
+                .get(i)
+                .map_or("+Inf".to_string(), |bound| bound.to_string());
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, forge, bound, cumulative
+            );
+        }
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, forge, counts.duration_sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, forge, counts.duration_count);
+        out
+    }
+}
+
+/// Answers `GET /metrics` on `addr` with the output of `render` until the process
+/// exits, so Prometheus can scrape a run while it is going.
+pub async fn serve<F>(addr: SocketAddr, render: F) -> Result<()>
+where
+    F: Fn() -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen for metrics on {}", addr))?;
+    info!("Serving metrics at http://{}/metrics", addr);
+    let render = Arc::new(render);
+    tokio::spawn(async move {
+        loop {
+            let mut stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Failed to accept a metrics connection: {}", e);
+                    continue;
+                }
+            };
+            let render = Arc::clone(&render);
+            tokio::spawn(async move {
+                let mut buffer = vec![0; 4096];
+                let read = stream.read(&mut buffer).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buffer[..read]);
+                let target = request.lines().next().unwrap_or_default();
+                let result = if target.starts_with("GET /metrics") {
+                    respond(&mut stream, "200 OK", CONTENT_TYPE, render().as_bytes()).await
+                } else {
+                    respond(&mut stream, "404 Not Found", "text/plain", b"Not found").await
+                };
+                if let Err(e) = result {
+                    warn!("Failed to answer a metrics request: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Replaces the metrics of the `kstars` job on the Pushgateway at `url`.
+pub async fn push(client: &Client, url: &str, metrics: String) -> Result<()> {
+    let url = format!(
+        "{}/metrics/job/{}",
+        url.trim_end_matches('/'),
+        PUSHGATEWAY_JOB
+    );
+    client
+        .put(&url)
+        .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE)
+        .body(metrics)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failed to push metrics to {}", url))?;
+    info!("Metrics pushed to {}", url);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+    use crate::report::{LanguageReport, LanguageStatus};
+
+    #[test]
+    fn test_render_counts_languages() {
+        let metrics = Metrics::new("github");
+        let mut rust = LanguageReport::new("Rust", "Rust");
+        rust.status = LanguageStatus::Completed;
+        rust.records = 100;
+        rust.api_calls = 4;
+        rust.duration_secs = 42.0;
+        metrics.record(&rust);
+        let mut go = LanguageReport::new("Go", "Go");
+        go.status = LanguageStatus::Skipped;
+        metrics.record(&go);
+
+        let text = metrics.render();
+        assert!(text.contains("kstars_api_calls_total{forge=\"github\"} 4\n"));
+        assert!(text.contains("kstars_repos_fetched_total{forge=\"github\"} 100\n"));
+        assert!(text.contains("kstars_languages_total{forge=\"github\",status=\"skipped\"} 1\n"));
+        assert!(
+            text.contains(
+                "kstars_language_duration_seconds_bucket{forge=\"github\",le=\"30\"} 0\n"
+            )
+        );
+        assert!(
+            text.contains(
+                "kstars_language_duration_seconds_bucket{forge=\"github\",le=\"60\"} 1\n"
+            )
+        );
+        assert!(text.contains("kstars_language_duration_seconds_count{forge=\"github\"} 1\n"));
+        assert!(text.contains("kstars_rate_limit_pauses_total{forge=\"github\"} 0\n"));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    alerts::Alert,
+    error::Result,
+    report::{LanguageStatus, RunReport},
+};
+use anyhow::Context;
+use reqwest::Client;
+use serde_json::{Value, json};
+use tracing::info;
+
+/// Kind of service behind a webhook URL, which decides the shape of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WebhookKind {
+    /// Slack incoming webhooks expect a `text` field.
+    Slack,
+    /// Discord webhooks expect a `content` field.
+    Discord,
+    /// Anything else gets the summary and the full run report.
+    Generic,
+}
+
+impl WebhookKind {
+    fn from_url(url: &str) -> Self {
+        let host = url
+            .split("://")
+            .nth(1)
+            .unwrap_or(url)
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default();
+        if host == "hooks.slack.com" {
+            WebhookKind::Slack
+        } else if host == "discord.com" || host == "discordapp.com" {
+            WebhookKind::Discord
+        } else {
+            WebhookKind::Generic
+        }
+    }
+}
+
+/// Overall outcome of a run: failed if any language failed, interrupted if it was
+/// stopped before every language was fetched.
+fn outcome(report: &RunReport) -> &'static str {
+    if report.totals.failed > 0 {
+        "failed"
+    } else if report.totals.interrupted > 0 {
+        "interrupted"
+    } else {
+        "succeeded"
+    }
+}
+
+/// One-line summary of a run, e.g. "kstars run failed: 10/12 languages completed,
+/// 1 failed (Go), 9800 repositories in 12m 3s."
+fn summary(report: &RunReport) -> String {
+    let totals = &report.totals;
+    let mut text = format!(
+        "kstars run {}: {}/{} languages completed",
+        outcome(report),
+        totals.completed + totals.skipped,
+        totals.languages
+    );
+    if totals.failed > 0 {
+        let failed: Vec<&str> = report
+            .languages
+            .iter()
+            .filter(|language| language.status == LanguageStatus::Failed)
+            .map(|language| language.display_name.as_str())
+            .collect();
+        text.push_str(&format!(
+            ", {} failed ({})",
+            totals.failed,
+            failed.join(", ")
+        ));
+    }
+    if totals.interrupted > 0 {
+        text.push_str(&format!(", {} interrupted", totals.interrupted));
+    }
+    let secs = report.duration_secs.round() as u64;
+    text.push_str(&format!(
+        ", {} repositories in {}m {}s.",
+        totals.records,
+        secs / 60,
+        secs % 60
+    ));
+    text
+}
+
+fn payload(kind: WebhookKind, report: &RunReport) -> Value {
+    match kind {
+        WebhookKind::Slack => json!({ "text": summary(report) }),
+        WebhookKind::Discord => json!({ "content": summary(report) }),
+        WebhookKind::Generic => json!({
+            "status": outcome(report),
+            "summary": summary(report),
+            "report": report,
+        }),
+    }
+}
+
+/// Text of the alerts of a diff, one per line.
+fn alerts_summary(alerts: &[Alert]) -> String {
+    let mut text = format!("kstars: {} ranking alert(s)", alerts.len());
+    for alert in alerts {
+        text.push_str("\n- ");
+        text.push_str(&alert.message());
+    }
+    text
+}
+
+fn alerts_payload(kind: WebhookKind, alerts: &[Alert]) -> Value {
+    match kind {
+        WebhookKind::Slack => json!({ "text": alerts_summary(alerts) }),
+        WebhookKind::Discord => json!({ "content": alerts_summary(alerts) }),
+        WebhookKind::Generic => json!({
+            "summary": alerts_summary(alerts),
+            "alerts": alerts,
+        }),
+    }
+}
+
+async fn post(client: &Client, url: &str, payload: &Value) -> Result<()> {
+    client
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .context("Failed to notify the webhook")?;
+    Ok(())
+}
+
+/// Posts the summary of a finished run to the webhook at `url`.
+pub async fn send(client: &Client, url: &str, report: &RunReport) -> Result<()> {
+    let kind = WebhookKind::from_url(url);
+    post(client, url, &payload(kind, report)).await?;
+    info!("Run summary sent to the {:?} webhook.", kind);
+    Ok(())
+}
+
+/// Posts the alerts of a diff to the webhook at `url`.
+pub async fn send_alerts(client: &Client, url: &str, alerts: &[Alert]) -> Result<()> {
+    let kind = WebhookKind::from_url(url);
+    post(client, url, &alerts_payload(kind, alerts)).await?;
+    info!("{} alert(s) sent to the {:?} webhook.", alerts.len(), kind);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WebhookKind, payload};
+    use crate::report::{LanguageReport, LanguageStatus, RunReport};
+    use std::time::Duration;
+
+    #[test]
+    fn test_payload_follows_webhook_kind() {
+        let mut rust = LanguageReport::new("Rust", "Rust");
+        rust.status = LanguageStatus::Completed;
+        rust.records = 100;
+        let go = LanguageReport::new("Go", "Go");
+        let report = RunReport::new(
+            "2024-05-01T00:00:00Z".to_string(),
+            Duration::from_secs(125),
+            vec![rust, go],
+        );
+
+        assert_eq!(
+            WebhookKind::from_url("https://hooks.slack.com/services/T0/B0/x"),
+            WebhookKind::Slack
+        );
+        assert_eq!(
+            WebhookKind::from_url("https://discord.com/api/webhooks/1/x"),
+            WebhookKind::Discord
+        );
+        let slack = payload(WebhookKind::Slack, &report);
+        assert_eq!(
+            slack["text"],
+            "kstars run failed: 1/2 languages completed, 1 failed (Go), 100 repositories in 2m 5s."
+        );
+        let generic = payload(WebhookKind::from_url("http://localhost:8080/hook"), &report);
+        assert_eq!(generic["status"], "failed");
+        assert_eq!(generic["report"]["totals"]["records"], 100);
+    }
+}
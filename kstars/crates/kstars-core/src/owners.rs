@@ -0,0 +1,164 @@
+use crate::{delimited, error::Result};
+use anyhow::Context;
+use csv::StringRecord;
+use reqwest::Url;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+/// Stem of the leaderboard of owners written next to the processed rankings.
+pub const OWNERS_STEM: &str = "owners";
+
+/// Header of the leaderboard of owners.
+pub const OWNERS_HEADER: [&str; 7] = [
+    "Ranking",
+    "Owner",
+    "Owner Type",
+    "Owner Avatar",
+    "Entries",
+    "Languages",
+    "Stars",
+];
+
+/// What one owner adds up to over the rankings.
+#[derive(Default)]
+struct Tally {
+    owner_type: String,
+    avatar: String,
+    entries: usize,
+    languages: BTreeSet<String>,
+    stars: u64,
+    /// Repositories whose stars were counted, so that repositories ranked in several
+    /// languages count once.
+    counted: HashSet<String>,
+}
+
+/// Owner of a repository, from its URL when the ranking has no "Owner" column.
+fn owner_of(record: &StringRecord, owner: Option<usize>, url: Option<usize>) -> Option<String> {
+    if let Some(login) = owner.and_then(|i| record.get(i)).filter(|o| !o.is_empty()) {
+        return Some(login.to_string());
+    }
+    let url = Url::parse(url.and_then(|i| record.get(i))?).ok()?;
+    url.path_segments()?
+        .next()
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+}
+
+/// Ranks the owners of the repositories in the language rankings `files` by their
+/// entries across the rankings, then by the stars of their distinct repositories,
+/// and writes them to `output`. Returns the number of owners written.
+pub fn write_owners(files: &[PathBuf], output: &Path, delimiter: u8) -> Result<usize> {
+    let mut tallies: HashMap<String, Tally> = HashMap::new();
+    for path in files {
+        let mut reader = delimited::reader(path, delimiter)?;
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let (owner_column, url_column) = (column("Owner"), column("Repo URL"));
+        if owner_column.is_none() && url_column.is_none() {
+            warn!(
+                "Leaving {:?} out of the owners: it has no \"Owner\" or \"Repo URL\" column.",
+                path
+            );
+            continue;
+        }
+        let (type_column, avatar_column, stars_column) = (
+            column("Owner Type"),
+            column("Owner Avatar"),
+            column("Stars"),
+        );
+        let language = path
+            .file_stem()
+            .expect("result files have a name")
+            .to_string_lossy()
+            .into_owned();
+        for record in reader.records() {
+            let record = record.with_context(|| format!("Failed to read a row of {:?}", path))?;
+            let Some(owner) = owner_of(&record, owner_column, url_column) else {
+                continue;
+            };
+            let tally = tallies.entry(owner).or_default();
+            tally.entries += 1;
+            tally.languages.insert(language.clone());
+            let get =
+                |column: Option<usize>| column.and_then(|i| record.get(i)).unwrap_or_default();
+            if tally.owner_type.is_empty() {
+                tally.owner_type = get(type_column).to_string();
+            }
+            if tally.avatar.is_empty() {
+                tally.avatar = get(avatar_column).to_string();
+            }
+            let url = get(url_column);
+            if url.is_empty() || tally.counted.insert(url.to_string()) {
+                tally.stars += get(stars_column).parse::<u64>().unwrap_or(0);
+            }
+        }
+    }
+
+    let mut owners: Vec<(String, Tally)> = tallies.into_iter().collect();
+    owners.sort_by(|(a, a_tally), (b, b_tally)| {
+        Reverse((a_tally.entries, a_tally.stars))
+            .cmp(&Reverse((b_tally.entries, b_tally.stars)))
+            .then_with(|| a.cmp(b))
+    });
+    let mut writer = delimited::writer(output, delimiter)?;
+    writer.write_record(OWNERS_HEADER)?;
+    for (i, (owner, tally)) in owners.iter().enumerate() {
+        writer.write_record([
+            (i + 1).to_string(),
+            owner.clone(),
+            tally.owner_type.clone(),
+            tally.avatar.clone(),
+            tally.entries.to_string(),
+            tally.languages.len().to_string(),
+            tally.stars.to_string(),
+        ])?;
+    }
+    delimited::commit(writer)?;
+    info!("Ranked {} owners in {:?}", owners.len(), output);
+    Ok(owners.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_owners;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_owners_ranks_by_entries_then_stars() {
+        let temp_dir = tempdir().unwrap();
+        let rust = temp_dir.path().join("Rust.csv");
+        let go = temp_dir.path().join("Go.csv");
+        fs::write(
+            &rust,
+            "Ranking,Stars,Repo URL,Owner,Owner Type\n\
+             1,500,https://github.com/solo/big,solo,user\n\
+             2,300,https://github.com/acme/a,acme,organization\n\
+             3,100,https://github.com/tiny/x,tiny,user\n",
+        )
+        .unwrap();
+        // Without an "Owner" column, owners are read from the URLs; "acme/a" is
+        // ranked in both languages but its stars count once.
+        fs::write(
+            &go,
+            "Ranking,Stars,Repo URL\n\
+             1,300,https://github.com/acme/a\n\
+             2,50,https://github.com/acme/b\n",
+        )
+        .unwrap();
+        let output = temp_dir.path().join("owners.csv");
+
+        assert_eq!(write_owners(&[go, rust], &output, b',').unwrap(), 3);
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "Ranking,Owner,Owner Type,Owner Avatar,Entries,Languages,Stars\n\
+             1,acme,organization,,3,2,350\n\
+             2,solo,user,,1,1,500\n\
+             3,tiny,user,,1,1,100\n"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{OwnerType, Repo, atomic::AtomicFile, error::Result, sink::OutputSink};
+use anyhow::Context;
+use arrow_array::{
+    ArrayRef, BooleanArray, RecordBatch, StringArray, TimestampSecondArray, UInt64Array,
+    builder::{ListBuilder, StringBuilder},
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::info;
+
+/// Columns of the Parquet files, typed instead of the CSV's strings.
+fn schema() -> Schema {
+    let timestamp = DataType::Timestamp(TimeUnit::Second, Some("UTC".into()));
+    Schema::new(vec![
+        Field::new("ranking", DataType::UInt64, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("stars", DataType::UInt64, false),
+        Field::new("forks", DataType::UInt64, false),
+        Field::new("watchers", DataType::UInt64, false),
+        Field::new("open_issues", DataType::UInt64, false),
+        Field::new("created_at", timestamp.clone(), true),
+        Field::new("last_commit", timestamp, true),
+        Field::new("size_kb", DataType::UInt64, false),
+        Field::new("description", DataType::Utf8, true),
+        Field::new("language", DataType::Utf8, true),
+        Field::new("url", DataType::Utf8, false),
+        Field::new("license", DataType::Utf8, true),
+        Field::new(
+            "topics",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            false,
+        ),
+        Field::new("owner", DataType::Utf8, true),
+        Field::new("archived", DataType::Boolean, false),
+        Field::new("fork", DataType::Boolean, false),
+        Field::new("default_branch", DataType::Utf8, true),
+        Field::new("owner_avatar", DataType::Utf8, true),
+        Field::new("owner_type", DataType::Utf8, true),
+    ])
+}
+
+/// Unix seconds of an RFC 3339 timestamp; `None` if it cannot be parsed.
+fn unix_seconds(value: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|date| date.timestamp())
+}
+
+/// Arranges the repositories into a single record batch.
+fn record_batch(repos: &[Repo]) -> Result<RecordBatch> {
+    let counts = |value: fn(&Repo) -> u64| -> ArrayRef {
+        Arc::new(repos.iter().map(value).collect::<UInt64Array>())
+    };
+    let timestamps = |value: fn(&Repo) -> &str| -> ArrayRef {
+        Arc::new(
+            repos
+                .iter()
+                .map(|r| unix_seconds(value(r)))
+                .collect::<TimestampSecondArray>()
+                .with_timezone("UTC"),
+        )
+    };
+    let flags = |value: fn(&Repo) -> bool| -> ArrayRef {
+        Arc::new(
+            repos
+                .iter()
+                .map(|r| Some(value(r)))
+                .collect::<BooleanArray>(),
+        )
+    };
+    let mut topics = ListBuilder::new(StringBuilder::new());
+    for repo in repos {
+        topics.append_value(repo.topics.iter().map(Some));
+    }
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new((1..=repos.len() as u64).collect::<UInt64Array>()),
+        Arc::new(StringArray::from_iter_values(repos.iter().map(|r| &r.name))),
+        counts(|r| r.stargazers_count),
+        counts(|r| r.forks_count),
+        counts(|r| r.watchers_count),
+        counts(|r| r.open_issues_count),
+        timestamps(|r| &r.created_at),
+        timestamps(|r| &r.pushed_at),
+        counts(|r| r.size),
+        Arc::new(StringArray::from_iter(
+            repos.iter().map(|r| r.description.as_deref()),
+        )),
+        Arc::new(StringArray::from_iter(
+            repos.iter().map(|r| r.language.as_deref()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            repos.iter().map(|r| &r.html_url),
+        )),
+        Arc::new(StringArray::from_iter(
+            repos.iter().map(|r| r.license.as_ref().map(|l| l.label())),
+        )),
+        Arc::new(topics.finish()),
+        Arc::new(StringArray::from_iter(
+            repos.iter().map(|r| r.owner_login()),
+        )),
+        flags(|r| r.archived),
+        flags(|r| r.fork),
+        Arc::new(StringArray::from_iter(
+            repos.iter().map(|r| r.default_branch.as_deref()),
+        )),
+        Arc::new(StringArray::from_iter(
+            repos.iter().map(|r| r.owner_avatar_url()),
+        )),
+        Arc::new(StringArray::from_iter(
+            repos.iter().map(|r| r.owner_type().map(OwnerType::name)),
+        )),
+    ];
+    Ok(
+        RecordBatch::try_new(Arc::new(schema()), columns)
+            .context("Failed to build record batch")?,
+    )
+}
+
+/// Writes the repositories to a Snappy-compressed Parquet file.
+fn write_repos_to_parquet(path: &Path, repos: &[Repo]) -> Result<()> {
+    info!(
+        "Writing {} repositories to Parquet: {:?}",
+        repos.len(),
+        path
+    );
+    let batch = record_batch(repos)?;
+    let file = AtomicFile::create(path)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.into_inner()?.commit()?;
+    info!("Parquet file written successfully.");
+    Ok(())
+}
+
+/// Parquet file; columns are built from all repositories at once, so they are
+/// collected until `finish`.
+pub struct ParquetSink {
+    path: PathBuf,
+    repos: Vec<Repo>,
+}
+
+impl ParquetSink {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            repos: Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for ParquetSink {
+    fn write_repo(&mut self, _ranking: usize, repo: &Repo) -> Result<()> {
+        self.repos.push(repo.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        write_repos_to_parquet(&self.path, &self.repos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{record_batch, unix_seconds};
+    use crate::Repo;
+    use arrow_array::{Array, ListArray, TimestampSecondArray, UInt64Array};
+
+    #[test]
+    fn test_record_batch_has_typed_columns() {
+        let repo = Repo {
+            name: "rust".to_string(),
+            html_url: "https://github.com/rust-lang/rust".to_string(),
+            stargazers_count: 50000,
+            forks_count: 10000,
+            watchers_count: 50000,
+            language: Some("Rust".to_string()),
+            open_issues_count: 5000,
+            created_at: "2010-06-16T20:39:03Z".to_string(),
+            pushed_at: "not a date".to_string(),
+            size: 100000,
+            topics: vec!["compiler".to_string(), "language".to_string()],
+            ..Default::default()
+        };
+
+        let batch = record_batch(&[repo]).unwrap();
+
+        assert_eq!(batch.num_rows(), 1);
+        let stars = batch
+            .column_by_name("stars")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .unwrap();
+        assert_eq!(stars.value(0), 50000);
+        let created = batch
+            .column_by_name("created_at")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampSecondArray>()
+            .unwrap();
+        assert_eq!(Some(created.value(0)), unix_seconds("2010-06-16T20:39:03Z"));
+        assert!(batch.column_by_name("last_commit").unwrap().is_null(0));
+        assert!(batch.column_by_name("description").unwrap().is_null(0));
+        let topics = batch
+            .column_by_name("topics")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        assert_eq!(topics.value_length(0), 2);
+    }
+}
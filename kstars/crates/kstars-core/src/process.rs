@@ -0,0 +1,1023 @@
+use crate::{
+    delimited,
+    diff::{self, RankedRepo},
+    error::{Result, bail},
+    filters::TRENDING_PREFIX,
+    health::{self, HealthInputs, HealthWeights},
+    linguist,
+    manifest::{DatasetEntry, Manifest, SCHEMA_VERSION},
+    owners::{self, OWNERS_STEM},
+};
+use anyhow::Context;
+use chrono::{
+    NaiveDate, SecondsFormat, Utc,
+    format::{Item, StrftimeItems},
+};
+use chrono_tz::Tz;
+use csv::StringRecord;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+/// File stem of the ranking that merges every language.
+pub const OVERALL_TOP_STEM: &str = "overall_top";
+
+/// Number of repositories in the overall ranking.
+const OVERALL_TOP_RECORDS: usize = 1000;
+
+/// Rows of the preview written next to each processed ranking when no sizes are
+/// given; the home page of the website shows it.
+pub const DEFAULT_PREVIEW_SIZE: usize = 10;
+
+/// Days between a snapshot and the previous one its movement is measured against.
+pub const DELTA_DAYS: u64 = 7;
+
+/// Positions a repository gained since the snapshot a week before, or "new".
+const RANK_DELTA_COLUMN: &str = "Rank Δ";
+
+/// Stars a repository gained since the snapshot a week before.
+const STARS_DELTA_COLUMN: &str = "Stars Δ (7d)";
+
+/// Average stars a repository gained per day since it was created.
+const STARS_PER_DAY_COLUMN: &str = "Stars/Day";
+
+/// Stars a repository gained since the last snapshot.
+const STARS_GAINED_COLUMN: &str = "Stars Gained";
+
+/// Years since a repository was created.
+const AGE_COLUMN: &str = "Age (Years)";
+
+/// Days since the last push to a repository.
+const DAYS_SINCE_COMMIT_COLUMN: &str = "Days Since Last Commit";
+
+/// Header of the column holding the health score of each row, see [`health::score`].
+pub const HEALTH_SCORE_COLUMN: &str = "Health Score";
+
+/// Header of the column holding the Linguist color of the language of each row.
+pub const LANGUAGE_COLOR_COLUMN: &str = "Language Color";
+
+/// Columns holding timestamps, which are written with the [`DateStyle`].
+const DATE_COLUMNS: [&str; 2] = ["Created At", "Last Commit"];
+
+/// Suffix of the columns that keep each date as a UTC RFC 3339 timestamp, e.g.
+/// "Created At (ISO)", so the rankings can still be sorted by it.
+pub const ISO_SUFFIX: &str = " (ISO)";
+
+/// strftime format of the dates of processed rankings when none is given.
+pub const DEFAULT_DATE_FORMAT: &str = "%d/%m/%Y";
+
+/// How processed rankings write their dates.
+#[derive(Debug, Clone)]
+pub struct DateStyle {
+    /// strftime format, e.g. "%d/%m/%Y".
+    pub format: String,
+    /// Time zone the timestamps are converted to before formatting.
+    pub timezone: Tz,
+}
+
+impl Default for DateStyle {
+    fn default() -> Self {
+        Self {
+            format: DEFAULT_DATE_FORMAT.to_string(),
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+/// Checks a `--date-format`: a strftime format.
+pub fn parse_date_format(format: &str) -> Result<String, String> {
+    if format.is_empty() || StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(format!("invalid strftime format {:?}", format));
+    }
+    Ok(format.to_string())
+}
+
+/// Parses a `--timezone`: an IANA name such as "Europe/Amsterdam", or "UTC".
+pub fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.parse()
+        .map_err(|_| format!("unknown time zone {:?}, e.g. \"Europe/Amsterdam\"", name))
+}
+
+/// What the derived columns of processed rankings are computed against.
+pub struct Baselines {
+    /// Day the rankings were fetched; "Stars/Day" counts the days up to it.
+    pub as_of: NaiveDate,
+    /// Snapshot a week older, for "Rank Δ" and "Stars Δ (7d)".
+    pub week_before: Option<PathBuf>,
+    /// Most recent older snapshot, for "Stars Gained".
+    pub last: Option<PathBuf>,
+}
+
+/// Converts a size in KB into a human-readable string, e.g. "16.25 MB".
+pub fn human_readable_size(size_kb: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut size = size_kb as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2} {}", size, UNITS[unit])
+}
+
+/// Rewrites an RFC 3339 timestamp in the time zone and format of `style`, by
+/// default "dd/mm/YYYY". Values that are not timestamps, e.g. dates that were
+/// already converted, are returned unchanged.
+pub fn format_date(value: &str, style: &DateStyle) -> String {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|date| {
+            date.with_timezone(&style.timezone)
+                .format(&style.format)
+                .to_string()
+        })
+        .unwrap_or_else(|_| value.to_string())
+}
+
+/// Normalizes an RFC 3339 timestamp to UTC, e.g. "2015-10-16T08:00:00Z"; empty for
+/// values that are not timestamps.
+fn iso_date(value: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|date| {
+            date.with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::Secs, true)
+        })
+        .unwrap_or_default()
+}
+
+/// Movement columns of one row against the `previous` ranking: positions gained
+/// ("new" for entrants) and stars gained.
+fn delta_values(
+    previous: &HashMap<&str, &RankedRepo>,
+    url: &str,
+    rank: usize,
+    stars: Option<u64>,
+) -> [String; 2] {
+    let Some(before) = previous.get(url) else {
+        return ["new".to_string(), String::new()];
+    };
+    [
+        (before.rank as i64 - rank as i64).to_string(),
+        stars_gained(previous, url, stars),
+    ]
+}
+
+/// Stars gained since the `previous` ranking; empty for entrants.
+fn stars_gained(previous: &HashMap<&str, &RankedRepo>, url: &str, stars: Option<u64>) -> String {
+    previous
+        .get(url)
+        .and_then(|before| stars.zip(before.stars))
+        .map(|(now, then)| (now as i64 - then as i64).to_string())
+        .unwrap_or_default()
+}
+
+/// Average stars per day from `created_at` to `as_of`, e.g. "12.50". Repositories
+/// created that day count as one day old.
+fn stars_per_day(stars: u64, created_at: &str, as_of: NaiveDate) -> Option<String> {
+    let created = chrono::DateTime::parse_from_rfc3339(created_at).ok()?;
+    let days = (as_of - created.date_naive()).num_days().max(1);
+    Some(format!("{:.2}", stars as f64 / days as f64))
+}
+
+/// What the health score of `record` is computed from, given the raw columns it is
+/// read from.
+fn health_inputs(record: &StringRecord, columns: &HealthColumns, as_of: NaiveDate) -> HealthInputs {
+    let get = |column: Option<usize>| column.and_then(|i| record.get(i));
+    let number = |column: Option<usize>| get(column).and_then(|v| v.parse::<f64>().ok());
+    let age_days = get(columns.created)
+        .and_then(|created_at| days_until(created_at, as_of))
+        .map(|days| days as f64);
+    HealthInputs {
+        days_since_commit: get(columns.pushed).and_then(|pushed| days_until(pushed, as_of)),
+        issue_close_rate: number(columns.issue_close_rate),
+        // Younger repositories count as a month old, lest one release look like many.
+        releases_per_year: number(columns.releases)
+            .zip(age_days)
+            .map(|(releases, days)| releases * 365.25 / days.max(30.0)),
+        stars_per_day: number(columns.stars)
+            .zip(age_days)
+            .map(|(stars, days)| stars / days.max(1.0)),
+    }
+}
+
+/// Raw columns the health score is computed from.
+struct HealthColumns {
+    pushed: Option<usize>,
+    created: Option<usize>,
+    stars: Option<usize>,
+    issue_close_rate: Option<usize>,
+    releases: Option<usize>,
+}
+
+impl HealthColumns {
+    fn any(&self) -> bool {
+        self.pushed.is_some()
+            || self.issue_close_rate.is_some()
+            || self.created.is_some() && (self.stars.is_some() || self.releases.is_some())
+    }
+}
+
+/// Whole days from an RFC 3339 timestamp to `as_of`; timestamps after it count as 0.
+fn days_until(timestamp: &str, as_of: NaiveDate) -> Option<i64> {
+    let date = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    Some((as_of - date.date_naive()).num_days().max(0))
+}
+
+/// Raw columns the processed ones are read from, with their processed header.
+/// GitHub's search reports stars as watchers: a "Subscribers" column of the
+/// `subscribers` enrichment takes the place of "Watchers", and a "Watchers" column
+/// that only mirrors "Stars" is left out.
+fn source_columns<'a>(
+    headers: &'a StringRecord,
+    records: &[StringRecord],
+) -> Vec<(usize, &'a str)> {
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let subscribers = column("Subscribers");
+    let watchers = column("Watchers");
+    let mirrored = watchers.zip(column("Stars")).is_some_and(|(w, s)| {
+        !records.is_empty() && records.iter().all(|record| record.get(w) == record.get(s))
+    });
+    headers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, header)| match header {
+            "Size (KB)" => Some((i, "Size")),
+            "Watchers" => match subscribers {
+                Some(subscribers) => Some((subscribers, header)),
+                None => (!mirrored).then_some((i, header)),
+            },
+            "Subscribers" if watchers.is_some() => None,
+            "Subscribers" => Some((i, "Watchers")),
+            _ => Some((i, header)),
+        })
+        .collect()
+}
+
+/// Indexes a ranking by repository URL.
+fn by_url(ranking: &[RankedRepo]) -> HashMap<&str, &RankedRepo> {
+    ranking.iter().map(|r| (r.url.as_str(), r)).collect()
+}
+
+/// Converts one raw result file into the processed schema: dates are written with
+/// `dates`, each also kept in an " (ISO)" column at the end to sort by, and the
+/// "Size (KB)" column is replaced by a human-readable "Size" column. "Watchers" hold
+/// the real watchers, see [`source_columns`]. A "Stars/Day" column averages the stars
+/// since creation up to `as_of`, and "Age (Years)" and "Days Since Last Commit" columns
+/// count up to it too. A "Health Score" column weighs the known parts of the score with
+/// `health`, and a "Language Color" column follows a "Language" one. Given the ranking
+/// of the language a week before, "Rank Δ" and "Stars Δ (7d)" columns are appended, and
+/// given the last ranking, a "Stars Gained" column. Returns the number of rows written.
+#[allow(clippy::too_many_arguments)]
+pub fn process_file(
+    input: &Path,
+    output: &Path,
+    delimiter: u8,
+    dates: &DateStyle,
+    health: &HealthWeights,
+    as_of: NaiveDate,
+    week_before: Option<&[RankedRepo]>,
+    last: Option<&[RankedRepo]>,
+) -> Result<usize> {
+    let mut reader = delimited::reader(input, delimiter)?;
+    let headers = reader.headers()?.clone();
+    let records = reader
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read a row of {:?}", input))?;
+    let sources = source_columns(&headers, &records);
+    let date_columns: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .filter(|(_, h)| DATE_COLUMNS.contains(h))
+        .map(|(i, _)| i)
+        .collect();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let size_column = column("Size (KB)");
+    let url_column = column("Repo URL");
+    let stars_column = column("Stars");
+    let created_column = column("Created At").filter(|_| stars_column.is_some());
+    let age_column = column("Created At");
+    let pushed_column = column("Last Commit");
+    let language_column = column("Language");
+    let health_columns = HealthColumns {
+        pushed: pushed_column,
+        created: age_column,
+        stars: stars_column,
+        issue_close_rate: column("Issue Close Rate"),
+        releases: column("Releases"),
+    };
+    let has_health = health_columns.any();
+    if url_column.is_none() && (week_before.is_some() || last.is_some()) {
+        warn!(
+            "{:?} has no \"Repo URL\" column; leaving out its movement.",
+            input
+        );
+    }
+    let week_before = week_before.filter(|_| url_column.is_some()).map(by_url);
+    let last = last.filter(|_| url_column.is_some()).map(by_url);
+
+    let mut writer = delimited::writer(output, delimiter)?;
+    let mut out_headers: StringRecord = sources.iter().map(|&(_, header)| header).collect();
+    for &i in &date_columns {
+        out_headers.push_field(&format!("{}{}", &headers[i], ISO_SUFFIX));
+    }
+    if created_column.is_some() {
+        out_headers.push_field(STARS_PER_DAY_COLUMN);
+    }
+    if age_column.is_some() {
+        out_headers.push_field(AGE_COLUMN);
+    }
+    if pushed_column.is_some() {
+        out_headers.push_field(DAYS_SINCE_COMMIT_COLUMN);
+    }
+    if has_health {
+        out_headers.push_field(HEALTH_SCORE_COLUMN);
+    }
+    if language_column.is_some() {
+        out_headers.push_field(LANGUAGE_COLOR_COLUMN);
+    }
+    if week_before.is_some() {
+        out_headers.extend([RANK_DELTA_COLUMN, STARS_DELTA_COLUMN]);
+    }
+    if last.is_some() {
+        out_headers.push_field(STARS_GAINED_COLUMN);
+    }
+    writer.write_record(&out_headers)?;
+
+    let mut rows = 0;
+    for (i, record) in records.iter().enumerate() {
+        let mut row: StringRecord = sources
+            .iter()
+            .map(|&(j, _)| {
+                let value = record.get(j).unwrap_or_default();
+                if date_columns.contains(&j) {
+                    format_date(value, dates)
+                } else if Some(j) == size_column {
+                    value
+                        .parse()
+                        .map(human_readable_size)
+                        .unwrap_or_else(|_| value.to_string())
+                } else {
+                    value.to_string()
+                }
+            })
+            .collect();
+        let stars = stars_column
+            .and_then(|column| record.get(column))
+            .and_then(|stars| stars.parse().ok());
+        let url = url_column
+            .and_then(|column| record.get(column))
+            .unwrap_or_default();
+        for &i in &date_columns {
+            row.push_field(&iso_date(record.get(i).unwrap_or_default()));
+        }
+        if let Some(created_column) = created_column {
+            let created_at = record.get(created_column).unwrap_or_default();
+            row.push_field(
+                &stars
+                    .and_then(|stars| stars_per_day(stars, created_at, as_of))
+                    .unwrap_or_default(),
+            );
+        }
+        if let Some(age_column) = age_column {
+            let created_at = record.get(age_column).unwrap_or_default();
+            row.push_field(
+                &days_until(created_at, as_of)
+                    .map(|days| format!("{:.1}", days as f64 / 365.25))
+                    .unwrap_or_default(),
+            );
+        }
+        if let Some(pushed_column) = pushed_column {
+            let pushed_at = record.get(pushed_column).unwrap_or_default();
+            row.push_field(
+                &days_until(pushed_at, as_of)
+                    .map(|days| days.to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        if has_health {
+            let inputs = health_inputs(record, &health_columns, as_of);
+            row.push_field(
+                &health::score(&inputs, health)
+                    .map(|score| format!("{:.0}", score))
+                    .unwrap_or_default(),
+            );
+        }
+        if let Some(language_column) = language_column {
+            let language = record.get(language_column).unwrap_or_default();
+            row.push_field(linguist::color(language).unwrap_or_default());
+        }
+        if let Some(week_before) = &week_before {
+            row.extend(delta_values(week_before, url, i + 1, stars));
+        }
+        if let Some(last) = &last {
+            row.push_field(&stars_gained(last, url, stars));
+        }
+        writer.write_record(&row)?;
+        rows += 1;
+    }
+    delimited::commit(writer)?;
+    Ok(rows)
+}
+
+/// Name of the preview of `size` rows of a processed file, e.g. "top10_Rust.csv".
+pub fn preview_file_name(size: usize, file_name: &str) -> String {
+    format!("top{}_{}", size, file_name)
+}
+
+/// Whether `file_name` is a preview written by [`write_preview`].
+pub fn is_preview(file_name: &str) -> bool {
+    file_name
+        .strip_prefix("top")
+        .and_then(|rest| rest.split_once('_'))
+        .is_some_and(|(size, _)| !size.is_empty() && size.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Processed rankings of `dir`, without their previews and the leaderboard of
+/// owners, sorted by name.
+pub fn rankings(dir: &Path, delimiter: u8) -> Result<Vec<PathBuf>> {
+    let mut files = delimited::result_files(dir, delimiter)?;
+    files.retain(|path| {
+        !is_preview(&path.file_name().unwrap_or_default().to_string_lossy())
+            && path.file_stem().is_none_or(|stem| stem != OWNERS_STEM)
+    });
+    Ok(files)
+}
+
+/// Copies the header and the first `size` rows of `input` into `output`.
+pub fn write_preview(input: &Path, output: &Path, size: usize, delimiter: u8) -> Result<()> {
+    let mut reader = delimited::reader(input, delimiter)?;
+    let mut writer = delimited::writer(output, delimiter)?;
+    writer.write_record(reader.headers()?)?;
+    for record in reader.records().take(size) {
+        writer.write_record(
+            &record.with_context(|| format!("Failed to read a row of {:?}", input))?,
+        )?;
+    }
+    delimited::commit(writer)?;
+    Ok(())
+}
+
+/// Merges the rankings of every language into one ranked by stars. Repositories
+/// listed under several languages appear once; files whose header differs from the
+/// first one are skipped. "Rank Δ" is left out, since it describes the ranking of a
+/// language. Returns whether the ranking was written.
+pub fn write_overall_top(files: &[PathBuf], output: &Path, delimiter: u8) -> Result<bool> {
+    let mut headers: Option<StringRecord> = None;
+    let mut rows: Vec<StringRecord> = Vec::new();
+    for path in files {
+        let mut reader = delimited::reader(path, delimiter)?;
+        let file_headers = reader.headers()?.clone();
+        match &headers {
+            Some(headers) if *headers != file_headers => {
+                warn!(
+                    "Leaving {:?} out of the overall ranking: its columns differ.",
+                    path
+                );
+                continue;
+            }
+            Some(_) => {}
+            None => headers = Some(file_headers),
+        }
+        for record in reader.records() {
+            rows.push(record.with_context(|| format!("Failed to read a row of {:?}", path))?);
+        }
+    }
+    let Some(headers) = headers else {
+        return Ok(false);
+    };
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (Some(url_column), Some(stars_column)) = (column("Repo URL"), column("Stars")) else {
+        warn!("Skipping the overall ranking: it needs the \"Repo URL\" and \"Stars\" columns.");
+        return Ok(false);
+    };
+    let ranking_column = column("Ranking");
+
+    let mut seen_urls = HashSet::new();
+    rows.retain(|row| seen_urls.insert(row[url_column].to_string()));
+    rows.sort_by_key(|row| Reverse(row[stars_column].parse::<u64>().unwrap_or(0)));
+    rows.truncate(OVERALL_TOP_RECORDS);
+
+    let kept: Vec<usize> = (0..headers.len())
+        .filter(|&j| &headers[j] != RANK_DELTA_COLUMN)
+        .collect();
+
+    let mut writer = delimited::writer(output, delimiter)?;
+    writer.write_record(kept.iter().map(|&j| &headers[j]))?;
+    for (i, row) in rows.iter().enumerate() {
+        let ranking = (i + 1).to_string();
+        let row: StringRecord = kept
+            .iter()
+            .map(|&j| {
+                if Some(j) == ranking_column {
+                    &ranking
+                } else {
+                    &row[j]
+                }
+            })
+            .collect();
+        writer.write_record(&row)?;
+    }
+    delimited::commit(writer)?;
+    info!(
+        "Wrote the overall ranking of {} repositories to {:?}",
+        rows.len(),
+        output
+    );
+    Ok(true)
+}
+
+/// Adds the colors of the languages ranked in the processed file `path` to `colors`.
+fn add_language_colors(
+    path: &Path,
+    delimiter: u8,
+    colors: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    let mut reader = delimited::reader(path, delimiter)?;
+    let headers = reader.headers()?;
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (Some(language), Some(color)) = (column("Language"), column(LANGUAGE_COLOR_COLUMN)) else {
+        return Ok(());
+    };
+    for record in reader.records() {
+        let record = record.with_context(|| format!("Failed to read a row of {:?}", path))?;
+        if !record[color].is_empty() {
+            colors.insert(record[language].to_string(), record[color].to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Processes every result file of `input_dir` into `output_dir`, keeping the delimiter,
+/// writing dates with `dates`, health scores weighed with `health` and a preview of
+/// its top rows for each of `preview_sizes`, then merges them into the overall
+/// ranking, ranks their owners and describes them in the manifest. Files with a counterpart
+/// in the snapshots of `baselines` get movement columns. `display_names` maps file
+/// stems to the names the manifest gives them; other rankings are named after
+/// their stem.
+#[allow(clippy::too_many_arguments)]
+pub fn process_dir(
+    input_dir: &Path,
+    output_dir: &Path,
+    delimiter: u8,
+    dates: &DateStyle,
+    health: &HealthWeights,
+    baselines: &Baselines,
+    preview_sizes: &[usize],
+    display_names: &HashMap<String, String>,
+) -> Result<()> {
+    if preview_sizes.contains(&0) {
+        bail!("Preview sizes must be positive.");
+    }
+    let mut preview_sizes = preview_sizes.to_vec();
+    preview_sizes.sort_unstable();
+    preview_sizes.dedup();
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create directory: {:?}", output_dir))?;
+    let files = delimited::result_files(input_dir, delimiter)?;
+    let mut processed = Vec::new();
+    let mut languages = Vec::new();
+    let mut language_colors = BTreeMap::new();
+    for path in &files {
+        if path
+            .file_stem()
+            .is_some_and(|stem| stem == OVERALL_TOP_STEM || stem == OWNERS_STEM)
+            || is_preview(&path.file_name().unwrap_or_default().to_string_lossy())
+        {
+            continue;
+        }
+        let file_name = path.file_name().expect("result files have a name");
+        let output = output_dir.join(file_name);
+        let ranking_in = |dir: &Option<PathBuf>| {
+            dir.as_ref()
+                .map(|dir| dir.join(file_name))
+                .filter(|previous| previous.exists())
+                .map(|previous| diff::read_ranking(&previous, delimiter))
+                .transpose()
+        };
+        let week_before = ranking_in(&baselines.week_before)?;
+        let last = ranking_in(&baselines.last)?;
+        let rows = process_file(
+            path,
+            &output,
+            delimiter,
+            dates,
+            health,
+            baselines.as_of,
+            week_before.as_deref(),
+            last.as_deref(),
+        )?;
+        let columns = delimited::reader(&output, delimiter)?
+            .headers()?
+            .iter()
+            .map(str::to_string)
+            .collect();
+        add_language_colors(&output, delimiter, &mut language_colors)?;
+        let file_name = file_name.to_string_lossy();
+        let mut previews = BTreeMap::new();
+        for &size in &preview_sizes {
+            let preview = preview_file_name(size, &file_name);
+            write_preview(&output, &output_dir.join(&preview), size, delimiter)?;
+            previews.insert(size, preview);
+        }
+        let name = path
+            .file_stem()
+            .expect("result files have a name")
+            .to_string_lossy()
+            .into_owned();
+        languages.push(DatasetEntry {
+            display_name: display_names.get(&name).unwrap_or(&name).clone(),
+            trending: name.starts_with(TRENDING_PREFIX),
+            color: linguist::color(name.strip_prefix(TRENDING_PREFIX).unwrap_or(&name))
+                .map(str::to_string),
+            name,
+            rows,
+            columns,
+            file: file_name.into_owned(),
+            previews,
+        });
+        processed.push(output);
+    }
+    info!(
+        "Processed {} file(s) into {:?}",
+        processed.len(),
+        output_dir
+    );
+    let overall_top = output_dir.join(format!(
+        "{}.{}",
+        OVERALL_TOP_STEM,
+        delimited::extension(delimiter)
+    ));
+    // Trending datasets would count their repositories a second time.
+    let all_time: Vec<PathBuf> = processed
+        .into_iter()
+        .filter(|path| {
+            !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(TRENDING_PREFIX))
+        })
+        .collect();
+    let overall_top = write_overall_top(&all_time, &overall_top, delimiter)?.then(|| {
+        overall_top
+            .file_name()
+            .expect("named above")
+            .to_string_lossy()
+            .into_owned()
+    });
+    let owners = format!("{}.{}", OWNERS_STEM, delimited::extension(delimiter));
+    let owners = (owners::write_owners(&all_time, &output_dir.join(&owners), delimiter)? > 0)
+        .then_some(owners);
+    Manifest {
+        schema_version: SCHEMA_VERSION,
+        snapshot_date: baselines.as_of.to_string(),
+        preview_sizes,
+        languages,
+        overall_top,
+        owners,
+        language_colors,
+    }
+    .write(output_dir)
+}
+
+/// Parses a "stem:display name" pair of `--display-names`, e.g. "CPP:C++".
+pub fn parse_display_name(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((stem, display_name)) if !stem.is_empty() && !display_name.is_empty() => {
+            Ok((stem.to_string(), display_name.to_string()))
+        }
+        _ => Err(format!(
+            "invalid display name {:?}: expected \"stem:display name\"",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Baselines, DateStyle, format_date, human_readable_size, is_preview, parse_date_format,
+        parse_timezone, preview_file_name, process_dir, process_file, rankings, write_overall_top,
+        write_preview,
+    };
+    use crate::{diff::read_ranking, health::HealthWeights};
+    use chrono::NaiveDate;
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn as_of() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()
+    }
+
+    #[test]
+    fn test_human_readable_size() {
+        assert_eq!(human_readable_size(109), "109.00 KB");
+        assert_eq!(human_readable_size(16640), "16.25 MB");
+        assert_eq!(human_readable_size(3 * 1024 * 1024), "3.00 GB");
+    }
+
+    #[test]
+    fn test_format_date() {
+        let style = DateStyle::default();
+        assert_eq!(format_date("2015-10-16T08:00:00Z", &style), "16/10/2015");
+        assert_eq!(format_date("16/10/2015", &style), "16/10/2015");
+        let style = DateStyle {
+            format: "%Y-%m-%d %H:%M".to_string(),
+            timezone: parse_timezone("Asia/Tokyo").unwrap(),
+        };
+        assert_eq!(
+            format_date("2015-10-16T20:00:00-02:00", &style),
+            "2015-10-17 07:00"
+        );
+        assert!(parse_timezone("Mars/Olympus").is_err());
+        assert!(parse_date_format("%Q").is_err());
+    }
+
+    #[test]
+    fn test_process_file() {
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("raw.csv");
+        let output = temp_dir.path().join("processed.csv");
+        fs::write(
+            &input,
+            "Ranking,Project Name,Created At,Last Commit,Size (KB)\n\
+             1,VVVVVV,2015-10-16T08:00:00Z,2026-02-26T10:00:00Z,16640\n",
+        )
+        .unwrap();
+
+        process_file(
+            &input,
+            &output,
+            b',',
+            &DateStyle::default(),
+            &HealthWeights::default(),
+            as_of(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "Ranking,Project Name,Created At,Last Commit,Size,Created At (ISO),Last Commit (ISO),\
+             Age (Years),Days Since Last Commit,Health Score\n\
+             1,VVVVVV,16/10/2015,26/02/2026,16.25 MB,2015-10-16T08:00:00Z,2026-02-26T10:00:00Z,\
+             9.6,0,100\n"
+        );
+    }
+
+    #[test]
+    fn test_process_file_derives_watchers() {
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("raw.csv");
+        let output = temp_dir.path().join("processed.csv");
+        let process = || {
+            process_file(
+                &input,
+                &output,
+                b',',
+                &DateStyle::default(),
+                &HealthWeights::default(),
+                as_of(),
+                None,
+                None,
+            )
+            .unwrap();
+            fs::read_to_string(&output).unwrap()
+        };
+
+        // Watchers repeating the stars are left out.
+        fs::write(&input, "Ranking,Stars,Watchers\n1,300,300\n2,200,200\n").unwrap();
+        assert_eq!(process(), "Ranking,Stars\n1,300\n2,200\n");
+        fs::write(&input, "Ranking,Stars,Watchers\n1,300,12\n").unwrap();
+        assert_eq!(process(), "Ranking,Stars,Watchers\n1,300,12\n");
+        // Subscribers take their place.
+        fs::write(
+            &input,
+            "Ranking,Stars,Watchers,Subscribers\n1,300,300,25\n2,200,200,\n",
+        )
+        .unwrap();
+        assert_eq!(process(), "Ranking,Stars,Watchers\n1,300,25\n2,200,\n");
+    }
+
+    #[test]
+    fn test_process_file_adds_movement_against_previous_ranking() {
+        let temp_dir = tempdir().unwrap();
+        let previous = temp_dir.path().join("previous.csv");
+        let input = temp_dir.path().join("raw.csv");
+        let output = temp_dir.path().join("processed.csv");
+        fs::write(
+            &previous,
+            "Ranking,Project Name,Stars,Repo URL\n\
+             1,a,300,https://github.com/x/a\n\
+             2,b,200,https://github.com/x/b\n",
+        )
+        .unwrap();
+        fs::write(
+            &input,
+            "Ranking,Project Name,Stars,Repo URL\n\
+             1,b,350,https://github.com/x/b\n\
+             2,c,320,https://github.com/x/c\n\
+             3,a,310,https://github.com/x/a\n",
+        )
+        .unwrap();
+
+        let previous = read_ranking(&previous, b',').unwrap();
+        process_file(
+            &input,
+            &output,
+            b',',
+            &DateStyle::default(),
+            &HealthWeights::default(),
+            as_of(),
+            Some(&previous),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "Ranking,Project Name,Stars,Repo URL,Rank Δ,Stars Δ (7d)\n\
+             1,b,350,https://github.com/x/b,1,150\n\
+             2,c,320,https://github.com/x/c,new,\n\
+             3,a,310,https://github.com/x/a,-2,10\n"
+        );
+    }
+
+    #[test]
+    fn test_process_file_adds_growth_columns() {
+        let temp_dir = tempdir().unwrap();
+        let last = temp_dir.path().join("last.csv");
+        let input = temp_dir.path().join("raw.csv");
+        let output = temp_dir.path().join("processed.csv");
+        fs::write(
+            &last,
+            "Ranking,Project Name,Stars,Repo URL\n\
+             1,a,900,https://github.com/x/a\n",
+        )
+        .unwrap();
+        fs::write(
+            &input,
+            "Ranking,Project Name,Stars,Created At,Repo URL\n\
+             1,a,1000,2025-05-22T08:00:00Z,https://github.com/x/a\n\
+             2,b,10,2025-06-01T08:00:00Z,https://github.com/x/b\n",
+        )
+        .unwrap();
+
+        let last = read_ranking(&last, b',').unwrap();
+        process_file(
+            &input,
+            &output,
+            b',',
+            &DateStyle::default(),
+            &HealthWeights::default(),
+            as_of(),
+            None,
+            Some(&last),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "Ranking,Project Name,Stars,Created At,Repo URL,Created At (ISO),Stars/Day,\
+             Age (Years),Health Score,Stars Gained\n\
+             1,a,1000,22/05/2025,https://github.com/x/a,2025-05-22T08:00:00Z,100.00,0.0,100,100\n\
+             2,b,10,01/06/2025,https://github.com/x/b,2025-06-01T08:00:00Z,10.00,0.0,61,\n"
+        );
+    }
+
+    #[test]
+    fn test_write_preview_keeps_the_top_rows() {
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("Rust.csv");
+        let output = temp_dir.path().join(preview_file_name(2, "Rust.csv"));
+        fs::write(&input, "Ranking,Stars\n1,300\n2,200\n3,100\n").unwrap();
+
+        write_preview(&input, &output, 2, b',').unwrap();
+
+        assert!(output.ends_with("top2_Rust.csv"));
+        assert!(is_preview("top2_Rust.csv") && !is_preview("topaz_Rust.csv"));
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "Ranking,Stars\n1,300\n2,200\n"
+        );
+    }
+
+    #[test]
+    fn test_process_dir_writes_previews_and_manifest() {
+        let temp_dir = tempdir().unwrap();
+        let input = temp_dir.path().join("raw");
+        let output = temp_dir.path().join("processed");
+        fs::create_dir(&input).unwrap();
+        fs::write(
+            input.join("CPP.csv"),
+            "Ranking,Stars,Repo URL,Language\n1,300,https://github.com/a/b,C++\n\
+             2,200,https://github.com/a/c,\n",
+        )
+        .unwrap();
+        let baselines = Baselines {
+            as_of: as_of(),
+            week_before: None,
+            last: None,
+        };
+
+        let display_names = HashMap::from([("CPP".to_string(), "C++".to_string())]);
+
+        process_dir(
+            &input,
+            &output,
+            b',',
+            &DateStyle::default(),
+            &HealthWeights::default(),
+            &baselines,
+            &[2, 1, 2],
+            &display_names,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(output.join("top1_CPP.csv")).unwrap(),
+            "Ranking,Stars,Repo URL,Language,Language Color\n\
+             1,300,https://github.com/a/b,C++,#f34b7d\n"
+        );
+        assert!(output.join("top2_CPP.csv").exists());
+        assert_eq!(
+            fs::read_to_string(output.join("owners.csv")).unwrap(),
+            "Ranking,Owner,Owner Type,Owner Avatar,Entries,Languages,Stars\n\
+             1,a,,,2,1,500\n"
+        );
+        assert_eq!(rankings(&output, b',').unwrap().len(), 2);
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output.join("manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            manifest,
+            serde_json::json!({
+                "schema_version": 3,
+                "snapshot_date": "2025-06-01",
+                "preview_sizes": [1, 2],
+                "languages": [{
+                    "name": "CPP",
+                    "display_name": "C++",
+                    "trending": false,
+                    "color": "#f34b7d",
+                    "rows": 2,
+                    "columns": ["Ranking", "Stars", "Repo URL", "Language", "Language Color"],
+                    "file": "CPP.csv",
+                    "previews": {"1": "top1_CPP.csv", "2": "top2_CPP.csv"},
+                }],
+                "overall_top": "overall_top.csv",
+                "owners": "owners.csv",
+                "language_colors": {"C++": "#f34b7d"},
+            })
+        );
+        assert!(
+            process_dir(
+                &input,
+                &output,
+                b',',
+                &DateStyle::default(),
+                &HealthWeights::default(),
+                &baselines,
+                &[0],
+                &display_names
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_write_overall_top_dedupes_and_reranks() {
+        let temp_dir = tempdir().unwrap();
+        let rust = temp_dir.path().join("Rust.csv");
+        let js = temp_dir.path().join("JavaScript.csv");
+        let output = temp_dir.path().join("overall_top.csv");
+        fs::write(
+            &rust,
+            "Ranking,Stars,Repo URL\n\
+             1,300,https://github.com/a/shared\n\
+             2,100,https://github.com/a/rust-only\n",
+        )
+        .unwrap();
+        fs::write(
+            &js,
+            "Ranking,Stars,Repo URL\n\
+             1,300,https://github.com/a/shared\n\
+             2,200,https://github.com/a/js-only\n",
+        )
+        .unwrap();
+
+        write_overall_top(&[rust, js], &output, b',').unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "Ranking,Stars,Repo URL\n\
+             1,300,https://github.com/a/shared\n\
+             2,200,https://github.com/a/js-only\n\
+             3,100,https://github.com/a/rust-only\n"
+        );
+    }
+}
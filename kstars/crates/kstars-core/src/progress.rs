@@ -0,0 +1,86 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{self, IsTerminal, Write};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Progress bars of a fetch run: one for the languages, and one per language being
+/// fetched counting its records and pages. They are drawn on stderr only when it is
+/// a terminal, so piped and scheduled runs only get the logs.
+#[derive(Clone)]
+pub struct Progress {
+    multi: MultiProgress,
+}
+
+impl Progress {
+    /// Progress drawn on stderr when it is a terminal, hidden otherwise.
+    pub fn for_stderr() -> Self {
+        if !io::stderr().is_terminal() {
+            return Self::hidden();
+        }
+        Self {
+            multi: MultiProgress::with_draw_target(ProgressDrawTarget::stderr()),
+        }
+    }
+
+    /// Progress that is never drawn.
+    pub fn hidden() -> Self {
+        Self {
+            multi: MultiProgress::with_draw_target(ProgressDrawTarget::hidden()),
+        }
+    }
+
+    /// Bar counting the languages of the run.
+    pub fn languages(&self, count: usize) -> ProgressBar {
+        let bar = self.multi.add(ProgressBar::new(count as u64));
+        bar.set_style(
+            ProgressStyle::with_template("{prefix:>12} [{bar:30}] {pos}/{len} {elapsed}")
+                .expect("template is valid")
+                .progress_chars("=> "),
+        );
+        bar.set_prefix("Languages");
+        bar
+    }
+
+    /// Bar counting the records collected for `language`, out of `records`.
+    pub fn language(&self, language: &str, records: u32) -> ProgressBar {
+        let bar = self.multi.add(ProgressBar::new(records as u64));
+        bar.set_style(
+            ProgressStyle::with_template("{prefix:>12} [{bar:30}] {pos}/{len} records {msg}")
+                .expect("template is valid")
+                .progress_chars("=> "),
+        );
+        bar.set_prefix(language.to_string());
+        bar
+    }
+
+    /// Writer for log lines that clears the bars while a line is printed, so logs
+    /// scroll above them.
+    pub fn log_writer(&self) -> LogWriter {
+        LogWriter {
+            multi: self.multi.clone(),
+        }
+    }
+}
+
+/// Writes log lines to stdout without garbling the progress bars.
+#[derive(Clone)]
+pub struct LogWriter {
+    multi: MultiProgress,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.multi.suspend(|| io::stdout().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
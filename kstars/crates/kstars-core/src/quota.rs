@@ -0,0 +1,170 @@
+use crate::{
+    error::{KstarsError, Result},
+    transport::HttpTransport,
+};
+use anyhow::Context;
+use chrono::{DateTime, Local, TimeZone};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use std::fmt::Write as _;
+
+/// Quota of one of GitHub's rate limits.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Unix time at which the quota is refilled.
+    pub reset: u64,
+}
+
+/// Quotas reported by `/rate_limit`. GitHub Enterprise Server may leave some out.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub core: Option<Quota>,
+    pub search: Option<Quota>,
+    pub graphql: Option<Quota>,
+}
+
+#[derive(Deserialize)]
+struct RateLimitResponse {
+    resources: RateLimits,
+}
+
+/// What GitHub reports about a token.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenStatus {
+    /// `None` when the server has rate limiting disabled.
+    pub limits: Option<RateLimits>,
+    /// OAuth scopes of classic tokens; `None` for fine-grained and app tokens,
+    /// which don't report them.
+    pub scopes: Option<Vec<String>>,
+}
+
+/// Asks `/rate_limit` about `token`. The request doesn't count against any quota.
+/// Fails with [`KstarsError::AuthFailed`] when the token is rejected.
+pub async fn token_status(
+    client: &Client,
+    transport: &dyn HttpTransport,
+    api_base_url: &str,
+    token: &str,
+) -> Result<TokenStatus> {
+    let url = format!("{}/rate_limit", api_base_url.trim_end_matches('/'));
+    let request = client
+        .get(&url)
+        .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
+        .header(reqwest::header::AUTHORIZATION, format!("token {}", token))
+        .build()
+        .context("Failed to build the request")?;
+    let resp = transport
+        .send(request)
+        .await
+        .map_err(|e| anyhow::Error::new(e).context(format!("Failed to reach {}", url)))?;
+    let scopes = resp
+        .headers
+        .get("x-oauth-scopes")
+        .and_then(|value| value.to_str().ok())
+        .map(|scopes| {
+            scopes
+                .split(',')
+                .map(str::trim)
+                .filter(|scope| !scope.is_empty())
+                .map(String::from)
+                .collect()
+        });
+    let limits = match resp.status {
+        StatusCode::UNAUTHORIZED => return Err(KstarsError::AuthFailed(resp.body)),
+        // Enterprise Server answers 404 when rate limiting is disabled.
+        StatusCode::NOT_FOUND => None,
+        status if status.is_success() => {
+            let parsed: RateLimitResponse = serde_json::from_str(&resp.body)
+                .context("Failed to deserialize the rate limits")?;
+            Some(parsed.resources)
+        }
+        status => {
+            return Err(KstarsError::Api {
+                status,
+                message: resp.body,
+            });
+        }
+    };
+    Ok(TokenStatus { limits, scopes })
+}
+
+/// One indented line per quota, e.g. "search     28/30    resets 14:05 (in 1 min)".
+pub fn describe(limits: &RateLimits, now: DateTime<Local>) -> String {
+    let mut text = String::new();
+    for (name, quota) in [
+        ("core", limits.core),
+        ("search", limits.search),
+        ("graphql", limits.graphql),
+    ] {
+        let Some(quota) = quota else {
+            continue;
+        };
+        let reset = Local
+            .timestamp_opt(quota.reset as i64, 0)
+            .single()
+            .unwrap_or(now);
+        let minutes = ((reset - now).num_seconds().max(0) as u64).div_ceil(60);
+        let _ = writeln!(
+            text,
+            "  {:<8} {:>6}/{:<6} resets {} (in {} min)",
+            name,
+            quota.remaining,
+            quota.limit,
+            reset.format("%H:%M"),
+            minutes
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe, token_status};
+    use crate::{error::KstarsError, transport::ReplayTransport};
+    use chrono::{Local, TimeZone};
+    use reqwest::{Client, Method};
+
+    #[tokio::test]
+    async fn test_token_status_reads_quotas_and_scopes() {
+        let body = r#"{"resources": {
+            "core": {"limit": 5000, "remaining": 4990, "reset": 1750000600, "used": 10},
+            "search": {"limit": 30, "remaining": 28, "reset": 1750000060, "used": 2}
+        }, "rate": {"limit": 5000, "remaining": 4990, "reset": 1750000600, "used": 10}}"#;
+        let transport = ReplayTransport::new()
+            .with(
+                Method::GET,
+                "https://api.github.com/rate_limit",
+                200,
+                &[("x-oauth-scopes", "repo, read:org")],
+                body,
+            )
+            .with(
+                Method::GET,
+                "https://ghe.example.com/api/v3/rate_limit",
+                401,
+                &[],
+                "Bad credentials",
+            );
+        let client = Client::new();
+
+        let status = token_status(&client, &transport, "https://api.github.com", "t")
+            .await
+            .unwrap();
+        assert_eq!(status.scopes.unwrap(), ["repo", "read:org"]);
+        let limits = status.limits.unwrap();
+        assert_eq!(limits.search.unwrap().remaining, 28);
+        assert!(limits.graphql.is_none());
+        let now = Local.timestamp_opt(1_750_000_000, 0).unwrap();
+        let text = describe(&limits, now);
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.contains("4990/5000") && text.contains("(in 10 min)"));
+        assert!(text.contains("(in 1 min)"));
+
+        let error = token_status(&client, &transport, "https://ghe.example.com/api/v3", "t")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, KstarsError::AuthFailed(_)));
+    }
+}
@@ -0,0 +1,367 @@
+use crate::error::{KstarsError, Result};
+use reqwest::{StatusCode, header::HeaderMap};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio::{sync::Mutex, time::Instant};
+use tracing::{debug, warn};
+
+/// Fallback wait for secondary limits that don't say how long to back off.
+/// GitHub's documentation asks clients to wait at least one minute.
+const SECONDARY_DEFAULT_WAIT: Duration = Duration::from_secs(60);
+
+/// Wait before the first retry of a timed out or failed (5xx) request; it doubles
+/// with every further attempt.
+const TRANSIENT_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Retries of a timed out or failed request when none are configured.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Which of GitHub's rate limits a response tripped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKind {
+    /// The hourly quota of the token is exhausted (`x-ratelimit-remaining: 0`).
+    Primary,
+    /// Abuse detection kicked in, usually because of bursts or concurrency.
+    Secondary,
+}
+
+/// A rate-limit response and how long the pipeline should stay paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub kind: RateLimitKind,
+    pub wait: Duration,
+}
+
+/// Reads a header as an unsigned integer, ignoring malformed values.
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Current Unix time according to the server that sent `headers`.
+///
+/// `x-ratelimit-reset` is an absolute timestamp on the server's clock. Self-hosted
+/// servers (e.g. GitHub Enterprise Server) can drift from the local clock, so the
+/// response `Date` header is preferred and the local clock is only a fallback.
+pub fn server_now(headers: &HeaderMap) -> u64 {
+    headers
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+        .map(|date| date.timestamp())
+        .unwrap_or_else(|| chrono::Utc::now().timestamp()) as u64
+}
+
+/// Decides whether a response is a rate-limit error and how long to wait.
+///
+/// `retry-after` always wins because GitHub sends it for secondary limits with
+/// the exact back-off it expects. A primary limit is recognised by an exhausted
+/// `x-ratelimit-remaining` and lasts until `x-ratelimit-reset`. A 429, or a 403
+/// whose body mentions a rate limit, without either header is treated as a
+/// secondary limit with the documented one minute back-off. Any other 403 is a
+/// real permission error and returns `None`; this also covers GitHub Enterprise
+/// Server instances with rate limiting disabled, which send no rate-limit headers.
+pub fn classify(
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &str,
+    now_unix: u64,
+) -> Option<RateLimit> {
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    if let Some(seconds) = header_u64(headers, "retry-after") {
+        return Some(RateLimit {
+            kind: RateLimitKind::Secondary,
+            wait: Duration::from_secs(seconds.max(1)),
+        });
+    }
+
+    if header_u64(headers, "x-ratelimit-remaining") == Some(0) {
+        let reset = header_u64(headers, "x-ratelimit-reset").unwrap_or(now_unix);
+        return Some(RateLimit {
+            kind: RateLimitKind::Primary,
+            wait: Duration::from_secs(reset.saturating_sub(now_unix).max(1)),
+        });
+    }
+
+    if status == StatusCode::TOO_MANY_REQUESTS || body.to_lowercase().contains("rate limit") {
+        return Some(RateLimit {
+            kind: RateLimitKind::Secondary,
+            wait: SECONDARY_DEFAULT_WAIT,
+        });
+    }
+
+    None
+}
+
+/// Whether a response status points at a temporary server problem worth retrying.
+pub fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Wait before retry number `attempt` (starting at 1) of a transient failure.
+pub fn transient_retry_delay(attempt: u32) -> Duration {
+    TRANSIENT_RETRY_BASE_DELAY * 2u32.saturating_pow(attempt.saturating_sub(1).min(16))
+}
+
+/// Rate limits hit by the limiters sharing these stats, and the seconds they
+/// paused the pipeline for.
+#[derive(Debug, Default)]
+pub struct PauseStats {
+    pauses: AtomicU64,
+    paused_secs: AtomicU64,
+}
+
+impl PauseStats {
+    pub fn pauses(&self) -> u64 {
+        self.pauses.load(Ordering::Relaxed)
+    }
+
+    pub fn paused_secs(&self) -> u64 {
+        self.paused_secs.load(Ordering::Relaxed)
+    }
+}
+
+/// Shared request budget used by every fetch task.
+///
+/// GitHub applies its limits per token, not per connection, so running several
+/// languages in parallel must not multiply the request rate. Each API call
+/// reserves the next free slot; slots are spaced `min_interval` apart no matter
+/// how many tasks are waiting.
+#[derive(Debug)]
+pub struct RateLimiter {
+    min_interval: Duration,
+    next_slot: Mutex<Instant>,
+    /// Retries of a request that timed out or failed with a 5xx status. Unlike
+    /// rate limits, these failures may be permanent, so retries are capped.
+    max_retries: u32,
+    /// Longest rate-limit pause to wait out; longer ones fail the request.
+    max_pause: Option<Duration>,
+    stats: Arc<PauseStats>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter that allows one request every `min_interval`.
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            next_slot: Mutex::new(Instant::now()),
+            max_retries: DEFAULT_MAX_RETRIES,
+            max_pause: None,
+            stats: Arc::default(),
+        }
+    }
+
+    /// Retries timed out and failed requests up to `max_retries` times.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Fails requests with [`KstarsError::RateLimited`] instead of pausing for
+    /// rate limits that reset later than `max_pause` from now.
+    pub fn with_max_pause(mut self, max_pause: Duration) -> Self {
+        self.max_pause = Some(max_pause);
+        self
+    }
+
+    /// Counts the rate limits hit into `stats`, which can outlive the limiter.
+    pub fn with_stats(mut self, stats: Arc<PauseStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Waits until this caller is allowed to send a request.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let now = Instant::now();
+            let slot = (*next_slot).max(now);
+            *next_slot = slot + self.min_interval;
+            slot
+        };
+        let now = Instant::now();
+        if slot > now {
+            debug!("Waiting {:?} for a request slot.", slot - now);
+            tokio::time::sleep_until(slot).await;
+        }
+    }
+
+    /// Holds back every task until `wait` has elapsed.
+    ///
+    /// Called when any task hits a rate limit, since the limit applies to the
+    /// token and the other tasks would only run into it as well. Fails without
+    /// pausing when `wait` is longer than the limiter's maximum pause.
+    pub async fn pause_for(&self, limit: RateLimit) -> Result<()> {
+        if self
+            .max_pause
+            .is_some_and(|max_pause| limit.wait > max_pause)
+        {
+            let now = chrono::Utc::now().timestamp() as u64;
+            return Err(KstarsError::RateLimited {
+                reset: now + limit.wait.as_secs(),
+            });
+        }
+        self.stats.pauses.fetch_add(1, Ordering::Relaxed);
+        self.stats
+            .paused_secs
+            .fetch_add(limit.wait.as_secs(), Ordering::Relaxed);
+        let resume_at = Instant::now() + limit.wait;
+        let mut next_slot = self.next_slot.lock().await;
+        if resume_at > *next_slot {
+            warn!(
+                "{:?} rate limit hit. Pausing all requests for {} seconds...",
+                limit.kind,
+                limit.wait.as_secs()
+            );
+            *next_slot = resume_at;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        KstarsError, PauseStats, RateLimit, RateLimitKind, RateLimiter, classify, is_transient,
+        server_now, transient_retry_delay,
+    };
+    use reqwest::{
+        StatusCode,
+        header::{HeaderMap, HeaderValue},
+    };
+    use std::{sync::Arc, time::Duration};
+    use tokio::time::Instant;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn test_classify_primary_limit_waits_until_reset() {
+        let h = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1100"),
+        ]);
+        let limit = classify(StatusCode::FORBIDDEN, &h, "", 1000).unwrap();
+        assert_eq!(limit.kind, RateLimitKind::Primary);
+        assert_eq!(limit.wait, Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_classify_retry_after_is_secondary() {
+        let h = headers(&[
+            ("retry-after", "30"),
+            ("x-ratelimit-remaining", "12"),
+            ("x-ratelimit-reset", "5000"),
+        ]);
+        let limit = classify(StatusCode::FORBIDDEN, &h, "", 1000).unwrap();
+        assert_eq!(
+            limit,
+            RateLimit {
+                kind: RateLimitKind::Secondary,
+                wait: Duration::from_secs(30),
+            }
+        );
+    }
+
+    #[test]
+    fn test_classify_secondary_from_body() {
+        let h = headers(&[("x-ratelimit-remaining", "25")]);
+        let body = r#"{"message":"You have exceeded a secondary rate limit."}"#;
+        let limit = classify(StatusCode::FORBIDDEN, &h, body, 1000).unwrap();
+        assert_eq!(limit.kind, RateLimitKind::Secondary);
+        assert_eq!(limit.wait, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_classify_plain_forbidden_is_not_rate_limit() {
+        let h = headers(&[("x-ratelimit-remaining", "25")]);
+        let body = r#"{"message":"Resource not accessible by integration"}"#;
+        assert!(classify(StatusCode::FORBIDDEN, &h, body, 1000).is_none());
+        assert!(classify(StatusCode::OK, &HeaderMap::new(), "", 1000).is_none());
+    }
+
+    #[test]
+    fn test_transient_failures_back_off_exponentially() {
+        assert!(is_transient(StatusCode::BAD_GATEWAY));
+        assert!(!is_transient(StatusCode::NOT_FOUND));
+        assert_eq!(transient_retry_delay(1), Duration::from_secs(1));
+        assert_eq!(transient_retry_delay(3), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_server_now_prefers_date_header() {
+        let h = headers(&[("date", "Thu, 01 Jan 2015 00:00:00 GMT")]);
+        assert_eq!(server_now(&h), 1_420_070_400);
+        assert!(server_now(&HeaderMap::new()) > 1_420_070_400);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_delays_next_acquire() {
+        let stats = Arc::new(PauseStats::default());
+        let limiter = RateLimiter::new(Duration::from_secs(2)).with_stats(Arc::clone(&stats));
+        let start = Instant::now();
+
+        limiter
+            .pause_for(RateLimit {
+                kind: RateLimitKind::Secondary,
+                wait: Duration::from_secs(30),
+            })
+            .await
+            .unwrap();
+        limiter.acquire().await;
+
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+        assert_eq!((stats.pauses(), stats.paused_secs()), (1, 30));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_longer_than_max_pause_fails() {
+        let limiter =
+            RateLimiter::new(Duration::from_secs(2)).with_max_pause(Duration::from_secs(60));
+        let limit = |secs| RateLimit {
+            kind: RateLimitKind::Primary,
+            wait: Duration::from_secs(secs),
+        };
+
+        limiter.pause_for(limit(60)).await.unwrap();
+        let error = limiter.pause_for(limit(3600)).await.unwrap_err();
+
+        assert!(
+            matches!(error, KstarsError::RateLimited { reset } if reset >= chrono::Utc::now().timestamp() as u64 + 3600)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_spaces_requests() {
+        let limiter = RateLimiter::new(Duration::from_secs(2));
+        let start = Instant::now();
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+        limiter.acquire().await;
+
+        assert_eq!(start.elapsed(), Duration::from_secs(4));
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{OwnerType, Repo, error::Result};
+use anyhow::Context;
+use serde::Serialize;
+use std::{collections::BTreeMap, fs, path::Path, time::Duration};
+use tracing::info;
+
+/// Name of the report written to the output folder at the end of a fetch run.
+pub const RUN_REPORT_FILE_NAME: &str = "run_report.json";
+
+/// How the fetch of one language ended.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LanguageStatus {
+    /// Every file of the language was written.
+    Completed,
+    Failed,
+    /// The run was interrupted before the language was written.
+    Interrupted,
+    /// Already completed by the run this one resumed.
+    Skipped,
+}
+
+impl LanguageStatus {
+    /// Name of the status, as written to the report.
+    pub fn name(self) -> &'static str {
+        match self {
+            LanguageStatus::Completed => "completed",
+            LanguageStatus::Failed => "failed",
+            LanguageStatus::Interrupted => "interrupted",
+            LanguageStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// Outcome of one language, for tools deciding which languages to fetch again.
+#[derive(Serialize, Debug, Clone)]
+pub struct LanguageReport {
+    /// API name of the language, as given to `--languages`.
+    pub language: String,
+    pub display_name: String,
+    pub status: LanguageStatus,
+    /// Repositories in the written ranking.
+    pub records: usize,
+    /// Repositories of the written ranking per license label, "None" for those
+    /// without one.
+    pub licenses: BTreeMap<String, usize>,
+    /// Repositories of the written ranking per owner type, "unknown" for those the
+    /// forge did not tell.
+    pub owner_types: BTreeMap<String, usize>,
+    /// Search pages served from the page cache.
+    pub cache_hits: u32,
+    /// Requests sent to the forge: search pages and enrichment lookups.
+    pub api_calls: u32,
+    /// Repositories the search of the language matches, as the forge reported with
+    /// its first page. `None` when that page came from the cache.
+    pub total_count: Option<u64>,
+    /// Whether the forge flagged any page of the search as incomplete.
+    pub incomplete_results: bool,
+    pub duration_secs: f64,
+    pub errors: Vec<String>,
+}
+
+impl LanguageReport {
+    pub fn new(language: &str, display_name: &str) -> Self {
+        Self {
+            language: language.to_string(),
+            display_name: display_name.to_string(),
+            status: LanguageStatus::Failed,
+            records: 0,
+            licenses: BTreeMap::new(),
+            owner_types: BTreeMap::new(),
+            cache_hits: 0,
+            api_calls: 0,
+            total_count: None,
+            incomplete_results: false,
+            duration_secs: 0.0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Records `repos` as the written ranking.
+    pub fn set_ranking(&mut self, repos: &[Repo]) {
+        self.records = repos.len();
+        self.licenses.clear();
+        self.owner_types.clear();
+        for repo in repos {
+            let label = repo.license.as_ref().map_or("None", |l| l.label());
+            *self.licenses.entry(label.to_string()).or_default() += 1;
+            let kind = repo.owner_type().map_or("unknown", OwnerType::name);
+            *self.owner_types.entry(kind.to_string()).or_default() += 1;
+        }
+    }
+}
+
+/// Sums of the language reports.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Totals {
+    pub languages: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub interrupted: usize,
+    pub skipped: usize,
+    pub records: usize,
+    pub licenses: BTreeMap<String, usize>,
+    pub owner_types: BTreeMap<String, usize>,
+    pub cache_hits: u32,
+    pub api_calls: u32,
+}
+
+/// Contents of `run_report.json`.
+#[derive(Serialize, Debug)]
+pub struct RunReport {
+    /// RFC 3339 time the run started.
+    pub started_at: String,
+    pub duration_secs: f64,
+    pub totals: Totals,
+    pub languages: Vec<LanguageReport>,
+}
+
+impl RunReport {
+    /// Report of a run that started at `started_at` and took `duration`; languages
+    /// are listed by API name.
+    pub fn new(started_at: String, duration: Duration, mut languages: Vec<LanguageReport>) -> Self {
+        languages.sort_by(|a, b| a.language.cmp(&b.language));
+        let mut totals = Totals {
+            languages: languages.len(),
+            ..Totals::default()
+        };
+        for language in &languages {
+            match language.status {
+                LanguageStatus::Completed => totals.completed += 1,
+                LanguageStatus::Failed => totals.failed += 1,
+                LanguageStatus::Interrupted => totals.interrupted += 1,
+                LanguageStatus::Skipped => totals.skipped += 1,
+            }
+            totals.records += language.records;
+            for (license, count) in &language.licenses {
+                *totals.licenses.entry(license.clone()).or_default() += count;
+            }
+            for (kind, count) in &language.owner_types {
+                *totals.owner_types.entry(kind.clone()).or_default() += count;
+            }
+            totals.cache_hits += language.cache_hits;
+            totals.api_calls += language.api_calls;
+        }
+        Self {
+            started_at,
+            duration_secs: duration.as_secs_f64(),
+            totals,
+            languages,
+        }
+    }
+
+    /// Writes the report to `output_dir`.
+    pub fn write(&self, output_dir: &Path) -> Result<()> {
+        let path = output_dir.join(RUN_REPORT_FILE_NAME);
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write run report: {:?}", path))?;
+        info!("Run report written to {:?}", path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LanguageReport, LanguageStatus, RunReport, Totals};
+    use std::{collections::BTreeMap, time::Duration};
+
+    #[test]
+    fn test_run_report_totals() {
+        let mut rust = LanguageReport::new("Rust", "Rust");
+        rust.status = LanguageStatus::Completed;
+        rust.records = 100;
+        rust.api_calls = 1;
+        rust.cache_hits = 2;
+        let licenses = BTreeMap::from([("MIT".to_string(), 60), ("None".to_string(), 40)]);
+        rust.licenses = licenses.clone();
+        let mut cpp = LanguageReport::new("CPP", "C++");
+        cpp.errors
+            .push("Request failed with status 500".to_string());
+
+        let report = RunReport::new(
+            "2024-05-01T00:00:00Z".to_string(),
+            Duration::from_secs(3),
+            vec![rust, cpp],
+        );
+        assert_eq!(
+            report.totals,
+            Totals {
+                languages: 2,
+                completed: 1,
+                failed: 1,
+                records: 100,
+                licenses,
+                cache_hits: 2,
+                api_calls: 1,
+                ..Totals::default()
+            }
+        );
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["languages"][0]["language"], "CPP");
+        assert_eq!(json["languages"][1]["status"], "completed");
+    }
+}
@@ -0,0 +1,131 @@
+use crate::error::Result;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::{info, warn};
+
+/// Name of the manifest, in the output folder, listing the languages a run completed.
+pub const RUN_STATE_FILE_NAME: &str = "run_state.json";
+
+/// Contents of the manifest. A run only resumes another with the same forge and
+/// number of records, since rankings of other runs are not comparable.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+struct Manifest {
+    forge: String,
+    records: u32,
+    /// API names of the languages whose files were written.
+    completed: BTreeSet<String>,
+}
+
+/// Progress of a fetch run, saved after every language so `--resume` can skip the
+/// languages an interrupted run already wrote.
+pub struct RunState {
+    path: PathBuf,
+    manifest: Mutex<Manifest>,
+}
+
+impl RunState {
+    /// Starts the state of a run. With `resume`, the languages completed by the
+    /// previous run into `output_dir` count as completed.
+    pub fn start(output_dir: &Path, forge: &str, records: u32, resume: bool) -> Result<Self> {
+        let path = output_dir.join(RUN_STATE_FILE_NAME);
+        let fresh = Manifest {
+            forge: forge.to_string(),
+            records,
+            completed: BTreeSet::new(),
+        };
+        let manifest = match fs::read_to_string(&path) {
+            Ok(content) if resume => match serde_json::from_str::<Manifest>(&content) {
+                Ok(previous) if previous.forge == forge && previous.records == records => {
+                    info!(
+                        "Resuming the previous run: {} language(s) already completed.",
+                        previous.completed.len()
+                    );
+                    previous
+                }
+                Ok(_) => {
+                    warn!("The previous run used another forge or record count; starting over.");
+                    fresh
+                }
+                Err(e) => {
+                    warn!("Ignoring unreadable {:?}: {}", path, e);
+                    fresh
+                }
+            },
+            _ => {
+                if resume {
+                    info!("No previous run to resume in {:?}.", output_dir);
+                }
+                fresh
+            }
+        };
+        Ok(Self {
+            path,
+            manifest: Mutex::new(manifest),
+        })
+    }
+
+    /// Whether `language` was written by this run or the one it resumes.
+    pub fn is_completed(&self, language: &str) -> bool {
+        self.manifest
+            .lock()
+            .expect("run state lock is never poisoned")
+            .completed
+            .contains(language)
+    }
+
+    /// Records that the files of `language` were written and saves the manifest.
+    pub fn mark_completed(&self, language: &str) -> Result<()> {
+        let mut manifest = self
+            .manifest
+            .lock()
+            .expect("run state lock is never poisoned");
+        manifest.completed.insert(language.to_string());
+        let content = serde_json::to_string_pretty(&*manifest)?;
+        fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write run state: {:?}", self.path))?;
+        Ok(())
+    }
+
+    /// Deletes the manifest once every language is completed, so the next run
+    /// starts from scratch.
+    pub fn finish(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)
+                .with_context(|| format!("Failed to delete run state: {:?}", self.path))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RunState;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resume_skips_completed_languages() {
+        let temp_dir = tempdir().unwrap();
+        let first = RunState::start(temp_dir.path(), "github", 1000, false).unwrap();
+        first.mark_completed("Rust").unwrap();
+
+        let resumed = RunState::start(temp_dir.path(), "github", 1000, true).unwrap();
+        assert!(resumed.is_completed("Rust"));
+        assert!(!resumed.is_completed("Go"));
+
+        // Without --resume, or for another kind of run, everything is fetched again.
+        let restarted = RunState::start(temp_dir.path(), "github", 1000, false).unwrap();
+        assert!(!restarted.is_completed("Rust"));
+        let other = RunState::start(temp_dir.path(), "gitlab", 1000, true).unwrap();
+        assert!(!other.is_completed("Rust"));
+
+        resumed.finish().unwrap();
+        let after_finish = RunState::start(temp_dir.path(), "github", 1000, true).unwrap();
+        assert!(!after_finish.is_completed("Rust"));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::Repo;
+
+/// Cleans up repository descriptions before they are written, for consumers that
+/// choke on multi-line or oversized fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sanitizer {
+    /// Also removes emoji, including their modifiers and joiners.
+    pub strip_emoji: bool,
+    /// Characters a description is cut to, counting the trailing "…".
+    pub max_length: Option<usize>,
+}
+
+impl Sanitizer {
+    /// Replaces control characters, newlines included, by spaces and collapses runs
+    /// of whitespace, then applies the optional emoji stripping and length cap.
+    pub fn description(&self, text: &str) -> String {
+        let kept = text
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .filter(|&c| !(self.strip_emoji && is_emoji(c)));
+        let mut cleaned = String::with_capacity(text.len());
+        for word in kept.collect::<String>().split_whitespace() {
+            if !cleaned.is_empty() {
+                cleaned.push(' ');
+            }
+            cleaned.push_str(word);
+        }
+        match self.max_length {
+            Some(max) if cleaned.chars().count() > max => {
+                let mut cut: String = cleaned.chars().take(max.saturating_sub(1)).collect();
+                cut.truncate(cut.trim_end().len());
+                cut.push('…');
+                cut
+            }
+            _ => cleaned,
+        }
+    }
+
+    /// Sanitizes the description of `repo`, dropping it when nothing is left.
+    pub fn apply(&self, repo: &mut Repo) {
+        repo.description = repo
+            .description
+            .as_deref()
+            .map(|text| self.description(text))
+            .filter(|text| !text.is_empty());
+    }
+}
+
+/// Whether `c` is an emoji or a character that only combines with one.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF // pictographs, emoticons, flags and skin tones
+            | 0x2600..=0x27BF // miscellaneous symbols and dingbats
+            | 0x2B00..=0x2BFF // arrows and stars, e.g. "⭐"
+            | 0x200D // zero width joiner
+            | 0x20E3 // combining keycap
+            | 0xFE00..=0xFE0F // variation selectors
+            | 0xE0020..=0xE007F // tags
+    )
+}
+
+/// Parses `--sanitize-descriptions`: comma-separated "strip-emoji" and
+/// "max-length=N" options, or nothing to only strip control characters.
+pub fn parse_options(value: &str) -> Result<Sanitizer, String> {
+    let mut sanitizer = Sanitizer::default();
+    for option in value.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+        match option.split_once('=') {
+            None if option == "strip-emoji" => sanitizer.strip_emoji = true,
+            Some(("max-length", length)) => match length.parse() {
+                Ok(length) if length > 0 => sanitizer.max_length = Some(length),
+                _ => return Err(format!("invalid max-length {:?}", length)),
+            },
+            _ => {
+                return Err(format!(
+                    "unknown option {:?}: expected \"strip-emoji\" or \"max-length=N\"",
+                    option
+                ));
+            }
+        }
+    }
+    Ok(sanitizer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_options;
+
+    #[test]
+    fn test_sanitizer_cleans_descriptions() {
+        let plain = parse_options("").unwrap();
+        assert_eq!(
+            plain.description("A fast\r\n\tparser\u{7}  🚀🚀🚀"),
+            "A fast parser 🚀🚀🚀"
+        );
+
+        let strict = parse_options("strip-emoji,max-length=12").unwrap();
+        assert_eq!(strict.description("🚀 A fast parser 👩‍💻✨"), "A fast pars…");
+        assert_eq!(strict.description("🚀🚀 ok ⭐️"), "ok");
+
+        assert!(parse_options("max-length=0").is_err());
+        assert!(parse_options("strip-html").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::error::Result;
+use anyhow::Context;
+use std::path::{Component, Path, PathBuf};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, info, warn};
+
+/// Decodes the `%XX` escapes of a request path, e.g. "C%2B%2B.csv" to "C++.csv".
+/// Returns `None` for malformed escapes or paths that are not UTF-8.
+fn percent_decode(path: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Maps a request target onto a file below `root`. Query strings are ignored,
+/// escapes are decoded, directories resolve to their `index.html`, and paths
+/// escaping `root` are refused.
+fn resolve_path(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    // Decoded before the components are checked, so "%2e%2e" is refused like "..".
+    let path = percent_decode(path)?;
+    let mut resolved = root.to_path_buf();
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if resolved.is_dir() {
+        resolved.push("index.html");
+    }
+    Some(resolved)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Writes a complete response and lets the client close the connection.
+pub async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    Ok(())
+}
+
+async fn handle_connection(root: &Path, mut stream: TcpStream) -> Result<()> {
+    let mut buffer = vec![0; 8192];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let mut request_line = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let (method, target) = (request_line.next(), request_line.next());
+    debug!("{:?} {:?}", method, target);
+
+    if method != Some("GET") {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"").await;
+    }
+    match target.and_then(|target| resolve_path(root, target)) {
+        Some(path) => match tokio::fs::read(&path).await {
+            Ok(body) => respond(&mut stream, "200 OK", content_type(&path), &body).await,
+            Err(_) => respond(&mut stream, "404 Not Found", "text/plain", b"Not found").await,
+        },
+        None => respond(&mut stream, "403 Forbidden", "text/plain", b"Forbidden").await,
+    }
+}
+
+/// Serves the files below `root` on localhost until the process is stopped.
+pub async fn serve(root: &Path, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("Failed to listen on port {}", port))?;
+    info!("Serving {:?} at http://127.0.0.1:{}/", root, port);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let root = root.to_path_buf();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(&root, stream).await {
+                warn!("Failed to answer request: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_type, resolve_path};
+    use std::{fs, path::Path};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resolve_path() {
+        let root = tempdir().unwrap();
+        fs::create_dir(root.path().join("pages")).unwrap();
+
+        assert_eq!(
+            resolve_path(root.path(), "/").unwrap(),
+            root.path().join("index.html")
+        );
+        assert_eq!(
+            resolve_path(root.path(), "/pages/language.html?lang=Rust").unwrap(),
+            root.path().join("pages/language.html")
+        );
+        assert!(resolve_path(root.path(), "/../secret").is_none());
+        assert_eq!(
+            resolve_path(root.path(), "/data/C%2B%2B.csv").unwrap(),
+            root.path().join("data/C++.csv")
+        );
+        assert!(resolve_path(root.path(), "/%2e%2e/secret").is_none());
+        assert!(resolve_path(root.path(), "/pages/%2E%2E%2F%2E%2E/secret").is_none());
+        assert!(resolve_path(root.path(), "/data/100%").is_none());
+        assert!(resolve_path(root.path(), "/data/%+1").is_none());
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(
+            content_type(Path::new("data/C.csv")),
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            content_type(Path::new("js/main.js")),
+            "text/javascript; charset=utf-8"
+        );
+    }
+}
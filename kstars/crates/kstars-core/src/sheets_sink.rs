@@ -0,0 +1,197 @@
+use crate::{Repo, columns::Column, error::Result, google_auth::ServiceAccount};
+use anyhow::Context;
+use reqwest::Client;
+use serde_json::{Number, Value, json};
+use std::path::Path;
+use tracing::info;
+
+const SHEETS_API_URL: &str = "https://sheets.googleapis.com/v4/spreadsheets";
+
+const SHEETS_SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+
+/// Characters Google Sheets does not allow in sheet titles.
+const FORBIDDEN_TITLE_CHARS: [char; 7] = ['[', ']', '*', '?', '/', '\\', ':'];
+
+/// Title of the sheet a language is written to; Google Sheets limits titles to 100
+/// characters and rejects some characters.
+fn sheet_title(language: &str) -> String {
+    language
+        .chars()
+        .map(|c| {
+            if FORBIDDEN_TITLE_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(100)
+        .collect()
+}
+
+/// A1 range of a whole sheet, e.g. `'C++'`.
+fn sheet_range(title: &str) -> String {
+    format!("'{}'", title.replace('\'', "''"))
+}
+
+/// Numbers are sent as numbers so that they sort and chart in Sheets; everything else
+/// is sent as text and never read as a formula.
+fn cell(value: String) -> Value {
+    if let Ok(number) = value.parse::<i64>() {
+        return Value::from(number);
+    }
+    if value.contains('.')
+        && let Some(number) = value.parse::<f64>().ok().and_then(Number::from_f64)
+    {
+        return Value::Number(number);
+    }
+    Value::String(value)
+}
+
+/// Rows of a sheet: the header of `columns`, then one row per repository.
+fn rows(columns: &[Column], repos: &[Repo]) -> Vec<Vec<Value>> {
+    let header = columns.iter().map(|c| Value::from(c.header())).collect();
+    let mut rows = vec![header];
+    rows.extend(
+        repos
+            .iter()
+            .enumerate()
+            .map(|(i, repo)| columns.iter().map(|c| cell(c.value(i + 1, repo))).collect()),
+    );
+    rows
+}
+
+/// Google Sheets spreadsheet the rankings are written to, one sheet per language.
+/// Each run replaces the content of the sheets it writes.
+///
+/// The spreadsheet must be shared with the email of the service account.
+pub struct SheetsSink {
+    client: Client,
+    account: ServiceAccount,
+    spreadsheet_id: String,
+}
+
+impl SheetsSink {
+    /// Loads the service account key at `credentials_path` and checks that it can
+    /// open the spreadsheet `spreadsheet_id`.
+    pub async fn connect(
+        client: Client,
+        credentials_path: &Path,
+        spreadsheet_id: String,
+    ) -> Result<Self> {
+        let sink = Self {
+            client,
+            account: ServiceAccount::load(credentials_path, SHEETS_SCOPE)?,
+            spreadsheet_id,
+        };
+        let titles = sink.sheet_titles().await?;
+        info!(
+            "Connected to Google Sheets spreadsheet {} ({} sheets).",
+            sink.spreadsheet_id,
+            titles.len()
+        );
+        Ok(sink)
+    }
+
+    async fn sheet_titles(&self) -> Result<Vec<String>> {
+        let token = self.account.access_token(&self.client).await?;
+        let spreadsheet: Value = self
+            .client
+            .get(format!("{}/{}", SHEETS_API_URL, self.spreadsheet_id))
+            .query(&[("fields", "sheets.properties.title")])
+            .bearer_auth(token)
+            .send()
+            .await
+            .context("HTTP request failed")?
+            .error_for_status()
+            .context("Failed to open the spreadsheet")?
+            .json()
+            .await
+            .context("Failed to deserialize spreadsheet")?;
+        Ok(spreadsheet["sheets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|sheet| sheet["properties"]["title"].as_str())
+            .map(str::to_string)
+            .collect())
+    }
+
+    async fn post(&self, url: String, body: &Value, action: &'static str) -> Result<()> {
+        let token = self.account.access_token(&self.client).await?;
+        self.client
+            .post(url)
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .context("HTTP request failed")?
+            .error_for_status()
+            .context(action)?;
+        Ok(())
+    }
+
+    /// Replaces the sheet of `language` with its ranking, adding the sheet if needed.
+    pub async fn write_language(
+        &self,
+        language: &str,
+        columns: &[Column],
+        repos: &[Repo],
+    ) -> Result<()> {
+        let title = sheet_title(language);
+        let range = sheet_range(&title);
+        let spreadsheet = format!("{}/{}", SHEETS_API_URL, self.spreadsheet_id);
+        if !self.sheet_titles().await?.contains(&title) {
+            let add_sheet = json!({
+                "requests": [{ "addSheet": { "properties": { "title": title } } }]
+            });
+            self.post(
+                format!("{}:batchUpdate", spreadsheet),
+                &add_sheet,
+                "Failed to add a sheet",
+            )
+            .await?;
+        }
+        self.post(
+            format!("{}/values:batchClear", spreadsheet),
+            &json!({ "ranges": [range] }),
+            "Failed to clear the sheet",
+        )
+        .await?;
+        let values = json!({
+            "valueInputOption": "RAW",
+            "data": [{ "range": range, "values": rows(columns, repos) }],
+        });
+        self.post(
+            format!("{}/values:batchUpdate", spreadsheet),
+            &values,
+            "Failed to write the sheet",
+        )
+        .await?;
+        info!(
+            "Wrote {} repositories for {} to Google Sheets",
+            repos.len(),
+            language
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cell, sheet_range, sheet_title};
+    use serde_json::json;
+
+    #[test]
+    fn test_sheet_titles_ranges_and_cells() {
+        assert_eq!(sheet_title("C++"), "C++");
+        assert_eq!(sheet_title("trending/Rust"), "trending_Rust");
+        assert_eq!(sheet_title(&"a".repeat(120)).len(), 100);
+        assert_eq!(sheet_range("Rust"), "'Rust'");
+        assert_eq!(sheet_range("Ren'Py"), "'Ren''Py'");
+
+        assert_eq!(cell("1200".to_string()), json!(1200));
+        assert_eq!(cell("0.5".to_string()), json!(0.5));
+        assert_eq!(cell("=1+1".to_string()), json!("=1+1"));
+        assert_eq!(cell("2024-05-01".to_string()), json!("2024-05-01"));
+    }
+}
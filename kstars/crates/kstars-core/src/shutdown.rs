@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::warn;
+
+/// Exit code of a run stopped by a second Ctrl-C, as shells report for SIGINT.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Tells the languages being fetched that the run is stopping, so they stop and
+/// save what they collected instead of being killed mid-way.
+#[derive(Clone)]
+pub struct Shutdown {
+    triggered: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    /// A shutdown triggered only through `trigger`.
+    pub fn new() -> Self {
+        Self {
+            triggered: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    /// A shutdown also triggered by the first Ctrl-C or SIGTERM. A second Ctrl-C
+    /// exits right away.
+    pub fn on_signals() -> Self {
+        let shutdown = Self::new();
+        let on_signal = shutdown.clone();
+        tokio::spawn(async move {
+            signal().await;
+            warn!("Interrupted. Saving progress; press Ctrl-C again to exit immediately.");
+            on_signal.trigger();
+            signal().await;
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        });
+        shutdown
+    }
+
+    /// Stops the run.
+    pub fn trigger(&self) {
+        self.triggered.send_replace(true);
+    }
+
+    /// Whether the run is stopping.
+    pub fn is_triggered(&self) -> bool {
+        *self.triggered.borrow()
+    }
+
+    /// Completes once the run is stopping.
+    pub async fn triggered(&self) {
+        let mut triggered = self.triggered.subscribe();
+        // The sender lives as long as `self`, so waiting cannot fail.
+        let _ = triggered.wait_for(|&t| t).await;
+    }
+}
+
+/// Completes on the next Ctrl-C, or SIGTERM on Unix.
+async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("Failed to listen for Ctrl-C: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
@@ -0,0 +1,368 @@
+use crate::{
+    Hygiene, OwnerType, PullRequestCounts, Repo, atomic::AtomicFile, columns::Column, delimited,
+    error::Result,
+};
+use anyhow::Context;
+use csv::Writer;
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+use tracing::info;
+
+/// Destination of one language's ranking, e.g. a CSV file or database rows.
+///
+/// The header is written first, then every repository in ranking order, and `finish`
+/// completes the output. Sinks that buffer write everything in `finish`.
+pub trait OutputSink: Send {
+    /// Writes whatever precedes the first repository.
+    fn write_header(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Writes the repository ranked at position `ranking` (starting at 1).
+    fn write_repo(&mut self, ranking: usize, repo: &Repo) -> Result<()>;
+
+    /// Completes the output and flushes it.
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// Writes a full ranking to `sink`.
+pub fn write_all(sink: &mut dyn OutputSink, repos: &[Repo]) -> Result<()> {
+    sink.write_header()?;
+    for (i, repo) in repos.iter().enumerate() {
+        sink.write_repo(i + 1, repo)?;
+    }
+    sink.finish()
+}
+
+fn create_file(path: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(path).with_context(|| format!("Failed to create file: {:?}", path))?;
+    Ok(BufWriter::new(file))
+}
+
+/// A ranked repository as written to JSON files.
+#[derive(Serialize, Debug)]
+pub struct RepoRecord<'a> {
+    ranking: usize,
+    name: &'a str,
+    stars: u64,
+    forks: u64,
+    watchers: u64,
+    open_issues: u64,
+    created_at: &'a str,
+    last_commit: &'a str,
+    size_kb: u64,
+    description: Option<&'a str>,
+    language: Option<&'a str>,
+    url: &'a str,
+    license: Option<&'a str>,
+    topics: &'a [String],
+    owner: Option<&'a str>,
+    owner_avatar: Option<&'a str>,
+    owner_type: Option<&'a str>,
+    archived: bool,
+    fork: bool,
+    default_branch: Option<&'a str>,
+    /// Enrichment fields are left out unless they were fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest_release: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_date: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commits_last_year: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    readme_excerpt: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subscribers: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    closed_issues: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issue_close_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pull_requests: Option<PullRequestCounts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sponsorable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hygiene: Option<Hygiene>,
+}
+
+impl<'a> RepoRecord<'a> {
+    pub fn new(ranking: usize, repo: &'a Repo) -> Self {
+        Self {
+            ranking,
+            name: &repo.name,
+            stars: repo.stargazers_count,
+            forks: repo.forks_count,
+            watchers: repo.watchers_count,
+            open_issues: repo.open_issues_count,
+            created_at: &repo.created_at,
+            last_commit: &repo.pushed_at,
+            size_kb: repo.size,
+            description: repo.description.as_deref(),
+            language: repo.language.as_deref(),
+            url: &repo.html_url,
+            license: repo.license.as_ref().map(|l| l.label()),
+            topics: &repo.topics,
+            owner: repo.owner_login(),
+            owner_avatar: repo.owner_avatar_url(),
+            owner_type: repo.owner_type().map(OwnerType::name),
+            archived: repo.archived,
+            fork: repo.fork,
+            default_branch: repo.default_branch.as_deref(),
+            latest_release: repo
+                .details
+                .latest_release
+                .as_ref()
+                .map(|r| r.tag_name.as_str()),
+            release_date: repo
+                .details
+                .latest_release
+                .as_ref()
+                .and_then(|r| r.published_at.as_deref()),
+            release_count: repo.details.release_count,
+            commits_last_year: repo.details.commits_last_year,
+            readme_excerpt: repo.details.readme_excerpt.as_deref(),
+            subscribers: repo.details.subscribers_count,
+            closed_issues: repo.details.closed_issues,
+            issue_close_rate: repo.details.issue_close_rate,
+            pull_requests: repo.details.pull_requests,
+            sponsorable: repo.details.sponsorable,
+            hygiene: repo.details.hygiene,
+        }
+    }
+}
+
+/// Delimited file with the selected columns, in the order given.
+pub struct CsvSink {
+    /// Taken by `finish`.
+    writer: Option<Writer<AtomicFile>>,
+    columns: Vec<Column>,
+}
+
+impl CsvSink {
+    pub fn create(path: &Path, delimiter: u8, columns: &[Column]) -> Result<Self> {
+        info!("Writing repositories to CSV: {:?}", path);
+        Ok(Self {
+            writer: Some(delimited::writer(path, delimiter)?),
+            columns: columns.to_vec(),
+        })
+    }
+}
+
+impl OutputSink for CsvSink {
+    fn write_header(&mut self) -> Result<()> {
+        let writer = self
+            .writer
+            .as_mut()
+            .expect("rows are written before finish");
+        writer.write_record(self.columns.iter().map(|c| c.header()))?;
+        Ok(())
+    }
+
+    fn write_repo(&mut self, ranking: usize, repo: &Repo) -> Result<()> {
+        let writer = self
+            .writer
+            .as_mut()
+            .expect("rows are written before finish");
+        writer.write_record(self.columns.iter().map(|c| c.value(ranking, repo)))?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            delimited::commit(writer)?;
+        }
+        info!("CSV file written successfully.");
+        Ok(())
+    }
+}
+
+/// JSON array of ranked records, one record per line.
+pub struct JsonSink {
+    /// Taken by `finish`.
+    file: Option<AtomicFile>,
+    count: usize,
+}
+
+impl JsonSink {
+    pub fn create(path: &Path) -> Result<Self> {
+        info!("Writing repositories to JSON: {:?}", path);
+        Ok(Self {
+            file: Some(AtomicFile::create(path)?),
+            count: 0,
+        })
+    }
+
+    fn file(&mut self) -> &mut AtomicFile {
+        self.file.as_mut().expect("rows are written before finish")
+    }
+}
+
+impl OutputSink for JsonSink {
+    fn write_header(&mut self) -> Result<()> {
+        self.file().write_all(b"[")?;
+        Ok(())
+    }
+
+    fn write_repo(&mut self, ranking: usize, repo: &Repo) -> Result<()> {
+        let separator: &[u8] = if self.count == 0 { b"\n  " } else { b",\n  " };
+        let file = self.file();
+        file.write_all(separator)?;
+        serde_json::to_writer(file, &RepoRecord::new(ranking, repo))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.write_all(b"\n]\n")?;
+            file.commit()?;
+        }
+        info!("JSON file written successfully.");
+        Ok(())
+    }
+}
+
+/// JSON Lines file, flushed after every record so the lines written so far survive
+/// an interrupted run. Used to stream repositories while they are being fetched,
+/// so unlike the other sinks it is written in place rather than atomically.
+pub struct JsonlSink {
+    writer: BufWriter<File>,
+}
+
+impl JsonlSink {
+    pub fn create(path: &Path) -> Result<Self> {
+        info!("Streaming repositories to JSON Lines: {:?}", path);
+        Ok(Self {
+            writer: create_file(path)?,
+        })
+    }
+}
+
+impl OutputSink for JsonlSink {
+    fn write_repo(&mut self, ranking: usize, repo: &Repo) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, &RepoRecord::new(ranking, repo))?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CsvSink, JsonSink, write_all};
+    use crate::error::Result;
+    use crate::{Repo, columns::Column};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn repos() -> Vec<Repo> {
+        vec![
+            Repo {
+                name: "rust".to_string(),
+                html_url: "https://github.com/rust-lang/rust".to_string(),
+                stargazers_count: 50000,
+                forks_count: 10000,
+                watchers_count: 50000,
+                language: Some("Rust".to_string()),
+                description: Some("The Rust Programming Language".to_string()),
+                open_issues_count: 5000,
+                created_at: "2010-01-01T00:00:00Z".to_string(),
+                pushed_at: "2023-01-01T00:00:00Z".to_string(),
+                size: 100000,
+                ..Default::default()
+            },
+            Repo {
+                name: "actix".to_string(),
+                html_url: "https://github.com/actix/actix".to_string(),
+                stargazers_count: 10000,
+                forks_count: 2000,
+                watchers_count: 10000,
+                language: Some("Rust".to_string()),
+                description: Some("Actor framework for Rust".to_string()),
+                open_issues_count: 1000,
+                created_at: "2018-01-01T00:00:00Z".to_string(),
+                pushed_at: "2023-01-02T00:00:00Z".to_string(),
+                size: 5000,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_write_repos_to_csv() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let file_path = temp_dir.path().join("rust.csv");
+
+        write_all(
+            &mut CsvSink::create(&file_path, b',', &Column::ALL)?,
+            &repos(),
+        )?;
+
+        // Check that the file exists
+        assert!(file_path.exists());
+
+        // Read the CSV to verify content
+        let content = fs::read_to_string(&file_path)?;
+        assert!(content.contains("Ranking,Project Name,Stars,Forks"));
+        assert!(content.contains("1,rust,50000,10000"));
+        assert!(content.contains("2,actix,10000,2000"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_selected_columns_in_order() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let file_path = temp_dir.path().join("rust.csv");
+        let columns = [Column::RepoUrl, Column::Stars];
+
+        write_all(&mut CsvSink::create(&file_path, b',', &columns)?, &repos())?;
+
+        assert_eq!(
+            fs::read_to_string(&file_path)?,
+            "Repo URL,Stars\n\
+             https://github.com/rust-lang/rust,50000\n\
+             https://github.com/actix/actix,10000\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_repos_to_json() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let file_path = temp_dir.path().join("rust.json");
+
+        write_all(&mut JsonSink::create(&file_path)?, &repos())?;
+
+        let records: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file_path)?)?;
+        assert_eq!(records.as_array().unwrap().len(), 2);
+        assert_eq!(records[0]["ranking"], 1);
+        assert_eq!(records[0]["name"], "rust");
+        assert_eq!(records[1]["stars"], 10000);
+        assert_eq!(records[1]["url"], "https://github.com/actix/actix");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_empty_json_array() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let file_path = temp_dir.path().join("empty.json");
+
+        write_all(&mut JsonSink::create(&file_path)?, &[])?;
+
+        let records: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file_path)?)?;
+        assert_eq!(records, serde_json::json!([]));
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use crate::error::Result;
+use anyhow::Context;
+use chrono::{
+    DateTime, Days, Local, NaiveDate, NaiveTime,
+    format::{Item, Parsed, StrftimeItems, parse},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+/// Name of the entry in the output folder pointing at the newest snapshot: a
+/// symlink on Unix, a copy elsewhere.
+pub const LATEST_NAME: &str = "latest";
+
+/// Default `--snapshot-template`: one folder per day.
+pub const DEFAULT_TEMPLATE: &str = "%Y-%m-%d";
+
+/// Checks a `--snapshot-template`: a strftime format naming a single folder.
+pub fn parse_template(template: &str) -> Result<String, String> {
+    if StrftimeItems::new(template).any(|item| item == Item::Error) {
+        return Err(format!("invalid strftime format {:?}", template));
+    }
+    let sample = Local::now().format(template).to_string();
+    if sample.is_empty()
+        || sample.starts_with('.')
+        || sample.contains(['/', '\\'])
+        || sample == LATEST_NAME
+    {
+        return Err(format!(
+            "{:?} must name a single folder, e.g. \"%Y-%m-%d\"",
+            template
+        ));
+    }
+    Ok(template.to_string())
+}
+
+/// Folder of the snapshot taken at `time`.
+pub fn snapshot_dir(output_dir: &Path, template: &str, time: DateTime<Local>) -> PathBuf {
+    output_dir.join(time.format(template).to_string())
+}
+
+/// Snapshots in `output_dir`, oldest first: the folders whose name matches `template`.
+pub fn list(output_dir: &Path, template: &str) -> Result<Vec<PathBuf>> {
+    if !output_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(output_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == LATEST_NAME || !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let mut parsed = Parsed::new();
+        if parse(&mut parsed, &name, StrftimeItems::new(template)).is_err() {
+            continue;
+        }
+        // Templates without a full date or time still sort by name.
+        let date: Option<NaiveDate> = parsed.to_naive_date().ok();
+        let time: Option<NaiveTime> = parsed.to_naive_time().ok();
+        snapshots.push(((date, time, name), entry.path()));
+    }
+    snapshots.sort();
+    Ok(snapshots.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Date of the snapshot folder `dir`, when its name carries one. `latest` resolves
+/// to the snapshot it points at.
+pub fn snapshot_date(dir: &Path, template: &str) -> Option<NaiveDate> {
+    let dir = fs::canonicalize(dir).ok()?;
+    let mut parsed = Parsed::new();
+    let name = dir.file_name()?.to_string_lossy().into_owned();
+    parse(&mut parsed, &name, StrftimeItems::new(template)).ok()?;
+    parsed.to_naive_date().ok()
+}
+
+/// The newest snapshot next to `dir` taken at least `days` days before it.
+pub fn previous(dir: &Path, template: &str, days: u64) -> Result<Option<PathBuf>> {
+    let Some(date) = snapshot_date(dir, template) else {
+        return Ok(None);
+    };
+    let dir = fs::canonicalize(dir)?;
+    let Some(parent) = dir.parent() else {
+        return Ok(None);
+    };
+    let cutoff = date - Days::new(days);
+    Ok(list(parent, template)?
+        .into_iter()
+        .rfind(|snapshot| snapshot_date(snapshot, template).is_some_and(|d| d <= cutoff)))
+}
+
+#[cfg(not(unix))]
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        // The page cache is only needed by the snapshot itself.
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()))?;
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Points `latest` in `output_dir` at `snapshot`.
+pub fn update_latest(output_dir: &Path, snapshot: &Path) -> Result<()> {
+    let latest = output_dir.join(LATEST_NAME);
+    match fs::symlink_metadata(&latest) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&latest)?,
+        Ok(_) => fs::remove_file(&latest)?,
+        Err(_) => {}
+    }
+    #[cfg(unix)]
+    let updated = std::os::unix::fs::symlink(
+        snapshot.strip_prefix(output_dir).unwrap_or(snapshot),
+        &latest,
+    );
+    #[cfg(not(unix))]
+    let updated = copy_dir(snapshot, &latest);
+    updated.with_context(|| format!("Failed to point {:?} at {:?}", latest, snapshot))?;
+    info!("{:?} now points at {:?}", latest, snapshot);
+    Ok(())
+}
+
+/// Deletes all but the `keep` newest snapshots. Returns the folders deleted.
+pub fn prune(output_dir: &Path, template: &str, keep: usize) -> Result<Vec<PathBuf>> {
+    let snapshots = list(output_dir, template)?;
+    let excess = snapshots.len().saturating_sub(keep);
+    let pruned: Vec<PathBuf> = snapshots.into_iter().take(excess).collect();
+    for dir in &pruned {
+        fs::remove_dir_all(dir).with_context(|| format!("Failed to delete {:?}", dir))?;
+        info!("Deleted old snapshot {:?}", dir);
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DEFAULT_TEMPLATE, LATEST_NAME, list, parse_template, previous, prune, update_latest,
+    };
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_prune_keeps_newest_snapshots() {
+        let output = tempdir().unwrap();
+        for name in ["2025-06-02", "2025-05-31", "2025-06-01", ".cache", "notes"] {
+            fs::create_dir(output.path().join(name)).unwrap();
+        }
+        fs::write(output.path().join("2025-06-02/Rust.csv"), "Ranking\n").unwrap();
+
+        let snapshots = list(output.path(), DEFAULT_TEMPLATE).unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert!(snapshots[0].ends_with("2025-05-31"));
+
+        update_latest(output.path(), &snapshots[2]).unwrap();
+        assert!(output.path().join(LATEST_NAME).join("Rust.csv").exists());
+
+        let pruned = prune(output.path(), DEFAULT_TEMPLATE, 2).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert!(!output.path().join("2025-05-31").exists());
+        assert!(output.path().join("notes").exists());
+
+        let day_before = previous(&output.path().join(LATEST_NAME), DEFAULT_TEMPLATE, 1);
+        assert!(day_before.unwrap().unwrap().ends_with("2025-06-01"));
+        assert_eq!(previous(&snapshots[2], DEFAULT_TEMPLATE, 7).unwrap(), None);
+
+        assert!(parse_template("%Y/%m/%d").is_err());
+        assert!(parse_template("%Y-%m-%d_%H%M").is_ok());
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{
+    auth,
+    error::{KstarsError, Result},
+};
+use anyhow::Context;
+use reqwest::{Url, header::HeaderMap};
+use std::{collections::HashMap, fs, path::Path, sync::Mutex};
+use tracing::{debug, error, info};
+
+/// A token is rotated out once its remaining quota drops to this value.
+const LOW_WATERMARK: u64 = 1;
+
+/// Quota of requests that are not searches, e.g. the REST API calls of enrichments.
+pub const CORE_RESOURCE: &str = "core";
+
+/// Quota of search requests, much smaller than the core one on GitHub.
+pub const SEARCH_RESOURCE: &str = "search";
+
+/// Quota of GraphQL requests.
+pub const GRAPHQL_RESOURCE: &str = "graphql";
+
+/// Quota a request to `url` counts against. GitHub names it in the
+/// `x-ratelimit-resource` header of the response, but the token has to be picked
+/// before the request is sent.
+pub fn resource(url: &Url) -> &'static str {
+    let path = url.path();
+    if path.contains("/search/") {
+        SEARCH_RESOURCE
+    } else if path.ends_with("/graphql") {
+        GRAPHQL_RESOURCE
+    } else {
+        CORE_RESOURCE
+    }
+}
+
+/// Quota GitHub last reported for one resource of a token.
+#[derive(Debug, Default)]
+struct Quota {
+    remaining: Option<u64>,
+    /// Unix time at which the quota is refilled.
+    reset_at: Option<u64>,
+}
+
+impl Quota {
+    fn is_exhausted(&self, now: u64) -> bool {
+        self.remaining.is_some_and(|r| r <= LOW_WATERMARK)
+            && self.reset_at.is_some_and(|reset| reset > now)
+    }
+}
+
+/// Quotas GitHub last reported for a token, by resource.
+#[derive(Debug)]
+struct TokenQuota {
+    token: String,
+    /// Resources without a response for this token yet are missing.
+    resources: HashMap<String, Quota>,
+}
+
+impl TokenQuota {
+    fn new(token: String) -> Self {
+        Self {
+            token,
+            resources: HashMap::new(),
+        }
+    }
+
+    fn quota(&self, resource: &str) -> Option<&Quota> {
+        self.resources.get(resource)
+    }
+
+    fn is_exhausted(&self, resource: &str, now: u64) -> bool {
+        self.quota(resource).is_some_and(|q| q.is_exhausted(now))
+    }
+}
+
+#[derive(Debug)]
+struct PoolState {
+    quotas: Vec<TokenQuota>,
+    current: usize,
+}
+
+/// A token handed out for one request.
+#[derive(Debug, Clone)]
+pub struct Checkout {
+    pub index: usize,
+    pub token: String,
+}
+
+/// Set of access tokens shared by all tasks.
+///
+/// Requests keep using the current token until its remaining quota (taken from the
+/// `x-ratelimit-*` headers of its responses) runs low, then switch to the token with
+/// the most quota left. Quotas are tracked per resource, since GitHub limits search
+/// and other requests separately. An empty pool means anonymous access.
+#[derive(Debug)]
+pub struct TokenPool {
+    state: Mutex<PoolState>,
+}
+
+impl TokenPool {
+    pub fn new(tokens: Vec<String>) -> Self {
+        let quotas = tokens.into_iter().map(TokenQuota::new).collect();
+        Self {
+            state: Mutex::new(PoolState { quotas, current: 0 }),
+        }
+    }
+
+    /// Replaces the pooled tokens, e.g. with freshly minted short-lived ones.
+    pub fn replace_tokens(&self, tokens: Vec<String>) {
+        let mut state = self.state.lock().unwrap();
+        *state = PoolState {
+            quotas: tokens.into_iter().map(TokenQuota::new).collect(),
+            current: 0,
+        };
+    }
+
+    /// Picks the token for the next request against `resource`, or `None` for an
+    /// empty pool. If every token is exhausted the one that resets first is returned.
+    pub fn checkout(&self, now: u64, resource: &str) -> Option<Checkout> {
+        let mut state = self.state.lock().unwrap();
+        if state.quotas.is_empty() {
+            return None;
+        }
+
+        let current = state.current;
+        if !state.quotas[current].is_exhausted(resource, now) {
+            return Some(Checkout {
+                index: current,
+                token: state.quotas[current].token.clone(),
+            });
+        }
+
+        // Unknown quota counts as full: the token has not been used yet.
+        let next =
+            state
+                .quotas
+                .iter()
+                .enumerate()
+                .filter(|(_, q)| !q.is_exhausted(resource, now))
+                .max_by_key(|(_, q)| {
+                    q.quota(resource)
+                        .and_then(|q| q.remaining)
+                        .unwrap_or(u64::MAX)
+                })
+                .or_else(|| {
+                    state.quotas.iter().enumerate().min_by_key(|(_, q)| {
+                        q.quota(resource).and_then(|q| q.reset_at).unwrap_or(0)
+                    })
+                })
+                .map(|(i, _)| i)
+                .expect("pool is not empty");
+        if next != current {
+            info!(
+                "Token #{} is close to its {} rate limit. Switching to token #{}.",
+                current + 1,
+                resource,
+                next + 1
+            );
+            state.current = next;
+        }
+        Some(Checkout {
+            index: next,
+            token: state.quotas[next].token.clone(),
+        })
+    }
+
+    /// Records the quota reported in the headers of a response made with token `index`.
+    /// The quota is filed under the `x-ratelimit-resource` header, or under `resource`
+    /// when the forge does not send one.
+    pub fn record(&self, index: usize, resource: &str, headers: &HeaderMap) {
+        let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+        let number = |name: &str| -> Option<u64> { header(name)?.parse().ok() };
+        let resource = header("x-ratelimit-resource").unwrap_or(resource);
+        let mut state = self.state.lock().unwrap();
+        if let Some(token) = state.quotas.get_mut(index) {
+            let quota = token.resources.entry(resource.to_string()).or_default();
+            if let Some(remaining) = number("x-ratelimit-remaining") {
+                quota.remaining = Some(remaining);
+            }
+            if let Some(reset_at) = number("x-ratelimit-reset") {
+                quota.reset_at = Some(reset_at);
+            }
+            debug!(
+                "Token #{}: {:?} {} requests left, reset at {:?}.",
+                index + 1,
+                quota.remaining,
+                resource,
+                quota.reset_at
+            );
+        }
+    }
+
+    /// Marks the `resource` quota of token `index` as used up until `reset_at`.
+    pub fn mark_exhausted(&self, index: usize, resource: &str, reset_at: u64) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(token) = state.quotas.get_mut(index) {
+            let quota = token.resources.entry(resource.to_string()).or_default();
+            quota.remaining = Some(0);
+            quota.reset_at = Some(reset_at);
+        }
+    }
+
+    /// Whether some token still has `resource` quota left.
+    pub fn has_available(&self, now: u64, resource: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        state.quotas.iter().any(|q| !q.is_exhausted(resource, now))
+    }
+}
+
+/// Splits a token list given as comma- or newline-separated values.
+/// Blank entries and `#` comment lines are ignored.
+pub fn parse_token_list(input: &str) -> Vec<String> {
+    input
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|t| !t.is_empty() && !t.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reads access tokens from a file (one per line) or a comma-separated string.
+pub fn read_token_list(token_input: &str) -> Result<Vec<String>> {
+    // Check if it's a valid file path.
+    if Path::new(token_input).exists() {
+        info!("Reading access tokens from file: {}", token_input);
+        let content = fs::read_to_string(token_input)
+            .with_context(|| format!("Failed to read access tokens from file: {}", token_input))?;
+        return Ok(parse_token_list(&content));
+    }
+
+    // Otherwise, assume it's a direct string.
+    Ok(parse_token_list(token_input))
+}
+
+/// Reads the tokens of a provider that also allows anonymous access.
+pub fn read_optional_tokens(token_input: Option<String>) -> Result<Vec<String>> {
+    token_input.map_or(Ok(Vec::new()), |input| read_token_list(&input))
+}
+
+/// Reads the GitHub access tokens from a file, string, or environment variable,
+/// falling back to the token stored by `kstars login`.
+/// Several tokens can be given to spread requests over their rate limits.
+pub fn get_access_tokens(token_input: Option<String>) -> Result<Vec<String>> {
+    let tokens = match token_input {
+        Some(token_input) => {
+            info!("Using access tokens from command-line input.");
+            read_token_list(&token_input)?
+        }
+        // Fall back to environment variable.
+        None => match std::env::var("GITHUB_TOKEN") {
+            Ok(token) => {
+                info!("Using access token from environment variable.");
+                parse_token_list(&token)
+            }
+            Err(_) => match auth::credentials_path().and_then(|p| auth::load_stored_token(&p)) {
+                Some(token) => {
+                    info!("Using access token stored by `kstars login`.");
+                    vec![token]
+                }
+                None => Vec::new(),
+            },
+        },
+    };
+
+    if tokens.is_empty() {
+        error!("Access token not provided.");
+        return Err(KstarsError::AuthFailed(
+            "access token not provided".to_string(),
+        ));
+    }
+    info!("Loaded {} access token(s).", tokens.len());
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CORE_RESOURCE, SEARCH_RESOURCE, TokenPool, parse_token_list, read_token_list, resource,
+    };
+    use crate::error::Result;
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn quota_headers(remaining: &'static str, reset: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static(remaining));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static(reset));
+        headers
+    }
+
+    #[test]
+    fn test_parse_token_list() {
+        assert_eq!(parse_token_list("a, b,,c"), ["a", "b", "c"]);
+        assert_eq!(parse_token_list("# tokens\na\n\nb\n"), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_checkout_rotates_when_quota_runs_low() {
+        let pool = TokenPool::new(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(pool.checkout(1000, CORE_RESOURCE).unwrap().token, "a");
+
+        pool.record(0, CORE_RESOURCE, &quota_headers("10", "2000"));
+        assert_eq!(pool.checkout(1000, CORE_RESOURCE).unwrap().token, "a");
+
+        pool.record(0, CORE_RESOURCE, &quota_headers("1", "2000"));
+        assert_eq!(pool.checkout(1000, CORE_RESOURCE).unwrap().token, "b");
+
+        // Once the first token's quota resets it is usable again.
+        pool.mark_exhausted(1, CORE_RESOURCE, 3000);
+        assert!(pool.has_available(2500, CORE_RESOURCE));
+        assert_eq!(pool.checkout(2500, CORE_RESOURCE).unwrap().token, "a");
+    }
+
+    #[test]
+    fn test_checkout_tracks_each_resource_separately() {
+        let pool = TokenPool::new(vec!["a".to_string(), "b".to_string()]);
+        let mut search = quota_headers("1", "2000");
+        search.insert("x-ratelimit-resource", HeaderValue::from_static("search"));
+        let mut core = quota_headers("4000", "5000");
+        core.insert("x-ratelimit-resource", HeaderValue::from_static("core"));
+
+        // The header names the quota, whatever the request was checked out for.
+        pool.record(0, CORE_RESOURCE, &search);
+        pool.record(0, CORE_RESOURCE, &core);
+        assert_eq!(pool.checkout(1000, SEARCH_RESOURCE).unwrap().token, "b");
+        assert!(pool.has_available(1000, CORE_RESOURCE));
+
+        // A low search quota does not rotate tokens with core quota left.
+        let pool = TokenPool::new(vec!["a".to_string(), "b".to_string()]);
+        pool.record(0, CORE_RESOURCE, &search);
+        assert_eq!(pool.checkout(1000, CORE_RESOURCE).unwrap().token, "a");
+
+        let url = |url: &str| url.parse().unwrap();
+        assert_eq!(
+            resource(&url("https://api.github.com/search/repositories?q=x")),
+            SEARCH_RESOURCE
+        );
+        assert_eq!(resource(&url("https://api.github.com/graphql")), "graphql");
+        assert_eq!(
+            resource(&url("https://api.github.com/repos/a/b/releases")),
+            CORE_RESOURCE
+        );
+    }
+
+    #[test]
+    fn test_checkout_when_all_exhausted_picks_earliest_reset() {
+        let pool = TokenPool::new(vec!["a".to_string(), "b".to_string()]);
+        pool.mark_exhausted(0, CORE_RESOURCE, 5000);
+        pool.mark_exhausted(1, CORE_RESOURCE, 4000);
+        assert!(!pool.has_available(1000, CORE_RESOURCE));
+        assert_eq!(pool.checkout(1000, CORE_RESOURCE).unwrap().token, "b");
+        assert!(
+            TokenPool::new(vec![])
+                .checkout(1000, CORE_RESOURCE)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_read_token_list_from_file_or_string() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let token_file = temp_dir.path().join("tokens.txt");
+        fs::write(&token_file, "ghp_one\n# backup\nghp_two\n")?;
+
+        let from_file = read_token_list(&token_file.to_string_lossy())?;
+        assert_eq!(from_file, ["ghp_one", "ghp_two"]);
+        assert_eq!(read_token_list("ghp_one,ghp_two")?, ["ghp_one", "ghp_two"]);
+        Ok(())
+    }
+}
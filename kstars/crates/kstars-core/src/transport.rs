@@ -0,0 +1,375 @@
+use async_trait::async_trait;
+use reqwest::{
+    Client, ClientBuilder, Method, Request, StatusCode, Url,
+    header::{HeaderMap, HeaderName, HeaderValue},
+};
+use std::{
+    collections::VecDeque,
+    fmt, fs,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Instant,
+};
+use tracing::{Instrument, info, info_span, warn};
+
+/// User-Agent of every request when none is given, as GitHub asks API clients
+/// to identify themselves.
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "kstars/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/luizvbo/kstars)"
+);
+
+/// Parses a "Name: value" header given on the command line.
+pub fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("Expected \"Name: value\", got {:?}", s))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|e| format!("Invalid header name {:?}: {}", name.trim(), e))?;
+    let mut value = HeaderValue::from_str(value.trim())
+        .map_err(|e| format!("Invalid value for header {}: {}", name, e))?;
+    // Headers often carry credentials; keep them out of logs.
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
+/// Client builder sending `user_agent` and `headers` with every request. The
+/// headers come last, so they may override the User-Agent too.
+pub fn client_builder(user_agent: &str, headers: &[(HeaderName, HeaderValue)]) -> ClientBuilder {
+    let mut header_map = HeaderMap::new();
+    for (name, value) in headers {
+        header_map.append(name.clone(), value.clone());
+    }
+    Client::builder()
+        .user_agent(user_agent)
+        .default_headers(header_map)
+}
+
+/// Status, headers and body of a response.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+/// A request that got no response at all.
+#[derive(Debug)]
+pub enum TransportError {
+    /// Timeouts and connection failures, which are worth retrying.
+    Transient(String),
+    /// Any other failure.
+    Failed(String),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Transient(message) | TransportError::Failed(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+/// Sends the requests of the forge clients. The network goes through
+/// [`ReqwestTransport`]; tests swap in a [`ReplayTransport`] to drive the pipeline
+/// against canned responses.
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    async fn send(&self, request: Request) -> Result<HttpResponse, TransportError>;
+}
+
+/// Sends requests over the network with a reqwest client.
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReqwestTransport {
+    async fn send(&self, request: Request) -> Result<HttpResponse, TransportError> {
+        let resp = self.client.execute(request).await.map_err(|e| {
+            if e.is_timeout() || e.is_connect() {
+                TransportError::Transient(e.to_string())
+            } else {
+                TransportError::Failed(e.to_string())
+            }
+        })?;
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let body = resp
+            .text()
+            .await
+            .unwrap_or_else(|_| "Failed to retrieve error message".to_string());
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// Rate-limit headers of GitHub, GitLab and Bitbucket responses worth logging.
+const RATE_LIMIT_HEADERS: [&str; 5] = [
+    "x-ratelimit-remaining",
+    "x-ratelimit-reset",
+    "ratelimit-remaining",
+    "ratelimit-reset",
+    "retry-after",
+];
+
+/// Logs every request sent through another transport with `--debug-http`: a
+/// span with its method and URL, and an event with the status, the time it took
+/// and the rate-limit headers of the response. With a dump folder, the bodies of
+/// failed responses are also written there, one file per response.
+pub struct DebugTransport {
+    inner: Arc<dyn HttpTransport>,
+    dump_dir: Option<PathBuf>,
+    /// Number of the next request, which names its dump.
+    sent: AtomicUsize,
+}
+
+impl DebugTransport {
+    pub fn new(inner: Arc<dyn HttpTransport>) -> Self {
+        Self {
+            inner,
+            dump_dir: None,
+            sent: AtomicUsize::new(0),
+        }
+    }
+
+    /// Writes the bodies of failed responses to `dir`.
+    pub fn with_dump_dir(mut self, dir: PathBuf) -> Self {
+        self.dump_dir = Some(dir);
+        self
+    }
+
+    /// Writes the body of `response` to the dump folder as "<request>-<status>.txt".
+    fn dump(&self, request: usize, described: &str, response: &HttpResponse) {
+        let Some(dir) = &self.dump_dir else {
+            return;
+        };
+        let path = dir.join(format!("{:05}-{}.txt", request, response.status.as_u16()));
+        match fs::create_dir_all(dir).and_then(|_| fs::write(&path, &response.body)) {
+            Ok(()) => info!("Dumped the body of {} to {:?}", described, path),
+            Err(e) => warn!(
+                "Failed to dump the body of {} to {:?}: {}",
+                described, path, e
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for DebugTransport {
+    async fn send(&self, request: Request) -> Result<HttpResponse, TransportError> {
+        let number = self.sent.fetch_add(1, Ordering::Relaxed) + 1;
+        let described = format!("{} {}", request.method(), request.url());
+        let span = info_span!(
+            "http_request",
+            id = number,
+            method = %request.method(),
+            url = %request.url()
+        );
+        async {
+            let started = Instant::now();
+            let result = self.inner.send(request).await;
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+            match &result {
+                Ok(response) => {
+                    let rate_limits = RATE_LIMIT_HEADERS
+                        .iter()
+                        .filter_map(|name| {
+                            let value = response.headers.get(*name)?.to_str().ok()?;
+                            Some(format!("{}={}", name, value))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    info!(
+                        status = response.status.as_u16(),
+                        elapsed_ms,
+                        bytes = response.body.len(),
+                        rate_limits = %rate_limits,
+                        "HTTP response"
+                    );
+                    if !response.status.is_success() {
+                        self.dump(number, &described, response);
+                    }
+                }
+                Err(e) => warn!(elapsed_ms, error = %e, "HTTP request failed"),
+            }
+            result
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+/// Responses queued for the requests matching a method and URL.
+struct Route {
+    method: Method,
+    url: Url,
+    responses: VecDeque<HttpResponse>,
+}
+
+impl Route {
+    /// Same method, scheme, host and path, and every query pair of the route is in
+    /// the request. A route without a query matches any query.
+    fn matches(&self, request: &Request) -> bool {
+        let url = request.url();
+        self.method == request.method()
+            && (self.url.scheme(), self.url.host_str(), self.url.path())
+                == (url.scheme(), url.host_str(), url.path())
+            && self
+                .url
+                .query_pairs()
+                .all(|pair| url.query_pairs().any(|other| other == pair))
+    }
+}
+
+/// Answers requests with canned responses instead of the network. Responses of a
+/// route are served in the order they were added and the last one repeats; the
+/// first route matching a request wins.
+#[derive(Default)]
+pub struct ReplayTransport {
+    routes: Mutex<Vec<Route>>,
+    /// Method and URL of every request sent, in order.
+    requests: Mutex<Vec<String>>,
+}
+
+impl ReplayTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a response for the requests matching `method` and `url`.
+    pub fn with(
+        self,
+        method: Method,
+        url: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> Self {
+        let url = Url::parse(url).expect("replayed URLs are valid");
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            header_map.insert(
+                HeaderName::from_bytes(name.as_bytes()).expect("valid header name"),
+                HeaderValue::from_str(value).expect("valid header value"),
+            );
+        }
+        let response = HttpResponse {
+            status: StatusCode::from_u16(status).expect("valid status"),
+            headers: header_map,
+            body: body.to_string(),
+        };
+        {
+            let mut routes = self.routes.lock().expect("replay lock is never poisoned");
+            match routes
+                .iter_mut()
+                .find(|route| route.method == method && route.url == url)
+            {
+                Some(route) => route.responses.push_back(response),
+                None => routes.push(Route {
+                    method,
+                    url,
+                    responses: VecDeque::from([response]),
+                }),
+            }
+        }
+        self
+    }
+
+    /// Method and URL of every request sent so far, e.g. "GET https://…".
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .expect("replay lock is never poisoned")
+            .clone()
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReplayTransport {
+    async fn send(&self, request: Request) -> Result<HttpResponse, TransportError> {
+        let described = format!("{} {}", request.method(), request.url());
+        self.requests
+            .lock()
+            .expect("replay lock is never poisoned")
+            .push(described.clone());
+        let mut routes = self.routes.lock().expect("replay lock is never poisoned");
+        let route = routes
+            .iter_mut()
+            .find(|route| route.matches(&request))
+            .ok_or_else(|| TransportError::Failed(format!("No response for {}", described)))?;
+        Ok(match route.responses.len() {
+            1 => route.responses[0].clone(),
+            _ => route.responses.pop_front().expect("routes keep a response"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DebugTransport, HttpTransport, ReplayTransport, parse_header};
+    use reqwest::{Client, Method};
+    use std::{fs, sync::Arc};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_debug_transport_dumps_failed_responses() {
+        let dump_dir = tempdir().unwrap();
+        let replay = ReplayTransport::new()
+            .with(Method::GET, "https://api.example/ok", 200, &[], "fine")
+            .with(
+                Method::GET,
+                "https://api.example/broken",
+                502,
+                &[("x-ratelimit-remaining", "41")],
+                "<html>Bad gateway</html>",
+            );
+        let transport =
+            DebugTransport::new(Arc::new(replay)).with_dump_dir(dump_dir.path().to_path_buf());
+        let client = Client::new();
+
+        for url in ["https://api.example/ok", "https://api.example/broken"] {
+            transport
+                .send(client.get(url).build().unwrap())
+                .await
+                .unwrap();
+        }
+
+        let dumps: Vec<_> = fs::read_dir(dump_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(dumps, ["00002-502.txt"]);
+        assert_eq!(
+            fs::read_to_string(dump_dir.path().join("00002-502.txt")).unwrap(),
+            "<html>Bad gateway</html>"
+        );
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let (name, value) = parse_header("X-Proxy-Auth:  secret value ").unwrap();
+        assert_eq!(name.as_str(), "x-proxy-auth");
+        assert_eq!(value.to_str().unwrap(), "secret value");
+        assert!(parse_header("X-Proxy-Auth").is_err());
+        assert!(parse_header("Bad Name: value").is_err());
+        assert!(parse_header("X-Line: a\nb").is_err());
+    }
+}
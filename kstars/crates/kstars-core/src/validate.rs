@@ -0,0 +1,213 @@
+use crate::{
+    CSV_HEADER,
+    columns::Column,
+    delimited,
+    error::{Result, bail},
+};
+use chrono::{DateTime, NaiveDate};
+use csv::{ErrorKind, StringRecord};
+use reqwest::Url;
+use std::path::Path;
+use tracing::{error, info};
+
+/// Checks the header and rows of one result file and describes every problem.
+/// Enrichment columns are optional.
+pub fn validate_file(path: &Path, delimiter: u8) -> Result<Vec<String>> {
+    let mut reader = delimited::reader(path, delimiter)?;
+    let headers = reader.headers()?.clone();
+    let mut problems = Vec::new();
+    for expected in CSV_HEADER {
+        if !headers.iter().any(|h| h == expected) {
+            problems.push(format!("missing column \"{}\"", expected));
+        }
+    }
+    let mut columns = Vec::new();
+    for found in &headers {
+        match Column::ALL.iter().find(|c| c.header() == found) {
+            Some(column) => columns.push(Some(*column)),
+            None => {
+                problems.push(format!("unexpected column \"{}\"", found));
+                columns.push(None);
+            }
+        }
+    }
+    for (index, record) in reader.records().enumerate() {
+        let row = index + 1;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                match e.kind() {
+                    ErrorKind::UnequalLengths { len, .. } => problems.push(format!(
+                        "row {}: {} field(s) where the header has {}",
+                        row,
+                        len,
+                        headers.len()
+                    )),
+                    _ => problems.push(format!("row {}: {}", row, e)),
+                }
+                continue;
+            }
+        };
+        problems.extend(
+            check_row(row, &columns, &record)
+                .into_iter()
+                .map(|problem| format!("row {}: {}", row, problem)),
+        );
+    }
+    Ok(problems)
+}
+
+/// Problems of the cells of the `row`-th record, whose ranking has to be `row`.
+fn check_row(row: usize, columns: &[Option<Column>], record: &StringRecord) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (column, cell) in columns.iter().zip(record) {
+        let Some(column) = column else { continue };
+        let problem = match column {
+            Column::Ranking => match cell.parse::<usize>() {
+                Ok(ranking) if ranking == row => None,
+                Ok(ranking) => Some(format!("ranking {} where {} was expected", ranking, row)),
+                Err(_) => Some("ranking is not a number".to_string()),
+            },
+            Column::Stars
+            | Column::Forks
+            | Column::Watchers
+            | Column::OpenIssues
+            | Column::SizeKb => cell
+                .parse::<u64>()
+                .err()
+                .map(|_| "is not a number".to_string()),
+            Column::Commits52w
+            | Column::ReleaseCount
+            | Column::Subscribers
+            | Column::ClosedIssues
+            | Column::OpenPullRequests
+            | Column::MergedPullRequests
+                if !cell.is_empty() =>
+            {
+                cell.parse::<u64>()
+                    .err()
+                    .map(|_| "is not a number".to_string())
+            }
+            Column::IssueCloseRate if !cell.is_empty() => match cell.parse::<f64>() {
+                Ok(rate) if (0.0..=1.0).contains(&rate) => None,
+                _ => Some("is not a rate from 0 to 1".to_string()),
+            },
+            Column::CreatedAt | Column::LastCommit => {
+                (!is_timestamp(cell)).then(|| "is not a timestamp".to_string())
+            }
+            Column::ReleaseDate if !cell.is_empty() => {
+                (!is_timestamp(cell)).then(|| "is not a timestamp".to_string())
+            }
+            Column::Archived | Column::Fork => {
+                (!matches!(cell, "true" | "false")).then(|| "is not true or false".to_string())
+            }
+            Column::Sponsorable | Column::SecurityPolicy | Column::Ci if !cell.is_empty() => {
+                (!matches!(cell, "true" | "false")).then(|| "is not true or false".to_string())
+            }
+            Column::RepoUrl => (!is_web_url(cell)).then(|| "is not a URL".to_string()),
+            Column::OwnerAvatar if !cell.is_empty() => {
+                (!is_web_url(cell)).then(|| "is not a URL".to_string())
+            }
+            Column::OwnerType if !cell.is_empty() => (!matches!(cell, "organization" | "user"))
+                .then(|| "is not organization or user".to_string()),
+            Column::ProjectName => cell.is_empty().then(|| "is empty".to_string()),
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            problems.push(format!("\"{}\" {}: {:?}", column.header(), problem, cell));
+        }
+    }
+    problems
+}
+
+/// RFC 3339 timestamps, as the forges return them, or plain dates.
+fn is_timestamp(cell: &str) -> bool {
+    DateTime::parse_from_rfc3339(cell).is_ok()
+        || NaiveDate::parse_from_str(cell, "%Y-%m-%d").is_ok()
+}
+
+/// Absolute http(s) URLs with a host and a path below it.
+fn is_web_url(cell: &str) -> bool {
+    Url::parse(cell).is_ok_and(|url| {
+        matches!(url.scheme(), "http" | "https")
+            && url.host_str().is_some()
+            && !url.path().trim_matches('/').is_empty()
+    })
+}
+
+/// Validates every result file of `dir` and fails if any of them has problems.
+pub fn run(dir: &Path, delimiter: u8) -> Result<()> {
+    let files = delimited::result_files(dir, delimiter)?;
+    let mut invalid = 0;
+    for path in &files {
+        let problems = validate_file(path, delimiter)?;
+        for problem in &problems {
+            error!("{}: {}", path.display(), problem);
+        }
+        if !problems.is_empty() {
+            invalid += 1;
+        }
+    }
+    if invalid > 0 {
+        bail!("{} of {} file(s) failed validation.", invalid, files.len());
+    }
+    info!("All {} file(s) are valid.", files.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_file;
+    use crate::CSV_HEADER;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_validate_file_reports_header_mismatches() {
+        let temp_dir = tempdir().unwrap();
+        let valid = temp_dir.path().join("valid.csv");
+        fs::write(&valid, format!("{}\n", CSV_HEADER.join(","))).unwrap();
+        assert!(validate_file(&valid, b',').unwrap().is_empty());
+
+        let invalid = temp_dir.path().join("invalid.csv");
+        let header = CSV_HEADER.join(",").replace("Stars", "Stargazers");
+        fs::write(&invalid, format!("{}\n", header)).unwrap();
+        assert_eq!(
+            validate_file(&invalid, b',').unwrap(),
+            [
+                "missing column \"Stars\"",
+                "unexpected column \"Stargazers\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_file_reports_row_problems() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("rows.csv");
+        let row = |ranking: &str, stars: &str, url: &str, owner_type: &str, archived: &str| {
+            format!(
+                "{ranking},demo,{stars},1,2,3,2020-01-01T00:00:00Z,2024-05-01T12:00:00Z,42,,Rust,{url},MIT,,octo,https://avatars.githubusercontent.com/u/1,{owner_type},{archived},false,main"
+            )
+        };
+        let content = [
+            CSV_HEADER.join(","),
+            row("1", "100", "https://github.com/octo/demo", "user", "false"),
+            row("3", "lots", "github.com/octo/demo", "team", "maybe"),
+            "4,short".to_string(),
+        ]
+        .join("\n");
+        fs::write(&path, content).unwrap();
+        assert_eq!(
+            validate_file(&path, b',').unwrap(),
+            [
+                "row 2: \"Ranking\" ranking 3 where 2 was expected: \"3\"",
+                "row 2: \"Stars\" is not a number: \"lots\"",
+                "row 2: \"Repo URL\" is not a URL: \"github.com/octo/demo\"",
+                "row 2: \"Owner Type\" is not organization or user: \"team\"",
+                "row 2: \"Archived\" is not true or false: \"maybe\"",
+                "row 3: 2 field(s) where the header has 20",
+            ]
+        );
+    }
+}
@@ -60,21 +60,6 @@ LANGUAGES = {
 }
 
 
-def human_readable_size(size_kb: int) -> str:
-    """Converts file size in KB to a human-readable format."""
-    if size_kb < 1024:
-        return f"{size_kb:.2f} KB"
-    elif size_kb < (1024 * 1024):
-        size_mb = size_kb / 1024
-        return f"{size_mb:.2f} MB"
-    elif size_kb < (1024 * 1024 * 1024):
-        size_gb = size_kb / (1024 * 1024)
-        return f"{size_gb:.2f} GB"
-    else:
-        size_tb = size_kb / (1024 * 1024 * 1024)
-        return f"{size_tb:.2f} TB"
-
-
 def generate_readme(
     languages: dict[str, str], lang_folder: str | Path, readme_path: Path | str
 ):
@@ -122,44 +107,6 @@ Below, you'll find a fallback representation of the top 10 repositories for each
         raise e
 
 
-def preprocess_data(lang_name: str, input_folder: Path, output_folder: Path):
-    fname = f"{lang_name}.csv"
-    input_file_path = Path(input_folder) / fname
-    output_file_path = Path(output_folder) / fname
-    output_top10_file_path = Path(output_folder) / f"top10_{fname}"
-
-    try:
-        df: pd.DataFrame = pd.read_csv(input_file_path)
-        for col in ("Last Commit", "Created At"):
-            if col in df.columns:
-                df[col] = df[col].apply(pd.to_datetime).dt.strftime("%d/%m/%Y")
-        
-        if "Size (KB)" in df.columns:
-            df["Size"] = df["Size (KB)"].apply(human_readable_size)
-            # Reorder columns to put Size where Size (KB) was
-            cols = df.columns.tolist()
-            idx = cols.index("Size (KB)")
-            new_columns = cols[:idx] + ["Size"] + cols[idx+1:]
-            # Remove Size (KB) from list if it's still there (it is in cols, not new_columns)
-            if "Size (KB)" in new_columns: 
-                new_columns.remove("Size (KB)")
-            
-            df = df[new_columns]
-
-        df.to_csv(output_file_path, index=False)
-        df.head(10).to_csv(output_top10_file_path, index=False)
-        logger.info(
-            f"Stored processed files for {lang_name}"
-        )
-
-    except FileNotFoundError:
-        logger.error(f"Error: Input file not found at '{input_file_path}'")
-        raise
-    except Exception as e:
-        logger.error(f"Error processing data for {lang_name}: {e}")
-        raise
-
-
 def run_kstars_task(
     language: str, lang_name: str, output_folder: str | Path
 ) -> None:
@@ -177,7 +124,7 @@ def run_kstars_task(
         logger.error(f"Access token not found at {token_path}")
         raise FileNotFoundError("Access token file missing")
 
-    command = f'kstars -t $(cat "{token_path}") -l "{language}:{lang_name}" -o "{output_folder}"'
+    command = f'kstars fetch -t $(cat "{token_path}") -l "{language}:{lang_name}" -o "{output_folder}"'
     
     attempt = 1
     wait_time_seconds = 300  # 5 minutes wait time for API reset
@@ -224,8 +171,15 @@ def run_post_processing(languages: dict[str, str], output_folder: str | Path):
     path_data_processed = Path(output_folder) / "processed"
     path_data_processed.mkdir(parents=True, exist_ok=True)
 
-    for lang_name in languages.keys():
-        preprocess_data(lang_name, path_data_original, path_data_processed)
+    # Dates, sizes, the top10_ previews and manifest.json are handled by `kstars process`.
+    display_names = ",".join(f"{safe}:{display}" for safe, display in languages.items())
+    command = [
+        "kstars", "process",
+        "-i", str(path_data_original),
+        "-o", str(path_data_processed),
+        "--display-names", display_names,
+    ]
+    subprocess.run(command, check=True)
 
     generate_readme(LANGUAGES, path_data_processed, README_PATH)
     logger.info("Post Processing completed successfully.")
@@ -0,0 +1,19 @@
+//! Former name of the `kstars` binary, kept so existing scripts keep working. It
+//! runs the `kstars` binary installed next to it with the same arguments.
+
+use std::{env, process::Command};
+
+fn main() {
+    eprintln!("data_loader is deprecated; run `kstars` instead.");
+    let kstars = env::current_exe()
+        .map(|exe| exe.with_file_name(format!("kstars{}", env::consts::EXE_SUFFIX)))
+        .unwrap_or_else(|_| "kstars".into());
+    let status = Command::new(&kstars)
+        .args(env::args_os().skip(1))
+        .status()
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to run {}: {}", kstars.display(), e);
+            std::process::exit(1);
+        });
+    std::process::exit(status.code().unwrap_or(1));
+}
@@ -0,0 +1,160 @@
+use crate::Provider;
+use anyhow::{Context, Result};
+use kstars_core::{
+    OutputFormat, bitbucket::BitbucketRankBy, columns::Column, delimited::parse_delimiter,
+    enrich::Enrichment, github::ApiBackend, health::HealthWeights,
+};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// File name looked up in the current directory and in `$XDG_CONFIG_HOME/kstars/`.
+pub const CONFIG_FILE_NAME: &str = "kstars.toml";
+
+/// Settings read from `kstars.toml`. Every key is optional and mirrors a command line
+/// flag; flags given on the command line or through the environment take precedence.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Languages as "api_name:display_name" entries.
+    pub languages: Option<Vec<String>>,
+    pub records: Option<u32>,
+    pub output: Option<String>,
+    pub concurrency: Option<u32>,
+    pub provider: Option<Provider>,
+    /// Output formats, e.g. `["csv", "json"]`.
+    pub format: Option<Vec<OutputFormat>>,
+    /// CSV columns in order, e.g. `["ranking", "project_name", "stars"]`.
+    pub columns: Option<Vec<Column>>,
+    /// Enrichments, e.g. `["releases"]`.
+    pub enrich: Option<Vec<Enrichment>>,
+    /// CSV field delimiter: a single character or "tab".
+    #[serde(default, deserialize_with = "deserialize_delimiter")]
+    pub delimiter: Option<u8>,
+    pub github: GithubConfig,
+    pub gitlab: GitlabConfig,
+    pub bitbucket: BitbucketConfig,
+    /// Weights of the health score written by `process`.
+    pub health: HealthWeights,
+}
+
+fn deserialize_delimiter<'de, D>(deserializer: D) -> Result<Option<u8>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_delimiter(&value)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// `[github]` table.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct GithubConfig {
+    /// Token string or path to a token file.
+    pub token: Option<String>,
+    pub api: Option<ApiBackend>,
+    pub api_base_url: Option<String>,
+    pub app_id: Option<String>,
+    pub private_key: Option<String>,
+    pub installation_id: Option<u64>,
+}
+
+/// `[gitlab]` table.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitlabConfig {
+    pub token: Option<String>,
+}
+
+/// `[bitbucket]` table.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BitbucketConfig {
+    pub token: Option<String>,
+    pub rank_by: Option<BitbucketRankBy>,
+}
+
+/// Finds the configuration file to use: `explicit` if given, otherwise `kstars.toml`
+/// in the current directory, then in the user's config directory.
+pub fn discover(explicit: Option<&Path>) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(path.to_path_buf());
+    }
+    let local = PathBuf::from(CONFIG_FILE_NAME);
+    if local.is_file() {
+        return Some(local);
+    }
+    dirs::config_dir()
+        .map(|dir| dir.join("kstars").join(CONFIG_FILE_NAME))
+        .filter(|path| path.is_file())
+}
+
+/// Reads and parses a configuration file.
+pub fn load(path: &Path) -> Result<Config> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {:?}", path))?;
+    let config: Config =
+        toml::from_str(&content).with_context(|| format!("Invalid config file: {:?}", path))?;
+    if config.concurrency == Some(0) {
+        anyhow::bail!(
+            "Invalid config file {:?}: concurrency must be at least 1",
+            path
+        );
+    }
+    config
+        .health
+        .check()
+        .with_context(|| format!("Invalid config file {:?}", path))?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::Provider;
+    use kstars_core::{bitbucket::BitbucketRankBy, columns::Column, github::ApiBackend};
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(
+            r#"
+            languages = ["Rust", "CPP:C++"]
+            records = 200
+            provider = "github"
+            delimiter = "tab"
+            columns = ["repo_url", "stars"]
+
+            [github]
+            api = "graphql"
+            api_base_url = "https://ghe.example.com/api/v3"
+
+            [bitbucket]
+            rank_by = "forks"
+
+            [health]
+            recency = 2.5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.languages.unwrap(), ["Rust", "CPP:C++"]);
+        assert_eq!(config.records, Some(200));
+        assert_eq!(config.provider, Some(Provider::Github));
+        assert_eq!(config.github.api, Some(ApiBackend::Graphql));
+        assert_eq!(config.bitbucket.rank_by, Some(BitbucketRankBy::Forks));
+        assert_eq!(config.delimiter, Some(b'\t'));
+        assert_eq!(config.columns.unwrap(), [Column::RepoUrl, Column::Stars]);
+        assert!(config.output.is_none());
+        assert_eq!(config.health.recency, 2.5);
+        assert_eq!(config.health.velocity, 1.0);
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("record = 10").is_err());
+    }
+}
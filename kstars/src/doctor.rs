@@ -0,0 +1,279 @@
+use crate::config;
+use kstars_core::{
+    KstarsError, cache, lock::OutputLock, quota, token_pool::get_access_tokens,
+    transport::HttpTransport,
+};
+use reqwest::Client;
+use std::{fmt, fs, path::Path};
+
+/// Outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warning,
+    Failed,
+}
+
+/// Result of one check, with what to do about it when it did not pass.
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn problem(
+        status: Status,
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.status {
+            Status::Ok => "[ok]  ",
+            Status::Warning => "[warn]",
+            Status::Failed => "[fail]",
+        };
+        write!(f, "{} {}: {}", label, self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n       Fix: {}", fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// What `kstars doctor` checks.
+pub struct Setup<'a> {
+    /// `--config`, if given.
+    pub config: Option<&'a Path>,
+    pub api_base_url: &'a str,
+    /// Tokens as given to a fetch; `None` falls back to the environment and
+    /// `kstars login`.
+    pub token: Option<String>,
+    /// Whether the forge to fetch from needs a GitHub token.
+    pub needs_token: bool,
+    pub output: &'a Path,
+}
+
+/// Validates the configuration file, if any.
+fn check_config(explicit: Option<&Path>) -> Check {
+    let name = "Config file";
+    match config::discover(explicit) {
+        None => Check::ok(name, "none found; using the defaults"),
+        Some(path) => match config::load(&path) {
+            Ok(_) => Check::ok(name, format!("{} is valid", path.display())),
+            Err(e) => Check::problem(
+                Status::Failed,
+                name,
+                format!("{:#}", e),
+                format!(
+                    "correct {}; every key mirrors a command line flag",
+                    path.display()
+                ),
+            ),
+        },
+    }
+}
+
+/// Checks that the API answers at all, without credentials.
+async fn check_connectivity(client: &Client, transport: &dyn HttpTransport, url: &str) -> Check {
+    let name = "API connectivity";
+    let request = match client.get(url).build() {
+        Ok(request) => request,
+        Err(e) => {
+            return Check::problem(
+                Status::Failed,
+                name,
+                format!("{} is not a valid URL: {}", url, e),
+                "pass a URL such as https://ghe.example.com/api/v3 to --api-base-url",
+            );
+        }
+    };
+    match transport.send(request).await {
+        Ok(resp) => Check::ok(name, format!("{} answered {}", url, resp.status)),
+        Err(e) => Check::problem(
+            Status::Failed,
+            name,
+            format!("{} is unreachable: {}", url, e),
+            "check the network and proxy settings, and --api-base-url for GitHub Enterprise Server",
+        ),
+    }
+}
+
+/// Checks every token: accepted by GitHub, its scopes and remaining quota.
+async fn check_tokens(
+    client: &Client,
+    transport: &dyn HttpTransport,
+    setup: &Setup<'_>,
+) -> Vec<Check> {
+    let tokens = match get_access_tokens(setup.token.clone()) {
+        Ok(tokens) => tokens,
+        Err(_) if !setup.needs_token => {
+            return vec![Check::ok(
+                "GitHub token",
+                "none given; not needed for this provider",
+            )];
+        }
+        Err(e) => {
+            return vec![Check::problem(
+                Status::Failed,
+                "GitHub token",
+                e.to_string(),
+                "pass --token, set GITHUB_TOKEN or run `kstars login`",
+            )];
+        }
+    };
+    let mut checks = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let name = format!("GitHub token #{}", i + 1);
+        let status = match quota::token_status(client, transport, setup.api_base_url, token).await {
+            Ok(status) => status,
+            Err(KstarsError::AuthFailed(message)) => {
+                checks.push(Check::problem(
+                    Status::Failed,
+                    name,
+                    format!("rejected: {}", message.trim()),
+                    "the token expired or was revoked; create a new one or run `kstars login`",
+                ));
+                continue;
+            }
+            Err(e) => {
+                checks.push(Check::problem(
+                    Status::Failed,
+                    name,
+                    format!("{:#}", e),
+                    "run `kstars rate-limit --debug-http` to see the response",
+                ));
+                continue;
+            }
+        };
+        let scopes = match &status.scopes {
+            Some(scopes) if scopes.is_empty() => "valid, no scopes".to_string(),
+            Some(scopes) => format!("valid, scopes: {}", scopes.join(", ")),
+            None => "valid (fine-grained or app token)".to_string(),
+        };
+        match status.limits.as_ref().and_then(|limits| limits.search) {
+            Some(search) if search.remaining == 0 => checks.push(Check::problem(
+                Status::Warning,
+                name,
+                format!("{}; search quota used up", scopes),
+                "wait for the reset shown by `kstars rate-limit`, or add another token",
+            )),
+            _ if status.scopes.as_ref().is_some_and(|s| !s.is_empty()) => {
+                checks.push(Check::problem(
+                    Status::Warning,
+                    name,
+                    scopes,
+                    "kstars only reads public data; a token without scopes is safer",
+                ))
+            }
+            _ => checks.push(Check::ok(name, scopes)),
+        }
+    }
+    checks
+}
+
+/// Checks that `dir` can be created and written to by writing a file into it.
+fn check_writable(name: &str, dir: &Path) -> Check {
+    let probe = dir.join(".kstars-doctor");
+    let written = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .and_then(|_| fs::remove_file(&probe));
+    match written {
+        Ok(()) => Check::ok(name, format!("{} is writable", dir.display())),
+        Err(e) => Check::problem(
+            Status::Failed,
+            name,
+            format!("cannot write to {}: {}", dir.display(), e),
+            "fix the permissions of the folder or pick another one with --output",
+        ),
+    }
+}
+
+/// Checks that no other run holds the output folder.
+fn check_lock(dir: &Path) -> Check {
+    let name = "Output folder lock";
+    match OutputLock::acquire(dir) {
+        Ok(_) => Check::ok(name, "no other run is using it"),
+        Err(KstarsError::Locked { holder, .. }) => Check::problem(
+            Status::Warning,
+            name,
+            format!("held by {}", holder),
+            "wait for that run to finish, or pass --wait to queue behind it",
+        ),
+        Err(e) => Check::problem(
+            Status::Failed,
+            name,
+            format!("{:#}", e),
+            "fix the permissions of the folder or pick another one with --output",
+        ),
+    }
+}
+
+/// Runs every check in order.
+pub async fn run(client: &Client, transport: &dyn HttpTransport, setup: &Setup<'_>) -> Vec<Check> {
+    let mut checks = vec![
+        check_config(setup.config),
+        check_connectivity(client, transport, setup.api_base_url).await,
+    ];
+    checks.extend(check_tokens(client, transport, setup).await);
+    let cache_dir = setup.output.join(cache::CACHE_DIR_NAME);
+    checks.push(check_writable("Output folder", setup.output));
+    checks.push(check_writable("Cache folder", &cache_dir));
+    if checks
+        .last()
+        .is_some_and(|check| check.status == Status::Ok)
+    {
+        checks.push(check_lock(setup.output));
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Status, check_config, check_lock, check_writable};
+    use kstars_core::lock::OutputLock;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_local_checks_report_fixes() {
+        let dir = tempdir().unwrap();
+        let config = dir.path().join("kstars.toml");
+        fs::write(&config, "records = \"many\"\n").unwrap();
+        let check = check_config(Some(&config));
+        assert_eq!(check.status, Status::Failed);
+        assert!(check.fix.is_some());
+        fs::write(&config, "records = 10\n").unwrap();
+        assert_eq!(check_config(Some(&config)).status, Status::Ok);
+
+        let output = dir.path().join("results");
+        assert_eq!(check_writable("Output folder", &output).status, Status::Ok);
+        assert!(fs::read_dir(&output).unwrap().next().is_none());
+        let _lock = OutputLock::acquire(&output).unwrap();
+        let check = check_lock(&output);
+        assert_eq!(check.status, Status::Warning);
+        assert!(check.to_string().contains("Fix: "));
+    }
+}